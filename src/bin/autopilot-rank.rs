@@ -0,0 +1,118 @@
+//! Offline ranking of captured control-plane payloads.
+//!
+//! ```text
+//! autopilot-rank --utilization <utilization.json> [--models <models.json>] [--format table|json]
+//! ```
+//!
+//! Runs the same pipeline as the live candidate refresh, so the output can be used to explain a
+//! production selection from captured payloads or diffed across scoring changes.
+
+use std::fs;
+
+use serde_json::Value;
+
+const USAGE: &str = "usage: autopilot-rank --utilization <utilization.json> [--models <models.json>] [--format table|json]";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Table,
+    Json,
+}
+
+struct Args {
+    utilization: String,
+    models: Option<String>,
+    format: Format,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> anyhow::Result<Args> {
+    let mut utilization = None;
+    let mut models = None;
+    let mut format = Format::Table;
+
+    while let Some(flag) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| anyhow::anyhow!("{flag} requires a value\n{USAGE}"))
+        };
+        match flag.as_str() {
+            "--utilization" => utilization = Some(value()?),
+            "--models" => models = Some(value()?),
+            "--format" => {
+                format = match value()?.as_str() {
+                    "table" => Format::Table,
+                    "json" => Format::Json,
+                    other => return Err(anyhow::anyhow!("unknown format {other:?}\n{USAGE}")),
+                }
+            }
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                std::process::exit(0);
+            }
+            other => return Err(anyhow::anyhow!("unknown argument {other:?}\n{USAGE}")),
+        }
+    }
+
+    let utilization =
+        utilization.ok_or_else(|| anyhow::anyhow!("--utilization is required\n{USAGE}"))?;
+    Ok(Args {
+        utilization,
+        models,
+        format,
+    })
+}
+
+fn read(path: &str) -> anyhow::Result<Vec<u8>> {
+    fs::read(path).map_err(|e| anyhow::anyhow!("failed to read {path:?}: {e}"))
+}
+
+fn num(v: &Value, key: &str) -> f64 {
+    v[key].as_f64().unwrap_or(f64::NAN)
+}
+
+fn print_table(report: &Value) {
+    println!(
+        "{:>4}  {:>9}  {:>9}  {:>6}  {:>8}  {:>6}  {:>8}  {:>9}  model",
+        "pos", "score", "free_cap", "util", "throttle", "scale", "scaling", "instances"
+    );
+    for c in report["ranked"].as_array().into_iter().flatten() {
+        println!(
+            "{:>4}  {:>9.4}  {:>9.4}  {:>6.3}  {:>8.3}  {:>6.3}  {:>8.3}  {:>9}  {}",
+            c["position"].as_u64().unwrap_or_default(),
+            num(c, "score"),
+            num(c, "free_capacity"),
+            num(c, "util"),
+            num(c, "throttle_signal"),
+            num(c, "scale_bonus"),
+            num(c, "scaling_adjustment"),
+            c["record"]["active_instance_count"]
+                .as_u64()
+                .unwrap_or_default(),
+            c["record"]["name"].as_str().unwrap_or_default(),
+        );
+    }
+
+    let excluded = report["excluded"].as_array().map_or(&[][..], Vec::as_slice);
+    println!();
+    println!("excluded ({}):", excluded.len());
+    for e in excluded {
+        println!(
+            "  {:<16}  {}",
+            e["reason"].as_str().unwrap_or_default(),
+            e["record"]["name"].as_str().unwrap_or_default()
+        );
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let args = parse_args(std::env::args().skip(1))?;
+    let utilization = read(&args.utilization)?;
+    let models = args.models.as_deref().map(read).transpose()?;
+
+    let report = chutes_autopilot::explain_captured_ranking(&utilization, models.as_deref())?;
+    match args.format {
+        Format::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+        Format::Table => print_table(&report),
+    }
+    Ok(())
+}
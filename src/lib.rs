@@ -1,9 +1,12 @@
 use std::collections::hash_map::DefaultHasher;
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::future::Future;
 use std::hash::{Hash, Hasher};
 use std::net::{IpAddr, SocketAddr};
-use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use axum::body::{Body, Bytes};
 use axum::extract::rejection::{BytesRejection, FailedToBufferBody};
@@ -11,15 +14,18 @@ use axum::extract::{ConnectInfo, DefaultBodyLimit, State};
 use axum::http::header::HeaderName;
 use axum::http::{HeaderMap, HeaderValue, StatusCode};
 use axum::response::{IntoResponse, Response};
-use axum::routing::{get, post};
+use axum::routing::{delete, get, post};
 use axum::{Json, Router};
 use futures_util::{stream, StreamExt};
 use ipnet::IpNet;
-use prometheus::{Encoder, IntCounterVec, IntGauge, Opts, Registry, TextEncoder};
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts,
+    Registry, TextEncoder,
+};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
-use tokio::sync::RwLock;
+use tokio::sync::{oneshot, RwLock, Semaphore};
 use uuid::Uuid;
 
 #[derive(Clone)]
@@ -28,6 +34,10 @@ pub struct AppState {
     config: AppConfig,
     http_client: Client,
     metrics: Arc<Metrics>,
+    budgets: Arc<BudgetLedger>,
+    header_latency: Arc<Mutex<LatencyWindow>>,
+    model_header_latency: Arc<Mutex<HashMap<String, Duration>>>,
+    mirror_slots: Arc<Semaphore>,
 }
 
 #[derive(Clone, Debug)]
@@ -49,6 +59,316 @@ pub struct AppConfig {
     pub sticky_max_entries: usize,
     pub trust_proxy_headers: bool,
     pub trusted_proxy_cidrs: Vec<IpNet>,
+    pub client_budgets: Vec<ClientBudget>,
+    pub client_budget_state_path: Option<PathBuf>,
+    pub hedge_enabled: bool,
+    pub hedge_delay: Duration,
+    pub max_upstream_attempts: usize,
+    pub upstream_retry_budget: Duration,
+    pub retryable_statuses: RetryableStatuses,
+    pub retry_after_max_cooldown: Duration,
+    pub normalize_upstream_errors: bool,
+    pub admin_token: Option<AdminToken>,
+    pub leader_score_margin: f64,
+    pub leader_min_dwell: Duration,
+    pub selection_mode: SelectionMode,
+    pub selection_top_k: usize,
+    pub utilization_schema_mode: SchemaMode,
+    pub utilization_max_drift_pct: f64,
+    pub control_plane_max_backoff: Duration,
+    pub backends: Vec<BackendConfig>,
+    pub model_redirects: Vec<ModelRedirect>,
+    /// Accept model ids that differ from a known id only in case.
+    pub case_insensitive_models: bool,
+    pub mirror_rules: Vec<MirrorRule>,
+    /// JSONL file receiving one line per shadow request, with both response bodies.
+    pub mirror_log_path: Option<PathBuf>,
+    pub mirror_timeout: Duration,
+    pub attestation_enabled: bool,
+    /// Evidence endpoint; `{chute_id}` is replaced per chute.
+    pub attestation_evidence_url: String,
+    pub attestation_refresh: Duration,
+    pub require_attested: bool,
+    pub sanitize_sampling_params: bool,
+    pub clamp_max_tokens: bool,
+}
+
+/// Name of the primary backend configured via `BACKEND_BASE_URL`, `MODELS_URL` and
+/// `UTILIZATION_URL`.
+pub const PRIMARY_BACKEND: &str = "chutes";
+
+/// An additional OpenAI-compatible upstream, tried after the primary backend's candidates.
+#[derive(Clone, PartialEq, Eq, Deserialize)]
+pub struct BackendConfig {
+    pub name: String,
+    pub base_url: String,
+    /// Sent as `Authorization: Bearer <api_key>`. Without it the client's `Authorization` header
+    /// is dropped rather than forwarded to a third party.
+    #[serde(default)]
+    pub api_key: Option<String>,
+    /// Defaults to `${base_url}/v1/models`.
+    #[serde(default)]
+    pub models_url: Option<String>,
+    /// Without a utilization source, candidates follow the models catalog order.
+    #[serde(default)]
+    pub utilization_url: Option<String>,
+    /// Set when the backend accepts `stream_options.include_usage`; only then are streamed
+    /// requests from budgeted keys asked to report usage.
+    #[serde(default)]
+    pub stream_usage: bool,
+}
+
+impl std::fmt::Debug for BackendConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BackendConfig")
+            .field("name", &self.name)
+            .field("base_url", &self.base_url)
+            .field("api_key", &self.api_key.as_ref().map(|_| "<redacted>"))
+            .field("models_url", &self.models_url)
+            .field("utilization_url", &self.utilization_url)
+            .field("stream_usage", &self.stream_usage)
+            .finish()
+    }
+}
+
+impl BackendConfig {
+    fn models_url(&self) -> String {
+        self.models_url
+            .clone()
+            .unwrap_or_else(|| format!("{}/v1/models", self.base_url.trim_end_matches('/')))
+    }
+}
+
+/// Maps a retired or renamed model id to its replacement, optionally until a sunset date after
+/// which the old id is rejected.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct ModelRedirect {
+    pub from: String,
+    pub to: String,
+    /// Day the old id was deprecated; `validate_model_redirects` defaults it to the load day.
+    #[serde(default)]
+    pub deprecated: Option<SunsetDate>,
+    #[serde(default)]
+    pub sunset: Option<SunsetDate>,
+}
+
+/// Checks redirects loaded from `MODEL_REDIRECTS_PATH` and fills in missing `deprecated` days
+/// with the UTC day of `now`. Redirects are applied once, so a `to` that is itself a `from`
+/// would never resolve and is rejected, as are empty ids and a duplicate `from`.
+pub fn validate_model_redirects(
+    redirects: &mut [ModelRedirect],
+    now: SystemTime,
+) -> anyhow::Result<()> {
+    let mut sources = HashSet::new();
+    for redirect in redirects.iter() {
+        if redirect.from.trim().is_empty() || redirect.to.trim().is_empty() {
+            return Err(anyhow::anyhow!(
+                "model redirect {:?} must set a non-empty from and to",
+                redirect.from
+            ));
+        }
+        if !sources.insert(redirect.from.as_str()) {
+            return Err(anyhow::anyhow!(
+                "duplicate model redirect from {:?}",
+                redirect.from
+            ));
+        }
+    }
+    if let Some(chained) = redirects.iter().find(|r| sources.contains(r.to.as_str())) {
+        return Err(anyhow::anyhow!(
+            "model redirect {:?} -> {:?} points at another redirected model",
+            chained.from,
+            chained.to
+        ));
+    }
+    for redirect in redirects.iter_mut() {
+        redirect.deprecated.get_or_insert(SunsetDate::on(now));
+    }
+    Ok(())
+}
+
+/// A UTC calendar day, written `YYYY-MM-DD` in config.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SunsetDate {
+    /// Days since 1970-01-01.
+    days: u64,
+}
+
+impl SunsetDate {
+    pub fn parse(raw: &str) -> Option<Self> {
+        let mut parts = raw.trim().splitn(3, '-');
+        let year: i64 = parts.next()?.parse().ok()?;
+        let month: i64 = parts.next()?.parse().ok()?;
+        let day: i64 = parts.next()?.parse().ok()?;
+        if year < 1970 || !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+            return None;
+        }
+        let days = u64::try_from(days_from_civil(year, month, day)).ok()?;
+        // Day 31 of a 30-day month lands in the next month; reject instead of rolling over.
+        (civil_year_month(days) == (year as u64, month as u64)).then_some(Self { days })
+    }
+
+    /// The UTC day containing `now`.
+    fn on(now: SystemTime) -> Self {
+        let days = now
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() / 86_400)
+            .unwrap_or(0);
+        Self { days }
+    }
+
+    fn has_passed(self, now: SystemTime) -> bool {
+        Self::on(now) >= self
+    }
+
+    /// Midnight UTC as Unix seconds, for the `Deprecation` header (RFC 9745), e.g. `@1798675200`.
+    fn unix_secs(self) -> u64 {
+        self.days * 86_400
+    }
+
+    /// IMF-fixdate for the `Sunset` header (RFC 8594), e.g. `Thu, 31 Dec 2026 00:00:00 GMT`.
+    fn http_date(self) -> String {
+        const WEEKDAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+        const MONTHS: [&str; 12] = [
+            "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+        ];
+        let (year, month) = civil_year_month(self.days);
+        let day = self.days as i64 - days_from_civil(year as i64, month as i64, 1) + 1;
+        format!(
+            "{}, {day:02} {} {year} 00:00:00 GMT",
+            WEEKDAYS[(self.days % 7) as usize],
+            MONTHS[(month - 1) as usize]
+        )
+    }
+}
+
+impl std::fmt::Display for SunsetDate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (year, month) = civil_year_month(self.days);
+        let day = self.days as i64 - days_from_civil(year as i64, month as i64, 1) + 1;
+        write!(f, "{year:04}-{month:02}-{day:02}")
+    }
+}
+
+impl<'de> Deserialize<'de> for SunsetDate {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = String::deserialize(deserializer)?;
+        Self::parse(&raw).ok_or_else(|| {
+            serde::de::Error::custom(format!("invalid date {raw:?}; expected YYYY-MM-DD"))
+        })
+    }
+}
+
+/// Copies a sample of the requests for `source` (the AutoPilot alias or a model id) to `shadow`
+/// on the primary backend, for offline evaluation. `Debug` redacts `api_key`.
+#[derive(Clone, Deserialize)]
+pub struct MirrorRule {
+    pub name: String,
+    pub source: String,
+    pub shadow: String,
+    /// Share of matching requests to mirror, `0.0..=100.0`.
+    pub sample_percent: f64,
+    /// Bearer token for shadow requests; the client's `Authorization` is never reused.
+    pub api_key: String,
+}
+
+impl std::fmt::Debug for MirrorRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MirrorRule")
+            .field("name", &self.name)
+            .field("source", &self.source)
+            .field("shadow", &self.shadow)
+            .field("sample_percent", &self.sample_percent)
+            .field("api_key", &"<redacted>")
+            .finish()
+    }
+}
+
+impl MirrorRule {
+    /// The alias matches alias requests; a model id matches requests it served.
+    fn matches(&self, routing_mode: RoutingMode, served: &Candidate) -> bool {
+        if is_autopilot_alias(&self.source) {
+            routing_mode == RoutingMode::AutoPilotAlias
+        } else {
+            served.backend == 0 && served.model == self.source
+        }
+    }
+
+    fn sampled(&self) -> bool {
+        let roll = (Uuid::new_v4().as_u128() % 10_000) as f64;
+        roll < self.sample_percent * 100.0
+    }
+}
+
+impl AppConfig {
+    /// Backend name by candidate index: 0 is the primary, `1..` are [`AppConfig::backends`].
+    fn backend_name(&self, backend: usize) -> &str {
+        match backend.checked_sub(1) {
+            None => PRIMARY_BACKEND,
+            Some(idx) => &self.backends[idx].name,
+        }
+    }
+
+    /// Whether a backend accepts `stream_options.include_usage`. The primary always does.
+    fn accepts_stream_usage(&self, backend: usize) -> bool {
+        backend
+            .checked_sub(1)
+            .is_none_or(|idx| self.backends[idx].stream_usage)
+    }
+}
+
+/// What a candidate refresh does when utilization records lack core scoring signals.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SchemaMode {
+    /// Publish anyway; report drift via metrics and warnings.
+    #[default]
+    Lenient,
+    /// Keep the last-known-good snapshot when drift exceeds `utilization_max_drift_pct`.
+    Strict,
+}
+
+/// How a new AutoPilot client (no sticky entry) picks its first candidate.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SelectionMode {
+    /// Always the top-ranked candidate.
+    #[default]
+    Top,
+    /// Among the top-K, with probability proportional to score.
+    Weighted,
+    /// Sample two of the top-K and take the higher-scored one.
+    PowerOfTwo,
+}
+
+/// Upstream statuses that trigger failover before any bytes are committed, per routing mode.
+///
+/// Only `5xx` entries take effect: `429` and other `4xx` statuses are never retried, even when
+/// listed; see `specs/002-autopilot-mvp/spec.md`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RetryableStatuses {
+    pub alias: Vec<StatusCode>,
+    pub list: Vec<StatusCode>,
+    pub direct: Vec<StatusCode>,
+}
+
+impl Default for RetryableStatuses {
+    fn default() -> Self {
+        let default = vec![StatusCode::SERVICE_UNAVAILABLE];
+        Self {
+            alias: default.clone(),
+            list: default.clone(),
+            direct: default,
+        }
+    }
+}
+
+impl RetryableStatuses {
+    fn for_mode(&self, mode: RoutingMode) -> &[StatusCode] {
+        match mode {
+            RoutingMode::AutoPilotAlias => &self.alias,
+            RoutingMode::ExplicitModelList => &self.list,
+            RoutingMode::Direct => &self.direct,
+        }
+    }
 }
 
 impl Default for AppConfig {
@@ -71,6 +391,36 @@ impl Default for AppConfig {
             sticky_max_entries: 10_000,
             trust_proxy_headers: false,
             trusted_proxy_cidrs: Vec::new(),
+            client_budgets: Vec::new(),
+            client_budget_state_path: None,
+            hedge_enabled: false,
+            hedge_delay: Duration::from_millis(1_000),
+            max_upstream_attempts: 8,
+            upstream_retry_budget: Duration::ZERO,
+            retryable_statuses: RetryableStatuses::default(),
+            retry_after_max_cooldown: Duration::from_secs(300),
+            normalize_upstream_errors: false,
+            admin_token: None,
+            leader_score_margin: 0.0,
+            leader_min_dwell: Duration::ZERO,
+            selection_mode: SelectionMode::Top,
+            selection_top_k: 3,
+            utilization_schema_mode: SchemaMode::Lenient,
+            utilization_max_drift_pct: 10.0,
+            control_plane_max_backoff: Duration::from_secs(60),
+            backends: Vec::new(),
+            model_redirects: Vec::new(),
+            case_insensitive_models: false,
+            mirror_rules: Vec::new(),
+            mirror_log_path: None,
+            mirror_timeout: Duration::from_secs(60),
+            attestation_enabled: false,
+            attestation_evidence_url: "https://api.chutes.ai/chutes/{chute_id}/evidence"
+                .to_string(),
+            attestation_refresh: Duration::from_millis(300_000),
+            require_attested: false,
+            sanitize_sampling_params: false,
+            clamp_max_tokens: false,
         }
     }
 }
@@ -87,9 +437,35 @@ pub struct Readiness {
 struct RuntimeState {
     candidates: Vec<RankedCandidate>,
     models_allowlist: HashSet<String>,
+    models_catalog: HashMap<String, OpenAiModelItem>,
     models_allowlist_at: Option<Instant>,
     snapshot_at: Option<Instant>,
     sticky_models: HashMap<String, StickyModelSelection>,
+    model_cooldowns: HashMap<String, Instant>,
+    overrides: Vec<ModelOverride>,
+    next_override_id: u64,
+    candidate_scores: Vec<ScoredRecord>,
+    candidate_exclusions: Vec<ExcludedRecord>,
+    leader: Option<Leader>,
+    /// Per-backend snapshots for [`AppConfig::backends`], in the same order.
+    backends: Vec<BackendSnapshot>,
+    /// Latest evidence check per TEE model name.
+    attestations: HashMap<String, Attestation>,
+}
+
+/// Models and candidate order last fetched from an additional backend.
+#[derive(Debug, Default)]
+struct BackendSnapshot {
+    models: HashSet<String>,
+    ranked: Vec<String>,
+    refreshed_at: Option<Instant>,
+}
+
+/// One upstream attempt target: a model on a backend (0 is the primary).
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Candidate {
+    backend: usize,
+    model: String,
 }
 
 #[derive(Clone)]
@@ -102,7 +478,24 @@ struct Metrics {
     ready_candidates: IntGauge,
     ready_allowlist_size: IntGauge,
     selection_total: IntCounterVec,
+    backend_selection_total: IntCounterVec,
+    model_redirect_total: IntCounterVec,
+    mirror_requests_total: IntCounterVec,
+    mirror_latency_seconds: HistogramVec,
+    mirror_tokens_total: IntCounterVec,
     failover_reason_total: IntCounterVec,
+    budget_rejected_total: IntCounterVec,
+    hedge_fired_total: IntCounter,
+    hedge_wins_total: IntCounter,
+    leader_changes_total: IntCounter,
+    leader_held_total: IntCounter,
+    schema_drift_records: IntGaugeVec,
+    schema_drift_rejected_total: IntCounter,
+    control_plane_fetch_total: IntCounterVec,
+    control_plane_last_error: IntGaugeVec,
+    control_plane_consecutive_failures: IntGaugeVec,
+    attestation_candidates: IntGaugeVec,
+    tee_runtime_gate_candidates: IntGaugeVec,
 }
 
 struct ActiveRequestGuard {
@@ -188,6 +581,66 @@ impl Metrics {
             .register(Box::new(selection_total.clone()))
             .expect("register selection_total");
 
+        let backend_selection_total = IntCounterVec::new(
+            Opts::new(
+                "chutes_autopilot_backend_selection_total",
+                "count of upstream selections by backend and status",
+            ),
+            &["backend", "status"],
+        )
+        .expect("backend_selection_total");
+        registry
+            .register(Box::new(backend_selection_total.clone()))
+            .expect("register backend_selection_total");
+
+        let model_redirect_total = IntCounterVec::new(
+            Opts::new(
+                "chutes_autopilot_model_redirect_total",
+                "count of requests that named a redirected model id",
+            ),
+            &["from", "to"],
+        )
+        .expect("model_redirect_total");
+        registry
+            .register(Box::new(model_redirect_total.clone()))
+            .expect("register model_redirect_total");
+
+        let mirror_requests_total = IntCounterVec::new(
+            Opts::new(
+                "chutes_autopilot_mirror_requests_total",
+                "count of shadow requests by mirror rule and outcome",
+            ),
+            &["rule", "outcome"],
+        )
+        .expect("mirror_requests_total");
+        registry
+            .register(Box::new(mirror_requests_total.clone()))
+            .expect("register mirror_requests_total");
+
+        let mirror_latency_seconds = HistogramVec::new(
+            HistogramOpts::new(
+                "chutes_autopilot_mirror_latency_seconds",
+                "time to a complete shadow response by mirror rule",
+            ),
+            &["rule"],
+        )
+        .expect("mirror_latency_seconds");
+        registry
+            .register(Box::new(mirror_latency_seconds.clone()))
+            .expect("register mirror_latency_seconds");
+
+        let mirror_tokens_total = IntCounterVec::new(
+            Opts::new(
+                "chutes_autopilot_mirror_tokens_total",
+                "tokens reported by shadow responses by mirror rule and kind",
+            ),
+            &["rule", "kind"],
+        )
+        .expect("mirror_tokens_total");
+        registry
+            .register(Box::new(mirror_tokens_total.clone()))
+            .expect("register mirror_tokens_total");
+
         let failover_reason_total = IntCounterVec::new(
             Opts::new(
                 "chutes_autopilot_failover_reason_total",
@@ -200,6 +653,135 @@ impl Metrics {
             .register(Box::new(failover_reason_total.clone()))
             .expect("register failover_reason_total");
 
+        let budget_rejected_total = IntCounterVec::new(
+            Opts::new(
+                "chutes_autopilot_budget_rejected_total",
+                "count of requests rejected at admission because a client budget was exhausted",
+            ),
+            &["budget"],
+        )
+        .expect("budget_rejected_total");
+        registry
+            .register(Box::new(budget_rejected_total.clone()))
+            .expect("register budget_rejected_total");
+
+        let hedge_fired_total = IntCounter::new(
+            "chutes_autopilot_hedge_fired_total",
+            "count of hedged upstream requests fired after the hedge delay elapsed",
+        )
+        .expect("hedge_fired_total");
+        registry
+            .register(Box::new(hedge_fired_total.clone()))
+            .expect("register hedge_fired_total");
+
+        let hedge_wins_total = IntCounter::new(
+            "chutes_autopilot_hedge_wins_total",
+            "count of hedged upstream requests whose response was used",
+        )
+        .expect("hedge_wins_total");
+        registry
+            .register(Box::new(hedge_wins_total.clone()))
+            .expect("register hedge_wins_total");
+
+        let leader_changes_total = IntCounter::new(
+            "chutes_autopilot_leader_changes_total",
+            "count of refreshes that published a new top-ranked candidate",
+        )
+        .expect("leader_changes_total");
+        registry
+            .register(Box::new(leader_changes_total.clone()))
+            .expect("register leader_changes_total");
+
+        let leader_held_total = IntCounter::new(
+            "chutes_autopilot_leader_held_total",
+            "count of refreshes where hysteresis kept the current leader over a higher-ranked challenger",
+        )
+        .expect("leader_held_total");
+        registry
+            .register(Box::new(leader_held_total.clone()))
+            .expect("register leader_held_total");
+
+        let attestation_candidates = IntGaugeVec::new(
+            Opts::new(
+                "chutes_autopilot_attestation_candidates",
+                "ranked candidates by attestation status (evidence_returned, unverified, not_tee)",
+            ),
+            &["status"],
+        )
+        .expect("attestation_candidates");
+        registry
+            .register(Box::new(attestation_candidates.clone()))
+            .expect("register attestation_candidates");
+
+        let tee_runtime_gate_candidates = IntGaugeVec::new(
+            Opts::new(
+                "chutes_autopilot_tee_runtime_gate_candidates",
+                "ranked TEE candidates by chutes_version >= 0.6.0 evidence gate (meets, below, unknown)",
+            ),
+            &["gate"],
+        )
+        .expect("tee_runtime_gate_candidates");
+        registry
+            .register(Box::new(tee_runtime_gate_candidates.clone()))
+            .expect("register tee_runtime_gate_candidates");
+
+        let schema_drift_records = IntGaugeVec::new(
+            Opts::new(
+                "chutes_autopilot_utilization_schema_drift",
+                "utilization records in the latest refresh missing a scoring field (field=\"core\" counts records lacking core signals)",
+            ),
+            &["field"],
+        )
+        .expect("schema_drift_records");
+        registry
+            .register(Box::new(schema_drift_records.clone()))
+            .expect("register schema_drift_records");
+
+        let schema_drift_rejected_total = IntCounter::new(
+            "chutes_autopilot_utilization_schema_drift_rejected_total",
+            "count of utilization refreshes not published because of schema drift (strict mode)",
+        )
+        .expect("schema_drift_rejected_total");
+        registry
+            .register(Box::new(schema_drift_rejected_total.clone()))
+            .expect("register schema_drift_rejected_total");
+
+        let control_plane_fetch_total = IntCounterVec::new(
+            Opts::new(
+                "chutes_autopilot_control_plane_fetch_total",
+                "count of control-plane fetches by source and outcome (success, failure, not_modified)",
+            ),
+            &["source", "outcome"],
+        )
+        .expect("control_plane_fetch_total");
+        registry
+            .register(Box::new(control_plane_fetch_total.clone()))
+            .expect("register control_plane_fetch_total");
+
+        let control_plane_last_error = IntGaugeVec::new(
+            Opts::new(
+                "chutes_autopilot_control_plane_last_error_timestamp_seconds",
+                "unix time of the most recent failed control-plane fetch by source",
+            ),
+            &["source"],
+        )
+        .expect("control_plane_last_error");
+        registry
+            .register(Box::new(control_plane_last_error.clone()))
+            .expect("register control_plane_last_error");
+
+        let control_plane_consecutive_failures = IntGaugeVec::new(
+            Opts::new(
+                "chutes_autopilot_control_plane_consecutive_failures",
+                "consecutive failed control-plane fetches by source (0 once recovered)",
+            ),
+            &["source"],
+        )
+        .expect("control_plane_consecutive_failures");
+        registry
+            .register(Box::new(control_plane_consecutive_failures.clone()))
+            .expect("register control_plane_consecutive_failures");
+
         Self {
             registry,
             req_active,
@@ -209,7 +791,24 @@ impl Metrics {
             ready_candidates,
             ready_allowlist_size,
             selection_total,
+            backend_selection_total,
+            model_redirect_total,
+            mirror_requests_total,
+            mirror_latency_seconds,
+            mirror_tokens_total,
             failover_reason_total,
+            budget_rejected_total,
+            hedge_fired_total,
+            hedge_wins_total,
+            leader_changes_total,
+            leader_held_total,
+            schema_drift_records,
+            schema_drift_rejected_total,
+            control_plane_fetch_total,
+            control_plane_last_error,
+            control_plane_consecutive_failures,
+            attestation_candidates,
+            tee_runtime_gate_candidates,
         }
     }
 
@@ -225,11 +824,48 @@ impl Metrics {
         self.req_total.with_label_values(&[code.as_str()]).inc();
     }
 
-    fn observe_selection(&self, model: &str, status: StatusCode) {
+    fn observe_selection(&self, backend: &str, model: &str, status: StatusCode) {
         let code = status.as_u16().to_string();
         self.selection_total
             .with_label_values(&[model, code.as_str()])
             .inc();
+        self.backend_selection_total
+            .with_label_values(&[backend, code.as_str()])
+            .inc();
+    }
+
+    fn observe_redirect(&self, from: &str, to: &str) {
+        self.model_redirect_total
+            .with_label_values(&[from, to])
+            .inc();
+    }
+
+    fn observe_mirror_skipped(&self, rule: &str) {
+        self.mirror_requests_total
+            .with_label_values(&[rule, "skipped"])
+            .inc();
+    }
+
+    fn observe_mirror(&self, rule: &str, shadow: &ShadowOutcome) {
+        let outcome = shadow
+            .status
+            .map_or_else(|| shadow.error.to_string(), |status| status.to_string());
+        self.mirror_requests_total
+            .with_label_values(&[rule, outcome.as_str()])
+            .inc();
+        if shadow.status.is_some() {
+            self.mirror_latency_seconds
+                .with_label_values(&[rule])
+                .observe(shadow.latency.as_secs_f64());
+        }
+        if let Some(usage) = shadow.usage {
+            self.mirror_tokens_total
+                .with_label_values(&[rule, "prompt"])
+                .inc_by(usage.prompt_tokens);
+            self.mirror_tokens_total
+                .with_label_values(&[rule, "completion"])
+                .inc_by(usage.completion_tokens);
+        }
     }
 
     fn observe_failover(&self, reason: &str) {
@@ -238,6 +874,79 @@ impl Metrics {
             .inc();
     }
 
+    fn observe_budget_rejection(&self, budget: &str) {
+        self.budget_rejected_total
+            .with_label_values(&[budget])
+            .inc();
+    }
+
+    fn observe_hedge_fired(&self) {
+        self.hedge_fired_total.inc();
+    }
+
+    fn observe_hedge_win(&self) {
+        self.hedge_wins_total.inc();
+    }
+
+    fn observe_schema_drift(&self, drift: &SchemaDrift) {
+        for field in UTILIZATION_SIGNAL_FIELDS {
+            self.schema_drift_records
+                .with_label_values(&[field])
+                .set(drift.missing_count(field) as i64);
+        }
+        self.schema_drift_records
+            .with_label_values(&["core"])
+            .set(drift.missing_core as i64);
+    }
+
+    fn observe_attestations(&self, summary: &[CandidateAttestation]) {
+        for status in ["evidence_returned", "unverified", "not_tee"] {
+            let count = summary.iter().filter(|a| a.summary == status).count();
+            self.attestation_candidates
+                .with_label_values(&[status])
+                .set(count as i64);
+        }
+        for gate in ["meets", "below", "unknown"] {
+            let count = summary
+                .iter()
+                .filter(|a| a.runtime_gate == Some(gate))
+                .count();
+            self.tee_runtime_gate_candidates
+                .with_label_values(&[gate])
+                .set(count as i64);
+        }
+    }
+
+    fn observe_schema_drift_rejection(&self) {
+        self.schema_drift_rejected_total.inc();
+    }
+
+    fn observe_control_plane_fetch(&self, source: &str, outcome: &str, consecutive_failures: u32) {
+        self.control_plane_fetch_total
+            .with_label_values(&[source, outcome])
+            .inc();
+        self.control_plane_consecutive_failures
+            .with_label_values(&[source])
+            .set(i64::from(consecutive_failures));
+        if outcome == "failure" {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+            self.control_plane_last_error
+                .with_label_values(&[source])
+                .set(now as i64);
+        }
+    }
+
+    fn observe_leader(&self, outcome: LeaderOutcome) {
+        match outcome {
+            LeaderOutcome::Changed => self.leader_changes_total.inc(),
+            LeaderOutcome::Held => self.leader_held_total.inc(),
+            LeaderOutcome::Unchanged => {}
+        }
+    }
+
     fn observe_readiness(&self, readiness: &Readiness) {
         self.ready_candidates.set(readiness.candidates_len as i64);
         self.ready_allowlist_size
@@ -257,6 +966,34 @@ impl Metrics {
     }
 }
 
+/// Rolling window of recent upstream response-header latencies.
+#[derive(Debug, Default)]
+struct LatencyWindow {
+    samples: VecDeque<Duration>,
+}
+
+impl LatencyWindow {
+    const MAX_SAMPLES: usize = 256;
+    const MIN_SAMPLES: usize = 20;
+
+    fn record(&mut self, latency: Duration) {
+        if self.samples.len() == Self::MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(latency);
+    }
+
+    fn quantile(&self, q: f64) -> Option<Duration> {
+        if self.samples.len() < Self::MIN_SAMPLES {
+            return None;
+        }
+        let mut sorted: Vec<Duration> = self.samples.iter().copied().collect();
+        sorted.sort_unstable();
+        let idx = ((sorted.len() - 1) as f64 * q).round() as usize;
+        sorted.get(idx).copied()
+    }
+}
+
 #[derive(Clone, Debug)]
 struct StickyModelSelection {
     model: String,
@@ -276,11 +1013,27 @@ impl AppState {
             .build()
             .expect("failed to build reqwest client");
         let metrics = Arc::new(Metrics::new());
+        let budgets = Arc::new(BudgetLedger::new(
+            &config.client_budgets,
+            config.client_budget_state_path.clone(),
+        ));
+        let runtime = RuntimeState {
+            backends: config
+                .backends
+                .iter()
+                .map(|_| BackendSnapshot::default())
+                .collect(),
+            ..Default::default()
+        };
         Self {
-            runtime: Arc::new(RwLock::new(RuntimeState::default())),
+            runtime: Arc::new(RwLock::new(runtime)),
             config,
             http_client,
             metrics,
+            budgets,
+            header_latency: Arc::new(Mutex::new(LatencyWindow::default())),
+            model_header_latency: Arc::default(),
+            mirror_slots: Arc::new(Semaphore::new(MAX_INFLIGHT_MIRRORS)),
         }
     }
 
@@ -294,50 +1047,573 @@ impl AppState {
         }
     }
 
-    async fn update_candidate_snapshot(&self, candidates: anyhow::Result<Vec<RankedCandidate>>) {
-        let Ok(candidates) = candidates else {
+    /// Reports utilization schema drift and, in strict mode, rejects the refresh when too many
+    /// records lack core signals so the last-known-good snapshot stays published.
+    fn check_utilization_schema(&self, report: CandidateReport) -> anyhow::Result<CandidateReport> {
+        let drift = &report.schema_drift;
+        self.metrics.observe_schema_drift(drift);
+        if drift.missing.is_empty() {
+            return Ok(report);
+        }
+
+        let core_missing_pct = drift.core_missing_pct();
+        tracing::warn!(
+            records = drift.records,
+            missing_core = drift.missing_core,
+            missing = ?drift.missing,
+            "schema_drift: utilization records are missing scoring fields"
+        );
+
+        if self.config.utilization_schema_mode == SchemaMode::Strict
+            && core_missing_pct > self.config.utilization_max_drift_pct
+        {
+            self.metrics.observe_schema_drift_rejection();
+            return Err(anyhow::anyhow!(
+                "utilization schema drift: {core_missing_pct:.1}% of records lack core signals"
+            ));
+        }
+        Ok(report)
+    }
+
+    async fn update_candidate_snapshot(&self, report: anyhow::Result<CandidateReport>) {
+        let Ok(mut report) = report else {
             return;
         };
 
         let mut runtime = self.runtime.write().await;
-        runtime.candidates = candidates;
+        let outcome = apply_leader_hysteresis(
+            &mut report,
+            &mut runtime.leader,
+            self.config.leader_score_margin,
+            self.config.leader_min_dwell,
+            Instant::now(),
+        );
+        self.metrics.observe_leader(outcome);
+        runtime.candidates = report.ranked;
+        runtime.candidate_scores = report.scored;
+        runtime.candidate_exclusions = report.excluded;
         runtime.snapshot_at = Some(Instant::now());
     }
 
-    async fn models_allowlist(&self) -> HashSet<String> {
-        self.runtime.read().await.models_allowlist.clone()
+    /// Records a response-header latency for the hedge window and, for primary-backend models,
+    /// the per-model moving average used by `prefer: latency`.
+    fn observe_header_latency(&self, model: Option<&str>, latency: Duration) {
+        self.header_latency
+            .lock()
+            .expect("header latency window poisoned")
+            .record(latency);
+        if let Some(model) = model {
+            let mut per_model = self
+                .model_header_latency
+                .lock()
+                .expect("model header latency poisoned");
+            per_model
+                .entry(model.to_string())
+                .and_modify(|avg| *avg = avg.mul_f64(0.8) + latency.mul_f64(0.2))
+                .or_insert(latency);
+        }
     }
 
-    async fn candidate_models(&self) -> Vec<String> {
+    /// Stable-sorts candidates by the requested preference. Models without a latency sample or
+    /// catalog price keep their relative order after the ones that have one.
+    async fn order_by_preference(&self, candidates: &mut [String], prefer: RoutePreference) {
+        match prefer {
+            RoutePreference::Latency => {
+                let per_model = self
+                    .model_header_latency
+                    .lock()
+                    .expect("model header latency poisoned")
+                    .clone();
+                candidates
+                    .sort_by_key(|model| per_model.get(model).copied().unwrap_or(Duration::MAX));
+            }
+            RoutePreference::Cost => {
+                let runtime = self.runtime.read().await;
+                let cost = |model: &String| {
+                    runtime
+                        .models_catalog
+                        .get(model)
+                        .and_then(|item| item.pricing)
+                        .map_or(f64::INFINITY, |p| p.prompt + p.completion)
+                };
+                candidates.sort_by(|a, b| cost(a).total_cmp(&cost(b)));
+            }
+        }
+    }
+
+    /// Hedge delay: the observed p90 response-header latency, never below `hedge_delay`.
+    fn hedge_delay(&self) -> Duration {
+        let p90 = self
+            .header_latency
+            .lock()
+            .expect("header latency window poisoned")
+            .quantile(0.9);
+        p90.map_or(self.config.hedge_delay, |p90| {
+            p90.max(self.config.hedge_delay)
+        })
+    }
+
+    async fn model_pricing(&self, model: &str) -> Option<ModelPricing> {
         self.runtime
             .read()
             .await
-            .candidates
-            .iter()
-            .map(|candidate| candidate.name.clone())
-            .collect()
+            .models_catalog
+            .get(model)
+            .and_then(|item| item.pricing)
     }
 
-    async fn sticky_model(&self, key: &str) -> Option<String> {
-        let mut runtime = self.runtime.write().await;
-        Self::evict_expired_sticky(&mut runtime, self.config.sticky_ttl);
-
-        let now = Instant::now();
-        match runtime.sticky_models.get(key) {
-            Some(selection)
-                if now.duration_since(selection.last_used_at) <= self.config.sticky_ttl =>
-            {
-                Some(selection.model.clone())
-            }
-            Some(_) => {
-                runtime.sticky_models.remove(key);
-                None
-            }
-            None => None,
+    /// Replaces glob, `family:` and `autopilot` entries of a preference list with their matching
+    /// models in current rank order (catalog models that are not ranked follow, sorted by name).
+    /// Duplicates keep their first position. An expansion past `max_model_list_items` is rejected
+    /// like an over-long list.
+    async fn expand_model_patterns(
+        &self,
+        items: Vec<String>,
+    ) -> Result<Vec<String>, ModelListError> {
+        if items.iter().all(|item| ModelPattern::parse(item).is_none()) {
+            return Ok(items);
         }
-    }
 
-    async fn set_sticky_model(&self, key: String, model: String) {
+        let ranked = self.candidate_models().await;
+        let pool: Vec<String> = {
+            let runtime = self.runtime.read().await;
+            let mut unranked: Vec<&String> = runtime
+                .models_allowlist
+                .iter()
+                .filter(|model| !ranked.contains(model))
+                .collect();
+            unranked.sort();
+            ranked
+                .iter()
+                .cloned()
+                .chain(unranked.into_iter().cloned())
+                .collect()
+        };
+
+        let mut out: Vec<String> = Vec::new();
+        let mut seen: HashSet<String> = HashSet::new();
+        for item in items {
+            let expanded = match ModelPattern::parse(&item) {
+                None => vec![item],
+                Some(ModelPattern::AutoPilot) => ranked.clone(),
+                Some(ModelPattern::Family("")) => {
+                    return Err(ModelListError {
+                        code: "invalid_model_list".to_string(),
+                        message: "family: pattern needs a name".to_string(),
+                    });
+                }
+                Some(pattern) => {
+                    let matched: Vec<String> = pool
+                        .iter()
+                        .filter(|model| pattern.matches(model))
+                        .cloned()
+                        .collect();
+                    if matched.is_empty() {
+                        return Err(ModelListError {
+                            code: "unknown_model".to_string(),
+                            message: format!("model pattern matches no known models: {item}"),
+                        });
+                    }
+                    matched
+                }
+            };
+            for model in expanded {
+                if seen.insert(model.clone()) {
+                    out.push(model);
+                }
+            }
+        }
+
+        let max_items = self.config.max_model_list_items;
+        if out.len() > max_items {
+            return Err(ModelListError {
+                code: "invalid_model_list".to_string(),
+                message: format!(
+                    "model patterns expand to {} models, over MAX_MODEL_LIST_ITEMS ({max_items})",
+                    out.len()
+                ),
+            });
+        }
+        Ok(out)
+    }
+
+    /// Ranked AutoPilot candidates with active admin overrides applied (and, under
+    /// `require_attested`, only chutes that returned evidence).
+    async fn candidate_models(&self) -> Vec<String> {
+        let runtime = self.runtime.read().await;
+        let ranked = runtime
+            .candidates
+            .iter()
+            .map(|candidate| candidate.name.clone())
+            .collect();
+        let mut candidates = apply_alias_overrides(&runtime.overrides, ranked, Instant::now());
+        if self.config.require_attested {
+            candidates.retain(|model| is_attested(&runtime.attestations, model));
+        }
+        candidates
+    }
+
+    /// Moves the spread-selected candidate (see [`SelectionMode`]) to the front, behind any
+    /// boosted models; the rest keep their ranked order for failover. `seed_key` makes the choice
+    /// reproducible per client.
+    async fn spread_alias_selection(&self, candidates: &mut [String], seed_key: &str) {
+        let boosted = self.boosted_prefix_len(candidates).await;
+        let candidates = &mut candidates[boosted..];
+        let top_k = self.config.selection_top_k.min(candidates.len());
+        if self.config.selection_mode == SelectionMode::Top || top_k < 2 {
+            return;
+        }
+
+        let scores: Vec<Option<f64>> = {
+            let runtime = self.runtime.read().await;
+            candidates[..top_k]
+                .iter()
+                .map(|name| {
+                    runtime
+                        .candidates
+                        .iter()
+                        .find(|c| &c.name == name)
+                        .map(|c| c.score)
+                })
+                .collect()
+        };
+
+        let mut hasher = DefaultHasher::new();
+        seed_key.hash(&mut hasher);
+        let chosen = spread_choice(self.config.selection_mode, &scores, hasher.finish());
+        candidates[..=chosen].rotate_right(1);
+    }
+
+    async fn remove_blocked_models(&self, candidates: &mut Vec<String>) -> usize {
+        let runtime = self.runtime.read().await;
+        remove_blocked_models(&runtime.overrides, candidates, Instant::now())
+    }
+
+    /// Number of leading alias candidates placed first by an active `boost` override.
+    async fn boosted_prefix_len(&self, candidates: &[String]) -> usize {
+        let runtime = self.runtime.read().await;
+        let now = Instant::now();
+        candidates
+            .iter()
+            .take_while(|candidate| {
+                runtime.overrides.iter().any(|o| {
+                    o.action == OverrideAction::Boost && o.model == **candidate && o.is_active(now)
+                })
+            })
+            .count()
+    }
+
+    /// Under `require_attested`, removes models without returned evidence from an explicit list
+    /// or direct request, returning how many were dropped.
+    async fn remove_unattested_models(&self, candidates: &mut Vec<String>) -> usize {
+        if !self.config.require_attested {
+            return 0;
+        }
+        let runtime = self.runtime.read().await;
+        let before = candidates.len();
+        candidates.retain(|model| is_attested(&runtime.attestations, model));
+        before - candidates.len()
+    }
+
+    /// Removes candidates a USD budget cannot be charged for: additional backends and primary
+    /// models without catalog pricing. Returns how many were dropped.
+    async fn remove_unpriced_candidates(&self, candidates: &mut Vec<Candidate>) -> usize {
+        let runtime = self.runtime.read().await;
+        let before = candidates.len();
+        candidates.retain(|candidate| {
+            candidate.backend == 0
+                && runtime
+                    .models_catalog
+                    .get(&candidate.model)
+                    .is_some_and(|item| item.pricing.is_some())
+        });
+        before - candidates.len()
+    }
+
+    /// Catalog limits the per-attempt body rewrite enforces for a candidate, each only when its
+    /// option is on. Additional backends and models missing from the catalog get none.
+    async fn attempt_limits(
+        &self,
+        candidate: &Candidate,
+        meter_stream_usage: bool,
+    ) -> AttemptLimits {
+        let config = &self.config;
+        let limits = AttemptLimits {
+            stream_usage: meter_stream_usage && config.accepts_stream_usage(candidate.backend),
+            ..AttemptLimits::default()
+        };
+        if candidate.backend != 0 || !(config.sanitize_sampling_params || config.clamp_max_tokens) {
+            return limits;
+        }
+        let runtime = self.runtime.read().await;
+        let Some(item) = runtime.models_catalog.get(&candidate.model) else {
+            return limits;
+        };
+        AttemptLimits {
+            sampling_params: item
+                .supported_sampling_parameters
+                .as_ref()
+                .filter(|_| config.sanitize_sampling_params)
+                .map(|params| params.iter().cloned().collect()),
+            max_output_length: item.max_output_length.filter(|_| config.clamp_max_tokens),
+            ..limits
+        }
+    }
+
+    /// Replaces redirected model ids with their targets, keeping each model's first position.
+    /// Returns the redirects applied, or the first one whose sunset has passed.
+    fn apply_model_redirects(
+        &self,
+        models: &mut Vec<String>,
+        now: SystemTime,
+    ) -> Result<Vec<&ModelRedirect>, &ModelRedirect> {
+        let mut applied = Vec::new();
+        if self.config.model_redirects.is_empty() {
+            return Ok(applied);
+        }
+        for model in models.iter_mut() {
+            let Some(redirect) = self
+                .config
+                .model_redirects
+                .iter()
+                .find(|redirect| redirect.from == *model)
+            else {
+                continue;
+            };
+            if redirect.sunset.is_some_and(|sunset| sunset.has_passed(now)) {
+                return Err(redirect);
+            }
+            model.clone_from(&redirect.to);
+            applied.push(redirect);
+        }
+        if !applied.is_empty() {
+            let mut seen = HashSet::new();
+            models.retain(|model| seen.insert(model.clone()));
+        }
+        Ok(applied)
+    }
+
+    /// The chute behind a primary-backend model, from the ranked snapshot or the catalog.
+    async fn chute_id_for(&self, candidate: &Candidate) -> Option<String> {
+        if candidate.backend != 0 {
+            return None;
+        }
+        let runtime = self.runtime.read().await;
+        runtime
+            .candidates
+            .iter()
+            .find(|c| c.name == candidate.model)
+            .and_then(|c| c.chute_id.clone())
+            .or_else(|| {
+                runtime
+                    .models_catalog
+                    .get(&candidate.model)?
+                    .chute_id
+                    .clone()
+            })
+    }
+
+    /// Replaces chute UUIDs in a direct or list request with the model name they serve (catalog
+    /// first, then the ranked snapshot), dropping duplicates this creates. Unknown ids are left
+    /// for allowlist validation.
+    async fn resolve_chute_ids(&self, models: &mut Vec<String>) {
+        let runtime = self.runtime.read().await;
+        let resolved: Vec<String> = models
+            .drain(..)
+            .map(|model| {
+                if Uuid::parse_str(&model).is_err() {
+                    return model;
+                }
+                runtime
+                    .models_catalog
+                    .values()
+                    .find(|item| item.chute_id.as_deref() == Some(model.as_str()))
+                    .map(|item| item.id.clone())
+                    .or_else(|| {
+                        runtime
+                            .candidates
+                            .iter()
+                            .find(|c| c.chute_id.as_deref() == Some(model.as_str()))
+                            .map(|c| c.name.clone())
+                    })
+                    .unwrap_or(model)
+            })
+            .collect();
+        for model in resolved {
+            if !models.contains(&model) {
+                models.push(model);
+            }
+        }
+    }
+
+    /// Header value for the candidate that served a request: `evidence-returned`, `not-tee`, or
+    /// `unverified` (unchecked, failed checks, and additional backends). `None` when attestation
+    /// checks are off.
+    async fn attestation_label(&self, candidate: &Candidate) -> Option<&'static str> {
+        if !(self.config.attestation_enabled || self.config.require_attested) {
+            return None;
+        }
+        if candidate.backend != 0 {
+            return Some("unverified");
+        }
+        let runtime = self.runtime.read().await;
+        let status = runtime.attestations.get(&candidate.model).map(|a| a.status);
+        Some(match status {
+            Some(AttestationStatus::EvidenceReturned) => "evidence-returned",
+            Some(AttestationStatus::NonTee) => "not-tee",
+            _ if !candidate.model.ends_with("-TEE") => "not-tee",
+            _ => "unverified",
+        })
+    }
+
+    /// Models no backend serves, or empty while the primary catalog is not yet authoritative.
+    async fn unknown_models(&self, models: &[String]) -> Vec<String> {
+        let runtime = self.runtime.read().await;
+        if runtime.models_allowlist.is_empty() {
+            return Vec::new();
+        }
+        models
+            .iter()
+            .filter(|model| {
+                !runtime.models_allowlist.contains(*model)
+                    && !runtime.backends.iter().any(|b| b.models.contains(*model))
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Known model ids closest to an unknown one, best first (see [`suggest_models`]).
+    async fn model_suggestions(&self, model: &str) -> Vec<String> {
+        let known: Vec<String> = {
+            let runtime = self.runtime.read().await;
+            runtime
+                .models_allowlist
+                .iter()
+                .chain(runtime.backends.iter().flat_map(|b| b.models.iter()))
+                .cloned()
+                .collect()
+        };
+        suggest_models(model, known.iter(), MAX_MODEL_SUGGESTIONS)
+    }
+
+    /// Under `case_insensitive_models`, replaces unknown ids that match exactly one known id
+    /// ignoring ASCII case with that id, keeping each model's first position.
+    async fn fold_model_case(&self, models: &mut Vec<String>) {
+        if !self.config.case_insensitive_models {
+            return;
+        }
+        let runtime = self.runtime.read().await;
+        let known: HashSet<&String> = runtime
+            .models_allowlist
+            .iter()
+            .chain(runtime.backends.iter().flat_map(|b| b.models.iter()))
+            .collect();
+        for model in models.iter_mut() {
+            if known.contains(model) {
+                continue;
+            }
+            let matches: Vec<&&String> = known
+                .iter()
+                .filter(|k| k.eq_ignore_ascii_case(model))
+                .collect();
+            if let [found] = matches[..] {
+                model.clone_from(found);
+            }
+        }
+        let mut seen = HashSet::new();
+        models.retain(|model| seen.insert(model.clone()));
+    }
+
+    /// Keeps the requested models the primary backend serves (all of them before its catalog
+    /// has loaded); the rest are left to additional backends.
+    async fn retain_primary_models(&self, models: &mut Vec<String>) {
+        let runtime = self.runtime.read().await;
+        if !runtime.models_allowlist.is_empty() {
+            models.retain(|model| runtime.models_allowlist.contains(model));
+        }
+    }
+
+    /// Turns the primary backend's candidates into attempt targets and adds each additional
+    /// backend's candidates as a failover tier: its ranked models (with overrides) for the
+    /// AutoPilot alias, otherwise the `requested` models it serves, in request order.
+    ///
+    /// The tier goes after the first `max_attempts - <backends in the tier>` primary candidates,
+    /// ahead of the rest, so a long primary ranking cannot use up every attempt before the
+    /// additional backends are reached.
+    async fn with_failover_backends(
+        &self,
+        primary: Vec<String>,
+        routing_mode: RoutingMode,
+        requested: &[String],
+        max_attempts: usize,
+    ) -> Vec<Candidate> {
+        let mut primary: Vec<Candidate> = primary
+            .into_iter()
+            .map(|model| Candidate { backend: 0, model })
+            .collect();
+
+        // Additional backends have no attestation evidence.
+        if self.config.require_attested {
+            return primary;
+        }
+
+        let mut tier: Vec<Candidate> = Vec::new();
+        let mut tier_backends = 0;
+        let runtime = self.runtime.read().await;
+        for (idx, snapshot) in runtime.backends.iter().enumerate() {
+            let models = match routing_mode {
+                RoutingMode::AutoPilotAlias => {
+                    let mut ranked = apply_alias_overrides(
+                        &runtime.overrides,
+                        snapshot.ranked.clone(),
+                        Instant::now(),
+                    );
+                    ranked.retain(|model| snapshot.models.contains(model));
+                    ranked
+                }
+                RoutingMode::ExplicitModelList | RoutingMode::Direct => requested
+                    .iter()
+                    .filter(|model| snapshot.models.contains(*model))
+                    .cloned()
+                    .collect(),
+            };
+            tier_backends += usize::from(!models.is_empty());
+            tier.extend(models.into_iter().map(|model| Candidate {
+                backend: idx + 1,
+                model,
+            }));
+        }
+
+        let head = max_attempts
+            .saturating_sub(tier_backends)
+            .max(1)
+            .min(primary.len());
+        let rest = primary.split_off(head);
+        primary.extend(tier);
+        primary.extend(rest);
+        primary
+    }
+
+    async fn sticky_model(&self, key: &str) -> Option<String> {
+        let mut runtime = self.runtime.write().await;
+        Self::evict_expired_sticky(&mut runtime, self.config.sticky_ttl);
+
+        let now = Instant::now();
+        match runtime.sticky_models.get(key) {
+            Some(selection)
+                if now.duration_since(selection.last_used_at) <= self.config.sticky_ttl =>
+            {
+                Some(selection.model.clone())
+            }
+            Some(_) => {
+                runtime.sticky_models.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    async fn set_sticky_model(&self, key: String, model: String) {
         let mut runtime = self.runtime.write().await;
         runtime.sticky_models.insert(
             key,
@@ -353,6 +1629,33 @@ impl AppState {
         self.runtime.write().await.sticky_models.remove(key);
     }
 
+    async fn set_model_cooldown(&self, model: &str, cooldown: Duration) {
+        let until = Instant::now() + cooldown;
+        let mut runtime = self.runtime.write().await;
+        let entry = runtime
+            .model_cooldowns
+            .entry(model.to_string())
+            .or_insert(until);
+        *entry = (*entry).max(until);
+    }
+
+    /// Moves candidates that are cooling down after a `503 Retry-After` to the back of the list,
+    /// preserving relative order, so they are only tried once everything else has failed.
+    async fn demote_cooling_down(&self, candidates: &mut Vec<String>) {
+        let mut runtime = self.runtime.write().await;
+        let now = Instant::now();
+        runtime.model_cooldowns.retain(|_, until| *until > now);
+        if runtime.model_cooldowns.is_empty() {
+            return;
+        }
+
+        let (ready, cooling): (Vec<String>, Vec<String>) = candidates
+            .drain(..)
+            .partition(|candidate| !runtime.model_cooldowns.contains_key(candidate));
+        candidates.extend(ready);
+        candidates.extend(cooling);
+    }
+
     async fn rotate_sticky_model(&self, key: &str, candidates: &[String], failed_model: &str) {
         let mut runtime = self.runtime.write().await;
         Self::evict_expired_sticky(&mut runtime, self.config.sticky_ttl);
@@ -424,6 +1727,12 @@ impl AppState {
 /// candidates/allowlist in memory.
 pub fn spawn_control_plane_refresh(state: AppState) {
     tokio::spawn(refresh_models_allowlist(state.clone()));
+    for idx in 0..state.config.backends.len() {
+        tokio::spawn(refresh_backend(state.clone(), idx));
+    }
+    if state.config.attestation_enabled || state.config.require_attested {
+        tokio::spawn(refresh_attestations(state.clone()));
+    }
     tokio::spawn(refresh_candidates(state));
 }
 
@@ -438,6 +1747,14 @@ pub fn app(state: AppState) -> Router {
         .with_state(state)
 }
 
+/// Operator endpoints (`/admin/*` and `/debug/candidates`), kept off [`app`] so they can be bound
+/// to a separate, private address. Every route requires `ADMIN_TOKEN`.
+pub fn admin_app(state: AppState) -> Router {
+    admin_router()
+        .route("/debug/candidates", get(debug_candidates))
+        .with_state(state)
+}
+
 async fn healthz() -> impl IntoResponse {
     (StatusCode::OK, "ok")
 }
@@ -528,6 +1845,42 @@ async fn readyz(State(state): State<AppState>) -> Response {
     (StatusCode::OK, "ready").into_response()
 }
 
+/// Explains the current AutoPilot ranking: per-model score breakdown, sort position, exclusion
+/// reasons, and the effective order after admin overrides. Requires the admin token.
+async fn debug_candidates(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    if let Err(resp) = authorize_admin(&state, &headers) {
+        return *resp;
+    }
+
+    let autopilot_order = state.candidate_models().await;
+    let runtime = state.runtime.read().await;
+    let backends: Vec<Value> = state
+        .config
+        .backends
+        .iter()
+        .zip(&runtime.backends)
+        .map(|(backend, snapshot)| {
+            json!({
+                "name": backend.name,
+                "snapshot_age_ms": snapshot.refreshed_at.map(|at| at.elapsed().as_millis() as u64),
+                "models_len": snapshot.models.len(),
+                "ranked": snapshot.ranked,
+            })
+        })
+        .collect();
+    Json(json!({
+        "snapshot_age_ms": runtime.snapshot_at.map(|at| at.elapsed().as_millis() as u64),
+        "models_allowlist_len": runtime.models_allowlist.len(),
+        "autopilot_order": autopilot_order,
+        "ranked": runtime.candidate_scores,
+        "excluded": runtime.candidate_exclusions,
+        "failover_backends": backends,
+        "attestation": (state.config.attestation_enabled || state.config.require_attested)
+            .then(|| candidate_attestations(&runtime)),
+    }))
+    .into_response()
+}
+
 async fn chat_completions(
     State(state): State<AppState>,
     connect_info: Option<ConnectInfo<SocketAddr>>,
@@ -542,6 +1895,30 @@ async fn chat_completions(
         resp
     };
 
+    let budget_idx = state.budgets.budget_for(&headers);
+    if let Some(idx) = budget_idx {
+        if state.budgets.is_exhausted(idx, SystemTime::now()) {
+            let budget = &state.budgets.budgets[idx];
+            state.metrics.observe_budget_rejection(&budget.name);
+            tracing::info!(
+                req_id = %req_id,
+                budget = %budget.name,
+                window = budget.window.as_str(),
+                "client budget exhausted; rejecting request"
+            );
+            return record(openai_error_response(
+                StatusCode::TOO_MANY_REQUESTS,
+                "insufficient_quota",
+                &format!(
+                    "client budget exceeded for the current {} window",
+                    budget.window.as_str()
+                ),
+                None,
+                Some("budget_exceeded"),
+            ));
+        }
+    }
+
     let body = match body {
         Ok(body) => body,
         Err(rejection) => {
@@ -597,6 +1974,13 @@ async fn chat_completions(
             Some("invalid_body"),
         ));
     }
+    // Budgeted streams are metered from a usage chunk the client did not ask for; it is
+    // requested per attempt and stripped from the relayed stream.
+    let meter_stream_usage = budget_idx.is_some()
+        && v.get("stream").and_then(Value::as_bool) == Some(true)
+        && v.pointer("/stream_options/include_usage")
+            .and_then(Value::as_bool)
+            != Some(true);
 
     let Some(model) = v.get("model").and_then(Value::as_str) else {
         return record(openai_error_response(
@@ -608,23 +1992,46 @@ async fn chat_completions(
         ));
     };
 
+    let retry_budget = match RetryLimits::for_request(&state.config, &headers) {
+        Ok(limits) => limits,
+        Err(e) => return record(e.into_response()),
+    };
+    let hints = match RoutingHints::from_headers(&headers) {
+        Ok(hints) => hints,
+        Err(e) => return record(e.into_response()),
+    };
+
     let routing_mode = routing_mode_for_model(model);
     let routed_request = matches!(
         routing_mode,
         RoutingMode::AutoPilotAlias | RoutingMode::ExplicitModelList
     );
     let add_selected_header = routed_request;
-    let apply_stickiness = routed_request;
+    // A preference order replaces stickiness and alias spreading for the request.
+    let apply_stickiness = routed_request && !hints.disable_sticky && hints.prefer.is_none();
 
+    let mut redirects = Vec::new();
     let mut candidates: Vec<String> = match routing_mode {
         RoutingMode::AutoPilotAlias => state.candidate_models().await,
         RoutingMode::ExplicitModelList | RoutingMode::Direct => {
-            let models_allowlist = state.models_allowlist().await;
-
-            let models = if routing_mode == RoutingMode::Direct {
+            let mut models = if routing_mode == RoutingMode::Direct {
                 vec![model.to_string()]
             } else {
-                match parse_model_preference_list(model, state.config.max_model_list_items) {
+                let parsed = parse_model_preference_list(model, state.config.max_model_list_items);
+                let expanded = match parsed {
+                    Ok(items) => state.expand_model_patterns(items).await,
+                    Err(e) => Err(e),
+                };
+                match expanded {
+                    Ok(models) if models.is_empty() => {
+                        return record(openai_error_response(
+                            StatusCode::SERVICE_UNAVAILABLE,
+                            "server_error",
+                            "no eligible candidates available",
+                            Some("model"),
+                            Some("no_candidates"),
+                        ));
+                    }
                     Ok(models) => models,
                     Err(e) => {
                         return record(openai_error_response(
@@ -638,51 +2045,117 @@ async fn chat_completions(
                 }
             };
 
-            // Only validate when we have an authoritative model catalog allowlist.
-            if !models_allowlist.is_empty() {
-                let unknown: Vec<String> = models
-                    .iter()
-                    .filter(|m| !models_allowlist.contains(*m))
-                    .cloned()
-                    .collect();
-                if !unknown.is_empty() {
-                    let message = if models.len() == 1 {
-                        format!("unknown model: {}", unknown[0])
-                    } else {
-                        format!(
-                            "model list contains unknown model(s): {}",
-                            unknown.join(", ")
-                        )
-                    };
+            state.resolve_chute_ids(&mut models).await;
+
+            redirects = match state.apply_model_redirects(&mut models, SystemTime::now()) {
+                Ok(applied) => applied,
+                Err(retired) => {
+                    let sunset = retired.sunset.map(|s| s.to_string()).unwrap_or_default();
                     return record(openai_error_response(
-                        StatusCode::BAD_REQUEST,
+                        StatusCode::GONE,
                         "invalid_request_error",
-                        message.as_str(),
+                        &format!(
+                            "model {} was retired on {sunset}; use {} instead",
+                            retired.from, retired.to
+                        ),
                         Some("model"),
-                        Some("unknown_model"),
+                        Some("model_retired"),
                     ));
                 }
+            };
+            for redirect in &redirects {
+                state.metrics.observe_redirect(&redirect.from, &redirect.to);
+                tracing::info!(
+                    req_id = %req_id,
+                    from = %redirect.from,
+                    to = %redirect.to,
+                    "redirected deprecated model id"
+                );
+            }
+
+            state.fold_model_case(&mut models).await;
+
+            // Only validate when we have an authoritative model catalog allowlist.
+            let unknown = state.unknown_models(&models).await;
+            if !unknown.is_empty() {
+                let mut described = Vec::with_capacity(unknown.len());
+                for model in &unknown {
+                    let suggestions = state.model_suggestions(model).await;
+                    described.push(if suggestions.is_empty() {
+                        model.clone()
+                    } else {
+                        format!("{model} (did you mean {}?)", suggestions.join(", "))
+                    });
+                }
+                let message = if models.len() == 1 {
+                    format!("unknown model: {}", described[0])
+                } else {
+                    format!(
+                        "model list contains unknown model(s): {}",
+                        described.join(", ")
+                    )
+                };
+                return record(openai_error_response(
+                    StatusCode::BAD_REQUEST,
+                    "invalid_request_error",
+                    message.as_str(),
+                    Some("model"),
+                    Some("unknown_model"),
+                ));
             }
 
             models
         }
     };
 
-    tracing::info!(
-        req_id = %req_id,
-        routing_mode = ?routing_mode,
-        candidates_len = candidates.len(),
-        "chat request validated"
-    );
+    let mut requested_models = Vec::new();
+    if routing_mode != RoutingMode::AutoPilotAlias {
+        let requested = candidates.len();
+        if state.remove_blocked_models(&mut candidates).await == requested {
+            return record(openai_error_response(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "server_error",
+                "requested model(s) are temporarily blocked by an operator override",
+                Some("model"),
+                Some("model_blocked"),
+            ));
+        }
+        let requested = candidates.len();
+        if state.remove_unattested_models(&mut candidates).await == requested {
+            return record(openai_error_response(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "server_error",
+                "requested model(s) have no TEE attestation evidence",
+                Some("model"),
+                Some("attestation_required"),
+            ));
+        }
+        candidates.retain(|model| hints.allows(model));
+        if candidates.is_empty() {
+            return record(openai_error_response(
+                StatusCode::BAD_REQUEST,
+                "invalid_request_error",
+                "routing hints exclude every requested model",
+                Some("model"),
+                Some("excluded_by_routing_hints"),
+            ));
+        }
+        requested_models = candidates.clone();
+        state.retain_primary_models(&mut candidates).await;
+    } else {
+        candidates.retain(|model| hints.allows(model));
+    }
 
-    if candidates.is_empty() {
-        return record(openai_error_response(
-            StatusCode::SERVICE_UNAVAILABLE,
-            "server_error",
-            "no eligible candidates available",
-            Some("model"),
-            Some("no_candidates"),
-        ));
+    if let Some(prefer) = hints.prefer {
+        // Operator boosts outrank client preferences: only the models after them are reordered.
+        let boosted = if routing_mode == RoutingMode::AutoPilotAlias {
+            state.boosted_prefix_len(&candidates).await
+        } else {
+            0
+        };
+        state
+            .order_by_preference(&mut candidates[boosted..], prefer)
+            .await;
     }
 
     let client_key = if apply_stickiness {
@@ -691,6 +2164,7 @@ async fn chat_completions(
         None
     };
 
+    let mut sticky_applied = false;
     if let Some(client_key) = client_key.as_ref() {
         if let Some(sticky_model) = state.sticky_model(client_key).await {
             if let Some(pos) = candidates
@@ -699,51 +2173,506 @@ async fn chat_completions(
             {
                 let sticky = candidates.remove(pos);
                 candidates.insert(0, sticky);
+                sticky_applied = true;
             } else {
                 state.clear_sticky_model(client_key).await;
             }
         }
     }
 
-    let resp = proxy_chat_completions_with_failover(
-        &state,
-        &headers,
-        &mut v,
-        &candidates,
-        add_selected_header,
-        client_key.as_ref(),
-        &req_id,
-    )
-    .await;
-
-    record(resp)
-}
-
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-enum RoutingMode {
-    AutoPilotAlias,
-    ExplicitModelList,
-    Direct,
-}
-
-fn routing_mode_for_model(model: &str) -> RoutingMode {
-    if is_autopilot_alias(model) {
-        RoutingMode::AutoPilotAlias
-    } else if model.contains(',') {
-        RoutingMode::ExplicitModelList
-    } else {
-        RoutingMode::Direct
+    if routing_mode == RoutingMode::AutoPilotAlias && !sticky_applied && hints.prefer.is_none() {
+        let seed_key = client_key.as_deref().unwrap_or(req_id.as_str());
+        state
+            .spread_alias_selection(&mut candidates, seed_key)
+            .await;
     }
-}
 
-fn upstream_chat_completions_url(config: &AppConfig) -> String {
-    format!(
-        "{}/v1/chat/completions",
-        config.backend_base_url.trim_end_matches('/')
-    )
-}
+    if routed_request {
+        state.demote_cooling_down(&mut candidates).await;
+    }
 
-fn is_hop_by_hop_header(name: &HeaderName) -> bool {
+    let mut candidates = state
+        .with_failover_backends(
+            candidates,
+            routing_mode,
+            &requested_models,
+            retry_budget.max_attempts,
+        )
+        .await;
+    candidates.retain(|candidate| hints.allows_candidate(candidate));
+    let usd_budgeted = budget_idx.is_some_and(|idx| state.budgets.budgets[idx].max_usd.is_some());
+    let offered = candidates.len();
+    if usd_budgeted
+        && offered > 0
+        && state.remove_unpriced_candidates(&mut candidates).await == offered
+    {
+        return record(openai_error_response(
+            StatusCode::BAD_REQUEST,
+            "invalid_request_error",
+            "a USD budget only covers models with catalog pricing",
+            Some("model"),
+            Some("unpriced_model"),
+        ));
+    }
+    tracing::info!(
+        req_id = %req_id,
+        routing_mode = ?routing_mode,
+        candidates_len = candidates.len(),
+        "chat request validated"
+    );
+
+    if candidates.is_empty() {
+        return record(openai_error_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "server_error",
+            "no eligible candidates available",
+            Some("model"),
+            Some("no_candidates"),
+        ));
+    }
+
+    let mut resp = proxy_chat_completions_with_failover(
+        &state,
+        &mut v,
+        ProxyRequest {
+            headers: &headers,
+            candidates: &candidates,
+            add_selected_header,
+            client_key: client_key.as_ref(),
+            routing_mode,
+            req_id: &req_id,
+            retry_budget,
+            meter_stream_usage,
+        },
+    )
+    .await;
+    insert_redirect_headers(&mut resp, &redirects);
+    let resp = start_mirrors(
+        &state,
+        resp,
+        MirrorRequest {
+            body: &v,
+            headers: &headers,
+            routing_mode,
+            req_id: &req_id,
+        },
+    );
+
+    let resp = match budget_idx {
+        Some(idx) if resp.status().is_success() => {
+            // Catalog pricing only describes models served by the primary backend.
+            let pricing = match resp.extensions().get::<UpstreamModel>() {
+                Some(UpstreamModel(Candidate { backend: 0, model })) => {
+                    state.model_pricing(model).await
+                }
+                _ => None,
+            };
+            let recorder = UsageRecorder {
+                ledger: state.budgets.clone(),
+                budget_idx: idx,
+                pricing,
+                tail: Vec::new(),
+            };
+            meter_response_usage(resp, recorder)
+        }
+        _ => resp,
+    };
+    let resp = if resp.extensions().get::<StreamUsageRequested>().is_some() {
+        strip_stream_usage_chunk(resp)
+    } else {
+        resp
+    };
+
+    record(resp)
+}
+
+/// Marks a response whose request named redirected model ids: `Deprecation` with the earliest
+/// deprecation day, the earliest `Sunset` among them, and `x-chutes-autopilot-redirected-from`
+/// with the old ids.
+fn insert_redirect_headers(resp: &mut Response, redirects: &[&ModelRedirect]) {
+    if redirects.is_empty() {
+        return;
+    }
+    let from: Vec<&str> = redirects.iter().map(|r| r.from.as_str()).collect();
+    let headers = resp.headers_mut();
+    if let Some(deprecated) = redirects.iter().filter_map(|r| r.deprecated).min() {
+        if let Ok(value) = HeaderValue::from_str(&format!("@{}", deprecated.unix_secs())) {
+            headers.insert("deprecation", value);
+        }
+    }
+    if let Some(sunset) = redirects.iter().filter_map(|r| r.sunset).min() {
+        if let Ok(value) = HeaderValue::from_str(&sunset.http_date()) {
+            headers.insert("sunset", value);
+        }
+    }
+    if let Ok(value) = HeaderValue::from_str(&from.join(",")) {
+        headers.insert("x-chutes-autopilot-redirected-from", value);
+    }
+}
+
+/// Bearer token guarding the `/admin` API. `Debug` is redacted so configs can be logged safely.
+#[derive(Clone, PartialEq, Eq)]
+pub struct AdminToken(pub String);
+
+impl std::fmt::Debug for AdminToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("AdminToken(<redacted>)")
+    }
+}
+
+const DEFAULT_OVERRIDE_TTL: Duration = Duration::from_secs(3_600);
+const MAX_OVERRIDE_TTL: Duration = Duration::from_secs(7 * 24 * 3_600);
+
+/// Operator action applied on top of the ranked candidate snapshot.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum OverrideAction {
+    /// Remove the model from the AutoPilot pool; explicit lists and direct requests still use it.
+    Drain,
+    /// Route the AutoPilot alias to this model only (the most recent pin wins).
+    Pin,
+    /// Refuse the model in every routing mode.
+    Block,
+    /// Move the model to the front of the AutoPilot ranking, even if it is not ranked yet.
+    Boost,
+}
+
+impl OverrideAction {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Drain => "drain",
+            Self::Pin => "pin",
+            Self::Block => "block",
+            Self::Boost => "boost",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ModelOverride {
+    id: u64,
+    action: OverrideAction,
+    model: String,
+    reason: String,
+    created_at: SystemTime,
+    ttl: Duration,
+    expires_at: Instant,
+}
+
+impl ModelOverride {
+    fn is_active(&self, now: Instant) -> bool {
+        self.expires_at > now
+    }
+
+    fn to_json(&self, now: Instant) -> Value {
+        let unix_secs = |t: SystemTime| t.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        json!({
+            "id": self.id,
+            "action": self.action.as_str(),
+            "model": self.model,
+            "reason": self.reason,
+            "created_at": unix_secs(self.created_at),
+            "expires_at": unix_secs(self.created_at + self.ttl),
+            "expires_in_secs": self.expires_at.saturating_duration_since(now).as_secs(),
+        })
+    }
+}
+
+/// Applies active overrides to the ranked AutoPilot candidate list.
+fn apply_alias_overrides(
+    overrides: &[ModelOverride],
+    candidates: Vec<String>,
+    now: Instant,
+) -> Vec<String> {
+    let active: Vec<&ModelOverride> = overrides.iter().filter(|o| o.is_active(now)).collect();
+    if active.is_empty() {
+        return candidates;
+    }
+    let has = |action: OverrideAction, model: &str| {
+        active
+            .iter()
+            .any(|o| o.action == action && o.model == model)
+    };
+
+    if let Some(pin) = active
+        .iter()
+        .filter(|o| o.action == OverrideAction::Pin && !has(OverrideAction::Block, &o.model))
+        .max_by_key(|o| o.id)
+    {
+        return vec![pin.model.clone()];
+    }
+
+    let mut boosted: Vec<String> = Vec::new();
+    for o in active.iter().filter(|o| o.action == OverrideAction::Boost) {
+        if !boosted.contains(&o.model) {
+            boosted.push(o.model.clone());
+        }
+    }
+    let rest: Vec<String> = candidates
+        .into_iter()
+        .filter(|candidate| !boosted.contains(candidate))
+        .collect();
+
+    boosted
+        .into_iter()
+        .chain(rest)
+        .filter(|candidate| {
+            !has(OverrideAction::Drain, candidate) && !has(OverrideAction::Block, candidate)
+        })
+        .collect()
+}
+
+/// Removes blocked models from an explicit list or direct request, returning how many were dropped.
+fn remove_blocked_models(
+    overrides: &[ModelOverride],
+    candidates: &mut Vec<String>,
+    now: Instant,
+) -> usize {
+    let before = candidates.len();
+    candidates.retain(|candidate| {
+        !overrides
+            .iter()
+            .any(|o| o.action == OverrideAction::Block && o.model == *candidate && o.is_active(now))
+    });
+    before - candidates.len()
+}
+
+/// Override management routes for [`admin_app`]. Disabled unless `ADMIN_TOKEN` is set.
+fn admin_router() -> Router<AppState> {
+    let mut router = Router::new()
+        .route("/admin/overrides", get(list_overrides))
+        .route("/admin/overrides/:id", delete(delete_override));
+    for action in [
+        OverrideAction::Drain,
+        OverrideAction::Pin,
+        OverrideAction::Block,
+        OverrideAction::Boost,
+    ] {
+        router = router.route(
+            &format!("/admin/overrides/{}", action.as_str()),
+            post(move |state, headers, body| create_override(state, headers, body, action)),
+        );
+    }
+    router
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn authorize_admin(state: &AppState, headers: &HeaderMap) -> Result<(), Box<Response>> {
+    let Some(AdminToken(expected)) = state.config.admin_token.as_ref() else {
+        return Err(Box::new(openai_error_response(
+            StatusCode::NOT_FOUND,
+            "not_found_error",
+            "admin API is disabled (set ADMIN_TOKEN)",
+            None,
+            Some("admin_disabled"),
+        )));
+    };
+
+    let presented = headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::trim)
+        .unwrap_or_default();
+    if !constant_time_eq(presented.as_bytes(), expected.as_bytes()) {
+        return Err(Box::new(openai_error_response(
+            StatusCode::UNAUTHORIZED,
+            "authentication_error",
+            "invalid admin token",
+            None,
+            Some("invalid_admin_token"),
+        )));
+    }
+
+    Ok(())
+}
+
+async fn list_overrides(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    if let Err(resp) = authorize_admin(&state, &headers) {
+        return *resp;
+    }
+
+    let now = Instant::now();
+    let mut runtime = state.runtime.write().await;
+    runtime.overrides.retain(|o| o.is_active(now));
+    let data: Vec<Value> = runtime.overrides.iter().map(|o| o.to_json(now)).collect();
+    Json(json!({ "object": "list", "data": data })).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+struct CreateOverrideRequest {
+    model: String,
+    reason: String,
+    #[serde(default)]
+    ttl_secs: Option<u64>,
+}
+
+async fn create_override(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+    action: OverrideAction,
+) -> Response {
+    if let Err(resp) = authorize_admin(&state, &headers) {
+        return *resp;
+    }
+
+    let invalid = |message: &str, param: &str| {
+        openai_error_response(
+            StatusCode::BAD_REQUEST,
+            "invalid_request_error",
+            message,
+            Some(param),
+            Some("invalid_override"),
+        )
+    };
+
+    let Ok(req) = serde_json::from_slice::<CreateOverrideRequest>(&body) else {
+        return invalid(
+            "body must be a JSON object with string fields `model` and `reason`",
+            "body",
+        );
+    };
+
+    let model = req.model.trim().to_string();
+    if model.is_empty() || is_autopilot_alias(&model) || model.contains(',') {
+        return invalid("model must be a single model id", "model");
+    }
+    let reason = req.reason.trim().to_string();
+    if reason.is_empty() {
+        return invalid("reason is required", "reason");
+    }
+    let ttl = req
+        .ttl_secs
+        .map_or(DEFAULT_OVERRIDE_TTL, Duration::from_secs);
+    if ttl.is_zero() || ttl > MAX_OVERRIDE_TTL {
+        return invalid(
+            &format!(
+                "ttl_secs must be between 1 and {}",
+                MAX_OVERRIDE_TTL.as_secs()
+            ),
+            "ttl_secs",
+        );
+    }
+
+    let now = Instant::now();
+    let mut runtime = state.runtime.write().await;
+    // Pins and boosts may add models to the pool, so they must at least be chat-capable.
+    if matches!(action, OverrideAction::Pin | OverrideAction::Boost)
+        && !is_model_catalog_eligible(&model, &runtime.models_allowlist)
+    {
+        return invalid(&format!("unknown model: {model}"), "model");
+    }
+
+    runtime.overrides.retain(|o| o.is_active(now));
+    runtime.next_override_id += 1;
+    let entry = ModelOverride {
+        id: runtime.next_override_id,
+        action,
+        model,
+        reason,
+        created_at: SystemTime::now(),
+        ttl,
+        expires_at: now + ttl,
+    };
+    tracing::info!(
+        target: "audit",
+        override_id = entry.id,
+        action = action.as_str(),
+        model = %entry.model,
+        reason = %entry.reason,
+        ttl_secs = ttl.as_secs(),
+        "admin override created"
+    );
+    let body = entry.to_json(now);
+    runtime.overrides.push(entry);
+
+    (StatusCode::CREATED, Json(body)).into_response()
+}
+
+async fn delete_override(
+    State(state): State<AppState>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    if let Err(resp) = authorize_admin(&state, &headers) {
+        return *resp;
+    }
+
+    let now = Instant::now();
+    let mut runtime = state.runtime.write().await;
+    runtime.overrides.retain(|o| o.is_active(now));
+    let Some(pos) = id
+        .parse::<u64>()
+        .ok()
+        .and_then(|id| runtime.overrides.iter().position(|o| o.id == id))
+    else {
+        return openai_error_response(
+            StatusCode::NOT_FOUND,
+            "not_found_error",
+            &format!("no active override with id {id}"),
+            Some("id"),
+            Some("override_not_found"),
+        );
+    };
+
+    let removed = runtime.overrides.remove(pos);
+    tracing::info!(
+        target: "audit",
+        override_id = removed.id,
+        action = removed.action.as_str(),
+        model = %removed.model,
+        reason = %removed.reason,
+        "admin override deleted"
+    );
+    StatusCode::NO_CONTENT.into_response()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum RoutingMode {
+    AutoPilotAlias,
+    ExplicitModelList,
+    Direct,
+}
+
+fn routing_mode_for_model(model: &str) -> RoutingMode {
+    if is_autopilot_alias(model) {
+        RoutingMode::AutoPilotAlias
+    } else if model.contains(',') || ModelPattern::parse(model).is_some() {
+        RoutingMode::ExplicitModelList
+    } else {
+        RoutingMode::Direct
+    }
+}
+
+fn upstream_chat_completions_url(config: &AppConfig, backend: usize) -> String {
+    let base_url = match backend.checked_sub(1) {
+        None => &config.backend_base_url,
+        Some(idx) => &config.backends[idx].base_url,
+    };
+    format!("{}/v1/chat/completions", base_url.trim_end_matches('/'))
+}
+
+/// Request headers for one backend. Additional backends get their own credentials instead of
+/// the client's, which are meant for the primary backend.
+fn backend_request_headers(config: &AppConfig, backend: usize, headers: &HeaderMap) -> HeaderMap {
+    let Some(idx) = backend.checked_sub(1) else {
+        return headers.clone();
+    };
+    let mut out = headers.clone();
+    out.remove(axum::http::header::AUTHORIZATION);
+    if let Some(api_key) = &config.backends[idx].api_key {
+        if let Ok(mut value) = HeaderValue::from_str(&format!("Bearer {api_key}")) {
+            value.set_sensitive(true);
+            out.insert(axum::http::header::AUTHORIZATION, value);
+        }
+    }
+    out
+}
+
+fn is_hop_by_hop_header(name: &HeaderName) -> bool {
     use axum::http::header;
 
     name == header::CONNECTION
@@ -816,1309 +2745,5883 @@ where
     resp
 }
 
-fn log_selected_model(
-    add_selected_header: bool,
-    model_name: &str,
-    attempt_idx: usize,
-    candidates_total: usize,
-    status: StatusCode,
-    snapshot_age_ms: Option<u64>,
-    req_id: &str,
-) {
-    if !add_selected_header {
+/// Names the backend that served the response once additional backends are configured.
+fn insert_backend_header(resp: &mut Response, config: &AppConfig, backend_name: &str) {
+    if config.backends.is_empty() {
         return;
     }
-
-    tracing::info!(
-        req_id = %req_id,
-        selected_model = %model_name,
-        attempt_idx,
-        candidates_total,
-        upstream_status = status.as_u16(),
-        snapshot_age_ms = ?snapshot_age_ms,
-        "selected upstream chute"
-    );
+    if let Ok(value) = HeaderValue::from_str(backend_name) {
+        resp.headers_mut()
+            .insert("x-chutes-autopilot-backend", value);
+    }
 }
 
-async fn maybe_set_sticky_model(
-    state: &AppState,
-    client_key: Option<&String>,
+/// Upstream error bodies larger than this are passed through untouched.
+const MAX_NORMALIZED_ERROR_BODY_BYTES: usize = 64 * 1024;
+const MAX_NORMALIZED_ERROR_MESSAGE_CHARS: usize = 512;
+
+/// Rewrites a non-OpenAI-shaped upstream 4xx/5xx body (nginx HTML, FastAPI `{"detail": ...}`,
+/// plain text) into the OpenAI `ErrorResponse` shape, keeping the upstream status and a trimmed
+/// copy of the original detail. Only a body read completely is rewritten: OpenAI-shaped,
+/// oversized, timed-out or failed reads are relayed unchanged (the bytes read so far, then the
+/// rest of the upstream stream).
+async fn normalize_upstream_error(
     status: StatusCode,
-    model_name: &str,
-) {
-    let Some(key) = client_key else {
-        return;
+    upstream_headers: &HeaderMap,
+    upstream: reqwest::Response,
+    selected_model: Option<&str>,
+    read_timeout: Duration,
+) -> Response {
+    let mut body_stream = upstream.bytes_stream();
+    let mut buf: Vec<u8> = Vec::new();
+    let mut read_error: Option<reqwest::Error> = None;
+    let complete = loop {
+        match tokio::time::timeout(read_timeout, body_stream.next()).await {
+            Ok(Some(Ok(chunk))) => {
+                buf.extend_from_slice(&chunk);
+                if buf.len() > MAX_NORMALIZED_ERROR_BODY_BYTES {
+                    break false;
+                }
+            }
+            Ok(None) => break true,
+            Ok(Some(Err(err))) => {
+                read_error = Some(err);
+                break false;
+            }
+            Err(_) => break false,
+        }
     };
-    if !status.is_success() {
-        return;
+
+    if !complete || is_openai_error_body(&buf) {
+        let prefix = stream::once(async move { Ok::<Bytes, std::io::Error>(Bytes::from(buf)) });
+        let rest = stream::iter(read_error.map(Err))
+            .chain(body_stream)
+            .map(|item| item.map_err(map_reqwest_stream_error));
+        return streaming_response(status, upstream_headers, prefix.chain(rest), selected_model);
     }
 
-    state
-        .set_sticky_model(key.clone(), model_name.to_owned())
-        .await;
+    let message = upstream_error_detail(&buf)
+        .unwrap_or_else(|| format!("upstream returned HTTP {}", status.as_u16()));
+    let mut resp = openai_error_response(
+        status,
+        openai_error_type_for_status(status),
+        &message,
+        None,
+        Some("upstream_error"),
+    );
+    for (name, value) in upstream_headers.iter() {
+        use axum::http::header;
+        if is_hop_by_hop_header(name)
+            || name == header::CONTENT_TYPE
+            || name == header::CONTENT_LENGTH
+            || name == header::CONTENT_ENCODING
+        {
+            continue;
+        }
+        resp.headers_mut().append(name, value.clone());
+    }
+    if let Some(value) = selected_model.and_then(|m| HeaderValue::from_str(m).ok()) {
+        resp.headers_mut()
+            .insert("x-chutes-autopilot-selected", value);
+    }
+    resp
 }
 
-async fn proxy_chat_completions_with_failover(
-    state: &AppState,
-    headers: &HeaderMap,
-    body_json: &mut Value,
-    candidates: &[String],
-    add_selected_header: bool,
-    client_key: Option<&String>,
-    req_id: &str,
-) -> Response {
-    let url = upstream_chat_completions_url(&state.config);
-    let upstream_headers = filter_upstream_request_headers(headers);
-    let snapshot_age_ms = state
-        .runtime
-        .read()
-        .await
-        .snapshot_at
-        .map(|instant| instant.elapsed().as_millis() as u64);
-    let metrics = state.metrics.clone();
+fn is_openai_error_body(body: &[u8]) -> bool {
+    serde_json::from_slice::<Value>(body).is_ok_and(|v| {
+        v.get("error")
+            .and_then(|e| e.get("message"))
+            .is_some_and(Value::is_string)
+    })
+}
 
-    // Only rotate sticky selection when we have a client key. Centralizing this avoids repeating
-    // the same `if let Some(key)` guard all over the retry paths.
-    let rotate_sticky = |failed_model: String| async move {
-        if let Some(key) = client_key {
-            state
-                .rotate_sticky_model(key, candidates, failed_model.as_str())
-                .await;
-        }
+/// Extracts a human-readable detail from an upstream error body.
+fn upstream_error_detail(body: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(body);
+    let raw = match serde_json::from_slice::<Value>(body) {
+        Ok(v) => match v.get("detail").or_else(|| v.get("message")) {
+            Some(Value::String(detail)) => detail.clone(),
+            Some(Value::Array(items)) => items
+                .iter()
+                .filter_map(|item| item.get("msg").and_then(Value::as_str))
+                .collect::<Vec<_>>()
+                .join("; "),
+            Some(other) => other.to_string(),
+            None => v.to_string(),
+        },
+        Err(_) => html_title(&text).unwrap_or_else(|| strip_html_tags(&text)),
     };
 
-    for (idx, model_name) in candidates.iter().enumerate() {
-        let has_next = idx + 1 < candidates.len();
+    let collapsed = raw.split_whitespace().collect::<Vec<_>>().join(" ");
+    if collapsed.is_empty() {
+        return None;
+    }
+    Some(
+        match collapsed
+            .char_indices()
+            .nth(MAX_NORMALIZED_ERROR_MESSAGE_CHARS)
+        {
+            Some((end, _)) => format!("{}...", &collapsed[..end]),
+            None => collapsed,
+        },
+    )
+}
 
-        let Some(map) = body_json.as_object_mut() else {
-            return openai_error_response(
-                StatusCode::BAD_REQUEST,
-                "invalid_request_error",
-                "request body must be a JSON object",
-                None,
-                Some("invalid_body"),
-            );
-        };
-        map.insert("model".to_string(), json!(model_name));
+fn html_title(text: &str) -> Option<String> {
+    let lower = text.to_ascii_lowercase();
+    let start = lower.find("<title>")? + "<title>".len();
+    let end = start + lower[start..].find("</title>")?;
+    Some(text[start..end].to_string())
+}
 
-        let body_bytes = match serde_json::to_vec(body_json) {
-            Ok(bytes) => bytes,
-            Err(_) => {
-                return openai_error_response(
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "server_error",
-                    "failed to serialize request body",
-                    None,
-                    Some("serialization_error"),
-                );
+fn strip_html_tags(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut in_tag = false;
+    for c in text.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                out.push(' ');
             }
-        };
-
-        let req = state
-            .http_client
-            .post(&url)
-            .headers(upstream_headers.clone())
-            .body(body_bytes);
-
-        let upstream =
-            match tokio::time::timeout(state.config.upstream_header_timeout, req.send()).await {
-                Err(_) => {
-                    rotate_sticky(model_name.clone()).await;
-                    metrics.observe_failover("upstream_header_timeout");
-
-                    if has_next {
-                        tracing::warn!(
-                            req_id = %req_id,
-                            failed_model = %model_name,
-                            attempt_idx = idx,
-                            candidates_total = candidates.len(),
-                            snapshot_age_ms = ?snapshot_age_ms,
-                            reason = "upstream_header_timeout",
-                            "retryable upstream failure; attempting failover"
-                        );
-                        continue;
-                    }
-
-                    return openai_error_response(
-                        StatusCode::GATEWAY_TIMEOUT,
-                        "server_error",
-                        "upstream timeout waiting for response headers",
-                        None,
-                        Some("upstream_header_timeout"),
-                    );
-                }
-                Ok(Err(_)) => {
-                    rotate_sticky(model_name.clone()).await;
-                    metrics.observe_failover("upstream_connect_error");
+            _ if !in_tag => out.push(c),
+            _ => {}
+        }
+    }
+    out
+}
 
-                    if has_next {
-                        tracing::warn!(
-                            req_id = %req_id,
-                            failed_model = %model_name,
-                            attempt_idx = idx,
-                            candidates_total = candidates.len(),
-                            snapshot_age_ms = ?snapshot_age_ms,
-                            reason = "upstream_connect_error",
-                            "retryable upstream failure; attempting failover"
-                        );
-                        continue;
-                    }
+fn openai_error_type_for_status(status: StatusCode) -> &'static str {
+    match status {
+        StatusCode::UNAUTHORIZED => "authentication_error",
+        StatusCode::FORBIDDEN => "permission_error",
+        StatusCode::NOT_FOUND => "not_found_error",
+        StatusCode::TOO_MANY_REQUESTS => "rate_limit_error",
+        s if s.is_server_error() => "server_error",
+        _ => "invalid_request_error",
+    }
+}
 
-                    return openai_error_response(
-                        StatusCode::BAD_GATEWAY,
-                        "server_error",
-                        "upstream request failed",
-                        None,
-                        Some("upstream_connect_error"),
-                    );
+/// Picks an index into the top-K `scores` (ranked order) from a deterministic `seed`.
+///
+/// Candidates without a score (e.g. boosted models not in the snapshot) count as the best score.
+fn spread_choice(mode: SelectionMode, scores: &[Option<f64>], seed: u64) -> usize {
+    let best = scores.iter().flatten().copied().fold(0.0_f64, f64::max);
+    let weights: Vec<f64> = scores.iter().map(|s| s.unwrap_or(best).max(0.0)).collect();
+
+    match mode {
+        SelectionMode::Top => 0,
+        SelectionMode::Weighted => {
+            let total: f64 = weights.iter().sum();
+            if total <= 0.0 {
+                return 0;
+            }
+            // The top 53 bits give a uniform fraction in [0, 1).
+            let mut target = (seed >> 11) as f64 / (1u64 << 53) as f64 * total;
+            for (idx, weight) in weights.iter().enumerate() {
+                if target < *weight {
+                    return idx;
                 }
-                Ok(Ok(resp)) => resp,
-            };
+                target -= weight;
+            }
+            weights.len() - 1
+        }
+        SelectionMode::PowerOfTwo => {
+            let n = weights.len() as u64;
+            let a = (seed % n) as usize;
+            let b = ((a as u64 + 1 + (seed / n) % (n - 1)) % n) as usize;
+            // Ties go to the better-ranked candidate.
+            if weights[b] > weights[a] || (weights[b] == weights[a] && b < a) {
+                b
+            } else {
+                a
+            }
+        }
+    }
+}
 
-        let status = upstream.status();
-        let upstream_resp_headers = upstream.headers().clone();
+/// The backend/model that served a request, with its attestation status when checks are on.
+struct SelectedUpstream<'a> {
+    backend: &'a str,
+    model: &'a str,
+    chute_id: Option<String>,
+    attestation: Option<&'static str>,
+}
 
-        // Retryable upstream status before committing bytes.
-        if status == StatusCode::SERVICE_UNAVAILABLE && has_next {
-            rotate_sticky(model_name.clone()).await;
-            metrics.observe_failover("upstream_503");
-            tracing::warn!(
-                req_id = %req_id,
-                failed_model = %model_name,
-                attempt_idx = idx,
-                candidates_total = candidates.len(),
-                snapshot_age_ms = ?snapshot_age_ms,
-                reason = "upstream_503",
-                "retryable upstream failure; attempting failover"
+/// Adds the backend, body-adjustment, chute-id and attestation headers for the candidate that
+/// served a request and returns what to log about it. Chute-id and attestation headers are
+/// routed-request only, like `x-chutes-autopilot-selected`.
+async fn annotate_selected_response<'a>(
+    state: &'a AppState,
+    resp: &mut Response,
+    candidate: &'a Candidate,
+    add_selected_header: bool,
+    adjustments: &AttemptAdjustments,
+) -> SelectedUpstream<'a> {
+    let backend = state.config.backend_name(candidate.backend);
+    insert_backend_header(resp, &state.config, backend);
+    if !adjustments.stripped_params.is_empty() {
+        if let Ok(value) = HeaderValue::from_str(&adjustments.stripped_params.join(",")) {
+            resp.headers_mut()
+                .insert("x-chutes-autopilot-stripped-params", value);
+        }
+    }
+    if let Some(limit) = adjustments.max_tokens_clamped {
+        resp.headers_mut().insert(
+            "x-chutes-autopilot-max-tokens-clamped",
+            HeaderValue::from(limit),
+        );
+    }
+    if adjustments.stream_usage_requested {
+        resp.extensions_mut().insert(StreamUsageRequested);
+    }
+    let chute_id = state.chute_id_for(candidate).await;
+    let attestation = state.attestation_label(candidate).await;
+    if add_selected_header {
+        if let Some(value) = chute_id
+            .as_deref()
+            .and_then(|id| HeaderValue::from_str(id).ok())
+        {
+            resp.headers_mut()
+                .insert("x-chutes-autopilot-selected-chute-id", value);
+        }
+        if let Some(label) = attestation {
+            resp.headers_mut().insert(
+                "x-chutes-autopilot-attestation",
+                HeaderValue::from_static(label),
             );
-            continue;
         }
+    }
+    SelectedUpstream {
+        backend,
+        model: &candidate.model,
+        chute_id,
+        attestation,
+    }
+}
 
-        // For successful responses, delay commitment until we see the first body chunk.
-        // This keeps first-byte timeout enforcement consistent for both retry and single-candidate
-        // paths so requests do not hang indefinitely before any bytes are sent.
-        if status.is_success() {
-            let mut body_stream = upstream.bytes_stream();
-            match tokio::time::timeout(
-                state.config.upstream_first_body_byte_timeout,
-                body_stream.next(),
-            )
-            .await
-            {
-                Err(_) | Ok(None) => {
-                    rotate_sticky(model_name.clone()).await;
-                    metrics.observe_failover("upstream_first_body_byte_timeout");
-                    if has_next {
-                        tracing::warn!(
-                            req_id = %req_id,
-                            failed_model = %model_name,
-                            attempt_idx = idx,
-                            candidates_total = candidates.len(),
-                            snapshot_age_ms = ?snapshot_age_ms,
-                            reason = "upstream_first_body_byte_timeout",
-                            "retryable upstream failure; attempting failover"
-                        );
-                        continue;
-                    }
-
-                    return openai_error_response(
-                        StatusCode::GATEWAY_TIMEOUT,
-                        "server_error",
-                        "upstream timeout waiting for first response byte",
-                        None,
-                        Some("upstream_first_body_byte_timeout"),
-                    );
-                }
-                Ok(Some(Err(_))) => {
-                    rotate_sticky(model_name.clone()).await;
-                    metrics.observe_failover("upstream_first_body_byte_error");
-                    if has_next {
-                        tracing::warn!(
-                            req_id = %req_id,
-                            failed_model = %model_name,
-                            attempt_idx = idx,
-                            candidates_total = candidates.len(),
-                            snapshot_age_ms = ?snapshot_age_ms,
-                            reason = "upstream_first_body_byte_error",
-                            "retryable upstream failure; attempting failover"
-                        );
-                        continue;
-                    }
-
-                    return openai_error_response(
-                        StatusCode::BAD_GATEWAY,
-                        "server_error",
-                        "upstream response body error before first byte",
-                        None,
-                        Some("upstream_first_body_byte_error"),
-                    );
-                }
-                Ok(Some(Ok(first_chunk))) => {
-                    maybe_set_sticky_model(state, client_key, status, model_name).await;
+fn log_selected_model(
+    add_selected_header: bool,
+    selected: SelectedUpstream<'_>,
+    attempt_idx: usize,
+    candidates_total: usize,
+    status: StatusCode,
+    snapshot_age_ms: Option<u64>,
+    req_id: &str,
+) {
+    if !add_selected_header {
+        return;
+    }
 
-                    let rest = body_stream.map(|item| item.map_err(map_reqwest_stream_error));
-                    let combined =
-                        stream::once(async move { Ok::<Bytes, std::io::Error>(first_chunk) })
-                            .chain(rest);
+    tracing::info!(
+        req_id = %req_id,
+        selected_model = %selected.model,
+        chute_id = ?selected.chute_id,
+        backend = selected.backend,
+        attestation = selected.attestation,
+        attempt_idx,
+        candidates_total,
+        upstream_status = status.as_u16(),
+        snapshot_age_ms = ?snapshot_age_ms,
+        "selected upstream chute"
+    );
+}
 
-                    let selected_model_header = add_selected_header.then_some(model_name.as_str());
-                    let resp = streaming_response(
-                        status,
-                        &upstream_resp_headers,
-                        combined,
-                        selected_model_header,
-                    );
-                    metrics.observe_selection(model_name, status);
-                    log_selected_model(
-                        add_selected_header,
-                        model_name,
-                        idx,
-                        candidates.len(),
-                        status,
-                        snapshot_age_ms,
-                        req_id,
-                    );
-                    return resp;
-                }
-            }
-        }
+async fn maybe_set_sticky_model(
+    state: &AppState,
+    client_key: Option<&String>,
+    status: StatusCode,
+    model_name: &str,
+) {
+    let Some(key) = client_key else {
+        return;
+    };
+    if !status.is_success() {
+        return;
+    }
 
-        maybe_set_sticky_model(state, client_key, status, model_name).await;
+    state
+        .set_sticky_model(key.clone(), model_name.to_owned())
+        .await;
+}
 
-        let stream = upstream
-            .bytes_stream()
-            .map(|item| item.map_err(map_reqwest_stream_error));
+/// Parses a delta-seconds `Retry-After` header into a cooldown, capped at `max`.
+fn retry_after_cooldown(headers: &HeaderMap, max: Duration) -> Option<Duration> {
+    let secs = headers
+        .get(axum::http::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    (secs > 0).then(|| Duration::from_secs(secs).min(max))
+}
 
-        let selected_model_header = add_selected_header.then_some(model_name.as_str());
-        let resp = streaming_response(
-            status,
-            &upstream_resp_headers,
-            stream,
-            selected_model_header,
-        );
-        metrics.observe_selection(model_name, status);
-        log_selected_model(
-            add_selected_header,
-            model_name,
-            idx,
-            candidates.len(),
-            status,
-            snapshot_age_ms,
-            req_id,
-        );
-        return resp;
-    }
+type UpstreamSend = Result<reqwest::Result<reqwest::Response>, tokio::time::error::Elapsed>;
 
-    openai_error_response(
-        StatusCode::SERVICE_UNAVAILABLE,
-        "server_error",
-        "no eligible candidates available",
-        Some("model"),
-        Some("no_candidates"),
-    )
+fn is_streaming_request(body_json: &Value) -> bool {
+    body_json
+        .get("stream")
+        .and_then(Value::as_bool)
+        .unwrap_or(false)
 }
 
-fn parse_leftmost_x_forwarded_for(headers: &HeaderMap) -> Option<IpAddr> {
-    let raw = headers.get("x-forwarded-for")?.to_str().ok()?;
-    let first = raw.split(',').next()?.trim();
-    if first.is_empty() {
-        return None;
-    }
-    if let Ok(ip) = first.parse::<IpAddr>() {
-        return Some(ip);
-    }
-    if let Ok(addr) = first.parse::<SocketAddr>() {
-        return Some(addr.ip());
-    }
-    None
+/// Request fields the sanitizer may strip: every name the Chutes catalog uses in
+/// `supported_sampling_parameters`. Parameters the catalog never lists (e.g. `min_p`,
+/// `logit_bias`) say nothing about support either way, so they are always forwarded.
+const SAMPLING_PARAMETERS: [&str; 8] = [
+    "temperature",
+    "top_p",
+    "top_k",
+    "repetition_penalty",
+    "frequency_penalty",
+    "presence_penalty",
+    "stop",
+    "seed",
+];
+
+/// Output-length fields the clamp lowers to the candidate's `max_output_length`.
+const MAX_TOKENS_FIELDS: [&str; 2] = ["max_tokens", "max_completion_tokens"];
+
+/// Catalog limits for one candidate, from [`AppState::attempt_limits`].
+#[derive(Debug, Default)]
+struct AttemptLimits {
+    /// `supported_sampling_parameters`, when sanitizing.
+    sampling_params: Option<HashSet<String>>,
+    /// `max_output_length`, when clamping.
+    max_output_length: Option<u64>,
+    /// Ask for a usage chunk at the end of the stream, to meter a client budget.
+    stream_usage: bool,
 }
 
-fn is_trusted_proxy(peer_ip: IpAddr, trusted: &[IpNet]) -> bool {
-    trusted.iter().any(|net| net.contains(&peer_ip))
+/// What [`attempt_body`] changed for one attempt, reported on the response it produced.
+#[derive(Debug, Default)]
+struct AttemptAdjustments {
+    stripped_params: Vec<&'static str>,
+    /// The limit `max_tokens`/`max_completion_tokens` was lowered to.
+    max_tokens_clamped: Option<u64>,
+    /// `stream_options.include_usage` was added; the usage chunk is not the client's to see.
+    stream_usage_requested: bool,
 }
 
-fn requester_ip_for_stickiness(
-    config: &AppConfig,
-    headers: &HeaderMap,
-    connect_info: &Option<ConnectInfo<SocketAddr>>,
-) -> Option<IpAddr> {
-    let peer_ip = connect_info.as_ref().map(|ConnectInfo(addr)| addr.ip())?;
-
-    if !config.trust_proxy_headers {
-        return Some(peer_ip);
-    }
+/// Rewrites `model` for one upstream attempt and serializes the body. Sampling parameters outside
+/// `limits.sampling_params` are left out and output-length fields above
+/// `limits.max_output_length` are lowered to it, and streamed requests get
+/// `stream_options.include_usage` when `limits.stream_usage` is set. Those changes apply to this
+/// attempt's bytes only; `body_json` keeps the client's values for later candidates.
+fn attempt_body(
+    body_json: &mut Value,
+    model_name: &str,
+    limits: &AttemptLimits,
+) -> Result<(Vec<u8>, AttemptAdjustments), Box<Response>> {
+    let Some(map) = body_json.as_object_mut() else {
+        return Err(Box::new(openai_error_response(
+            StatusCode::BAD_REQUEST,
+            "invalid_request_error",
+            "request body must be a JSON object",
+            None,
+            Some("invalid_body"),
+        )));
+    };
+    map.insert("model".to_string(), json!(model_name));
+
+    let stripped_params: Vec<&'static str> =
+        limits
+            .sampling_params
+            .as_ref()
+            .map_or_else(Vec::new, |supported| {
+                SAMPLING_PARAMETERS
+                    .into_iter()
+                    .filter(|param| map.contains_key(*param) && !supported.contains(*param))
+                    .collect()
+            });
+    let max_tokens_clamped = limits.max_output_length.filter(|limit| {
+        MAX_TOKENS_FIELDS
+            .iter()
+            .any(|field| map.get(*field).and_then(Value::as_u64) > Some(*limit))
+    });
+    let stream_usage_requested =
+        limits.stream_usage && map.get("stream").and_then(Value::as_bool) == Some(true);
+    let adjustments = AttemptAdjustments {
+        stripped_params,
+        max_tokens_clamped,
+        stream_usage_requested,
+    };
 
-    if !is_trusted_proxy(peer_ip, &config.trusted_proxy_cidrs) {
-        return Some(peer_ip);
-    }
+    let serialized = if adjustments.stripped_params.is_empty()
+        && max_tokens_clamped.is_none()
+        && !stream_usage_requested
+    {
+        serde_json::to_vec(body_json)
+    } else {
+        tracing::debug!(
+            model = %model_name,
+            stripped = ?adjustments.stripped_params,
+            max_tokens_clamped = ?max_tokens_clamped,
+            "adjusting request body to the model's catalog limits"
+        );
+        let mut adjusted = map.clone();
+        for param in &adjustments.stripped_params {
+            adjusted.remove(*param);
+        }
+        if let Some(limit) = max_tokens_clamped {
+            for field in MAX_TOKENS_FIELDS {
+                if let Some(value) = adjusted.get_mut(field) {
+                    if value.as_u64() > Some(limit) {
+                        *value = json!(limit);
+                    }
+                }
+            }
+        }
+        if stream_usage_requested {
+            request_stream_usage(&mut adjusted);
+        }
+        serde_json::to_vec(&adjusted)
+    };
 
-    Some(parse_leftmost_x_forwarded_for(headers).unwrap_or(peer_ip))
+    let bytes = serialized.map_err(|_| {
+        Box::new(openai_error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "server_error",
+            "failed to serialize request body",
+            None,
+            Some("serialization_error"),
+        ))
+    })?;
+    Ok((bytes, adjustments))
 }
 
-fn derive_sticky_key(
-    config: &AppConfig,
-    headers: &HeaderMap,
-    connect_info: &Option<ConnectInfo<SocketAddr>>,
-) -> Option<String> {
-    if let Some(auth) = headers
-        .get("authorization")
-        .and_then(|value| value.to_str().ok())
-    {
-        let token = auth.trim().strip_prefix("Bearer").unwrap_or(auth).trim();
-
-        if !token.is_empty() {
-            let mut hasher = DefaultHasher::new();
-            token.hash(&mut hasher);
-            return Some(format!("auth:{:016x}", hasher.finish()));
-        }
+async fn send_upstream_attempt(
+    state: &AppState,
+    model: Option<&str>,
+    url: &str,
+    upstream_headers: &HeaderMap,
+    body_bytes: Vec<u8>,
+    header_timeout: Duration,
+) -> UpstreamSend {
+    let req = state
+        .http_client
+        .post(url)
+        .headers(upstream_headers.clone())
+        .body(body_bytes);
+
+    let started = Instant::now();
+    let sent = tokio::time::timeout(header_timeout, req.send()).await;
+    if let Ok(Ok(_)) = &sent {
+        state.observe_header_latency(model, started.elapsed());
     }
-
-    requester_ip_for_stickiness(config, headers, connect_info).map(|ip| format!("ip:{ip}"))
+    sent
 }
 
-fn is_autopilot_alias(model: &str) -> bool {
-    model == "chutesai/AutoPilot"
+enum HedgeWinner {
+    Primary(UpstreamSend),
+    Hedge(UpstreamSend),
 }
 
-fn is_model_catalog_eligible(model: &str, models_allowlist: &HashSet<String>) -> bool {
-    if models_allowlist.is_empty() {
-        // Conservative fallback when we don't have an authoritative model catalog yet.
-        model.ends_with("-TEE")
-    } else {
-        models_allowlist.contains(model)
+/// Races an attempt against a hedge sent to the next candidate once `delay` passes without
+/// response headers. The first attempt to produce usable response headers wins and the other is
+/// dropped, which cancels its in-flight request. If one side fails before producing headers or
+/// answers with a status in `retryable`, the other one is awaited instead. Returns the winner and
+/// whether the hedge was fired, in which case both candidates have been attempted.
+///
+/// Any other response counts as a win, including `429`: a hedge is only ever fired while the
+/// primary is still silent, so a rate-limited request is never duplicated onto another chute.
+async fn send_with_hedge<P, H>(
+    primary: P,
+    hedge: H,
+    delay: Duration,
+    retryable: &[StatusCode],
+    metrics: &Metrics,
+) -> (HedgeWinner, bool)
+where
+    P: Future<Output = UpstreamSend>,
+    H: Future<Output = UpstreamSend>,
+{
+    tokio::pin!(primary);
+    tokio::select! {
+        sent = &mut primary => return (HedgeWinner::Primary(sent), false),
+        _ = tokio::time::sleep(delay) => {}
     }
-}
 
-async fn refresh_models_allowlist(state: AppState) {
-    let client = state.http_client.clone();
-    loop {
-        if let Ok(allowlist) = fetch_models_allowlist(
-            &client,
-            &state.config.models_url,
-            state.config.control_plane_timeout,
-        )
-        .await
-        {
-            let mut runtime = state.runtime.write().await;
-            runtime.models_allowlist = allowlist;
-            runtime.models_allowlist_at = Some(Instant::now());
+    metrics.observe_hedge_fired();
+    let usable = |sent: &UpstreamSend| match sent {
+        Ok(Ok(resp)) => {
+            let status = resp.status();
+            !(status.is_server_error() && retryable.contains(&status))
         }
-
-        tokio::time::sleep(state.config.models_refresh_ms).await;
+        _ => false,
+    };
+    tokio::pin!(hedge);
+    let winner = tokio::select! {
+        sent = &mut primary => if usable(&sent) {
+            HedgeWinner::Primary(sent)
+        } else {
+            HedgeWinner::Hedge(hedge.await)
+        },
+        sent = &mut hedge => if usable(&sent) {
+            HedgeWinner::Hedge(sent)
+        } else {
+            HedgeWinner::Primary(primary.await)
+        },
+    };
+    if matches!(winner, HedgeWinner::Hedge(_)) {
+        metrics.observe_hedge_win();
     }
+    (winner, true)
 }
 
-async fn refresh_candidates(state: AppState) {
-    let client = state.http_client.clone();
-    loop {
-        let models_allowlist = state.runtime.read().await.models_allowlist.clone();
+const MAX_ATTEMPTS_HEADER: &str = "x-chutes-autopilot-max-attempts";
+const RETRY_BUDGET_HEADER: &str = "x-chutes-autopilot-retry-budget-ms";
 
-        let candidates = fetch_ranked_candidates(
-            &client,
-            &state.config.utilization_url,
-            &models_allowlist,
-            state.config.control_plane_timeout,
-        )
-        .await;
-        state.update_candidate_snapshot(candidates).await;
+/// A request header that Autopilot interprets had an unusable value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct InvalidHeader {
+    name: &'static str,
+    message: String,
+}
 
-        tokio::time::sleep(state.config.utilization_refresh_ms).await;
+impl InvalidHeader {
+    fn into_response(self) -> Response {
+        openai_error_response(
+            StatusCode::BAD_REQUEST,
+            "invalid_request_error",
+            &self.message,
+            Some(self.name),
+            Some("invalid_header"),
+        )
     }
 }
 
-async fn fetch_models_allowlist(
-    client: &Client,
-    url: &str,
-    timeout: Duration,
-) -> anyhow::Result<HashSet<String>> {
-    let response = client
-        .get(url)
-        .timeout(timeout)
-        .send()
-        .await?
-        .error_for_status()?;
-    let payload = response.json::<OpenAiModelListResponse>().await?;
-    Ok(payload.data.into_iter().map(|m| m.id).collect())
+/// Per-request cap on upstream attempts and on total wall-clock time spent before committing a
+/// response. Request headers may lower the configured limits but never raise them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct RetryLimits {
+    max_attempts: usize,
+    budget: Option<Duration>,
 }
 
-async fn fetch_ranked_candidates(
-    client: &Client,
-    url: &str,
-    models_allowlist: &HashSet<String>,
-    timeout: Duration,
-) -> anyhow::Result<Vec<RankedCandidate>> {
-    let utilizations: Vec<UtilizationRecord> = client
-        .get(url)
-        .timeout(timeout)
-        .send()
-        .await?
-        .error_for_status()?
-        .json::<Vec<UtilizationRecord>>()
-        .await?;
+impl RetryLimits {
+    fn for_request(config: &AppConfig, headers: &HeaderMap) -> Result<Self, InvalidHeader> {
+        let mut limits = Self {
+            max_attempts: config.max_upstream_attempts.max(1),
+            budget: (!config.upstream_retry_budget.is_zero())
+                .then_some(config.upstream_retry_budget),
+        };
 
-    Ok(rank_candidates(utilizations, models_allowlist))
-}
+        if let Some(max_attempts) = parse_limit_header(headers, MAX_ATTEMPTS_HEADER)? {
+            limits.max_attempts = limits.max_attempts.min(max_attempts as usize);
+        }
 
-fn rank_candidates(
-    records: Vec<UtilizationRecord>,
-    models_allowlist: &HashSet<String>,
-) -> Vec<RankedCandidate> {
-    let mut ranked: Vec<RankedCandidate> = records
-        .into_iter()
-        .filter(|record| !record.is_private_chute())
-        .filter(|record| record.active_instance_count > 0)
-        .filter(|record| is_model_catalog_eligible(&record.name, models_allowlist))
-        .map(RankedCandidate::from)
-        .collect();
+        if let Some(ms) = parse_limit_header(headers, RETRY_BUDGET_HEADER)? {
+            let requested = Duration::from_millis(ms);
+            limits.budget = Some(limits.budget.map_or(requested, |b| b.min(requested)));
+        }
 
-    sort_ranked_candidates(&mut ranked);
+        Ok(limits)
+    }
 
-    ranked
+    fn start(self) -> RetryBudget {
+        RetryBudget {
+            max_attempts: self.max_attempts,
+            deadline: self.budget.map(|budget| Instant::now() + budget),
+        }
+    }
 }
 
-fn sort_ranked_candidates(ranked: &mut [RankedCandidate]) {
-    ranked.sort_by(|a, b| {
-        b.score
-            .total_cmp(&a.score)
-            .then_with(|| b.active_instance_count.cmp(&a.active_instance_count))
-            .then_with(|| a.utilization_current.total_cmp(&b.utilization_current))
-            .then_with(|| a.rate_limit_ratio_5m.total_cmp(&b.rate_limit_ratio_5m))
-            .then_with(|| a.name.cmp(&b.name))
-    });
-}
+const EXCLUDE_HEADER: &str = "x-chutes-autopilot-exclude";
+const REQUIRE_TEE_HEADER: &str = "x-chutes-autopilot-require-tee";
+const STICKY_HEADER: &str = "x-chutes-autopilot-sticky";
+const PREFER_HEADER: &str = "x-chutes-autopilot-prefer";
 
-#[derive(Debug, Clone)]
-struct RankedCandidate {
-    name: String,
-    active_instance_count: u64,
-    utilization_current: f64,
-    rate_limit_ratio_5m: f64,
-    score: f64,
+/// Candidate order requested with `x-chutes-autopilot-prefer`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum RoutePreference {
+    /// Lowest moving-average response-header latency first.
+    Latency,
+    /// Lowest catalog prompt + completion price first.
+    Cost,
 }
 
-impl From<UtilizationRecord> for RankedCandidate {
-    fn from(record: UtilizationRecord) -> Self {
-        let u5 = record
-            .utilization_5m
-            .or(record.utilization_current)
-            .unwrap_or(1.0);
-        let u15 = record.utilization_15m.unwrap_or(u5);
-        let u1h = record.utilization_1h.unwrap_or(u15);
-        let utilization_current = record.utilization_current.unwrap_or(u5);
-        let util = 0.6 * u5 + 0.3 * u15 + 0.1 * u1h;
-
-        let rate_limit_ratio_5m = record.rate_limit_ratio_5m.unwrap_or(0.0);
-        let rate_limit_ratio_15m = record.rate_limit_ratio_15m.unwrap_or(rate_limit_ratio_5m);
-        let rate_limit_ratio_1h = record.rate_limit_ratio_1h.unwrap_or(rate_limit_ratio_15m);
+/// Per-request routing hints from `x-chutes-autopilot-*` headers. Like [`RetryLimits`], they can
+/// only narrow or reorder the candidates a request would otherwise use.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct RoutingHints {
+    exclude: HashSet<String>,
+    require_tee: bool,
+    disable_sticky: bool,
+    prefer: Option<RoutePreference>,
+}
 
-        let free_capacity = record.active_instance_count as f64 * (1.0 - util).max(0.0);
-        let scale_allowance = if record.scalable {
-            record.scale_allowance.unwrap_or(0.0).min(8.0) * 0.05
-        } else {
-            0.0
-        };
-        let throttle_signal = rate_limit_ratio_5m
-            .max(0.5 * rate_limit_ratio_15m)
-            .max(0.25 * rate_limit_ratio_1h);
-        let score = free_capacity + scale_allowance
-            - (record.active_instance_count as f64 * throttle_signal * 2.0);
+impl RoutingHints {
+    fn from_headers(headers: &HeaderMap) -> Result<Self, InvalidHeader> {
+        let mut hints = Self::default();
 
-        Self {
-            name: record.name,
-            active_instance_count: record.active_instance_count,
-            utilization_current,
-            rate_limit_ratio_5m,
-            score,
+        if let Some(raw) = header_str(headers, EXCLUDE_HEADER)? {
+            hints.exclude = raw
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(ToString::to_string)
+                .collect();
+            if hints.exclude.is_empty() {
+                return Err(InvalidHeader {
+                    name: EXCLUDE_HEADER,
+                    message: format!("{EXCLUDE_HEADER} must list at least one model"),
+                });
+            }
+        }
+        if let Some(required) = parse_bool_header(headers, REQUIRE_TEE_HEADER)? {
+            hints.require_tee = required;
+        }
+        if let Some(sticky) = parse_bool_header(headers, STICKY_HEADER)? {
+            hints.disable_sticky = !sticky;
+        }
+        if let Some(raw) = header_str(headers, PREFER_HEADER)? {
+            hints.prefer = Some(match raw.trim().to_ascii_lowercase().as_str() {
+                "latency" => RoutePreference::Latency,
+                "cost" => RoutePreference::Cost,
+                _ => {
+                    return Err(InvalidHeader {
+                        name: PREFER_HEADER,
+                        message: format!("{PREFER_HEADER} must be one of: latency, cost"),
+                    })
+                }
+            });
         }
+
+        Ok(hints)
     }
-}
 
-#[derive(Debug, Deserialize)]
-struct OpenAiModelListResponse {
-    data: Vec<OpenAiModelItem>,
-}
+    fn allows(&self, model: &str) -> bool {
+        !self.exclude.contains(model) && (!self.require_tee || model.ends_with("-TEE"))
+    }
 
-#[derive(Debug, Deserialize)]
-struct OpenAiModelItem {
-    id: String,
+    /// Additional backends serve no TEE models, so `require_tee` rules them out entirely.
+    fn allows_candidate(&self, candidate: &Candidate) -> bool {
+        self.allows(&candidate.model) && !(self.require_tee && candidate.backend != 0)
+    }
 }
 
-#[derive(Debug, Deserialize)]
-struct UtilizationRecord {
-    name: String,
-    #[serde(default)]
-    active_instance_count: u64,
-    #[serde(default)]
-    utilization_current: Option<f64>,
-    #[serde(default)]
-    utilization_5m: Option<f64>,
-    #[serde(default)]
-    utilization_15m: Option<f64>,
-    #[serde(default)]
-    utilization_1h: Option<f64>,
-    #[serde(default)]
-    rate_limit_ratio_5m: Option<f64>,
-    #[serde(default)]
-    rate_limit_ratio_15m: Option<f64>,
-    #[serde(default)]
-    rate_limit_ratio_1h: Option<f64>,
-    #[serde(default)]
-    scalable: bool,
-    #[serde(default)]
-    scale_allowance: Option<f64>,
+fn header_str<'a>(
+    headers: &'a HeaderMap,
+    name: &'static str,
+) -> Result<Option<&'a str>, InvalidHeader> {
+    headers
+        .get(name)
+        .map(|raw| {
+            raw.to_str().map_err(|_| InvalidHeader {
+                name,
+                message: format!("{name} must be valid ASCII"),
+            })
+        })
+        .transpose()
 }
 
-impl UtilizationRecord {
-    fn is_private_chute(&self) -> bool {
-        self.name == "[private chute]"
+fn parse_bool_header(
+    headers: &HeaderMap,
+    name: &'static str,
+) -> Result<Option<bool>, InvalidHeader> {
+    let Some(raw) = header_str(headers, name)? else {
+        return Ok(None);
+    };
+    match raw.trim().to_ascii_lowercase().as_str() {
+        "true" => Ok(Some(true)),
+        "false" => Ok(Some(false)),
+        _ => Err(InvalidHeader {
+            name,
+            message: format!("{name} must be true or false"),
+        }),
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-struct ModelListError {
-    code: String,
-    message: String,
+/// Parses a retry limit header. `0` is rejected: it would allow no attempt at all.
+fn parse_limit_header(
+    headers: &HeaderMap,
+    name: &'static str,
+) -> Result<Option<u64>, InvalidHeader> {
+    let Some(raw) = headers.get(name) else {
+        return Ok(None);
+    };
+    raw.to_str()
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .filter(|limit| *limit > 0)
+        .map(Some)
+        .ok_or_else(|| InvalidHeader {
+            name,
+            message: format!("{name} must be a positive integer"),
+        })
 }
 
-fn parse_model_preference_list(
-    model: &str,
-    max_items: usize,
-) -> Result<Vec<String>, ModelListError> {
-    let mut out: Vec<String> = Vec::new();
-    let mut seen: HashSet<String> = HashSet::new();
+/// Running retry limits for one request.
+#[derive(Clone, Copy, Debug)]
+struct RetryBudget {
+    max_attempts: usize,
+    deadline: Option<Instant>,
+}
 
-    for raw in model.split(',') {
-        let item = raw.trim_matches(|c: char| c.is_ascii_whitespace());
-        if item.is_empty() {
-            continue;
+impl RetryBudget {
+    /// Returns why no further attempt may start after `attempts` have been made, if exhausted.
+    fn exhausted(&self, attempts: usize) -> Option<&'static str> {
+        if attempts >= self.max_attempts {
+            return Some("max attempts reached");
         }
-        if seen.insert(item.to_string()) {
-            out.push(item.to_string());
+        if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            return Some("deadline reached");
+        }
+        None
+    }
+
+    /// Shortens a per-attempt timeout so it never runs past the overall deadline.
+    fn clamp(&self, timeout: Duration) -> Duration {
+        match self.deadline {
+            Some(deadline) => timeout.min(deadline.saturating_duration_since(Instant::now())),
+            None => timeout,
         }
     }
+}
+
+/// Per-request routing inputs for `proxy_chat_completions_with_failover`.
+struct ProxyRequest<'a> {
+    headers: &'a HeaderMap,
+    candidates: &'a [Candidate],
+    add_selected_header: bool,
+    client_key: Option<&'a String>,
+    routing_mode: RoutingMode,
+    req_id: &'a str,
+    retry_budget: RetryLimits,
+    /// Ask backends that accept it for a usage chunk at the end of the stream.
+    meter_stream_usage: bool,
+}
+
+async fn proxy_chat_completions_with_failover(
+    state: &AppState,
+    body_json: &mut Value,
+    request: ProxyRequest<'_>,
+) -> Response {
+    let ProxyRequest {
+        headers,
+        candidates,
+        add_selected_header,
+        client_key,
+        routing_mode,
+        req_id,
+        retry_budget,
+        meter_stream_usage,
+    } = request;
+    let retry_budget = retry_budget.start();
+    let retryable_statuses = state.config.retryable_statuses.for_mode(routing_mode);
+    let upstream_headers = filter_upstream_request_headers(headers);
+    let send_attempt = |candidate: &Candidate, body_bytes: Vec<u8>, header_timeout: Duration| {
+        let url = upstream_chat_completions_url(&state.config, candidate.backend);
+        let headers = backend_request_headers(&state.config, candidate.backend, &upstream_headers);
+        let model = (candidate.backend == 0).then(|| candidate.model.clone());
+        async move {
+            send_upstream_attempt(
+                state,
+                model.as_deref(),
+                &url,
+                &headers,
+                body_bytes,
+                header_timeout,
+            )
+            .await
+        }
+    };
+    let snapshot_age_ms = state
+        .runtime
+        .read()
+        .await
+        .snapshot_at
+        .map(|instant| instant.elapsed().as_millis() as u64);
+    let metrics = state.metrics.clone();
+
+    // Only rotate sticky selection when we have a client key. Centralizing this avoids repeating
+    // the same `if let Some(key)` guard all over the retry paths.
+    // Stickiness and cooldowns track primary-backend models only, so clients return to the
+    // primary as soon as it recovers.
+    let primary_models: Vec<String> = candidates
+        .iter()
+        .filter(|candidate| candidate.backend == 0)
+        .map(|candidate| candidate.model.clone())
+        .collect();
+    let primary_models = &primary_models;
+    let rotate_sticky = |failed: &Candidate| {
+        let failed_model = (failed.backend == 0).then(|| failed.model.clone());
+        async move {
+            if let (Some(key), Some(failed_model)) = (client_key, failed_model) {
+                state
+                    .rotate_sticky_model(key, primary_models, failed_model.as_str())
+                    .await;
+            }
+        }
+    };
+
+    let hedge_delay =
+        (state.config.hedge_enabled && add_selected_header && !is_streaming_request(body_json))
+            .then(|| state.hedge_delay());
+
+    // Attempts that were already consumed by a winning hedge are skipped by the loop.
+    let mut resume_at = 0;
+    for (primary_idx, primary_candidate) in candidates.iter().enumerate() {
+        if primary_idx < resume_at {
+            continue;
+        }
+        // Every index below `primary_idx` has been attempted (directly or as a hedge), so the
+        // index doubles as the number of attempts made so far.
+        if let Some(reason) = retry_budget.exhausted(primary_idx) {
+            metrics.observe_failover("retry_budget_exhausted");
+            tracing::warn!(
+                req_id = %req_id,
+                attempts = primary_idx,
+                candidates_total = candidates.len(),
+                snapshot_age_ms = ?snapshot_age_ms,
+                reason,
+                "retry budget exhausted; not attempting further candidates"
+            );
+            return openai_error_response(
+                StatusCode::GATEWAY_TIMEOUT,
+                "server_error",
+                &format!("upstream retry budget exhausted ({reason})"),
+                None,
+                Some("retry_budget_exhausted"),
+            );
+        }
+        let header_timeout = retry_budget.clamp(state.config.upstream_header_timeout);
+
+        let limits = state
+            .attempt_limits(primary_candidate, meter_stream_usage)
+            .await;
+        let (body_bytes, primary_adjustments) =
+            match attempt_body(body_json, &primary_candidate.model, &limits) {
+                Ok(body) => body,
+                Err(resp) => return *resp,
+            };
+        let primary = send_attempt(primary_candidate, body_bytes, header_timeout);
+
+        let hedge_idx = primary_idx + 1;
+        let hedge_allowed =
+            hedge_idx < candidates.len() && retry_budget.exhausted(hedge_idx).is_none();
+        let mut hedge_adjustments = AttemptAdjustments::default();
+        let mut hedge_fired = false;
+        let (idx, sent) = match hedge_delay.filter(|_| hedge_allowed) {
+            Some(delay) => {
+                let limits = state
+                    .attempt_limits(&candidates[hedge_idx], meter_stream_usage)
+                    .await;
+                let hedge_body =
+                    match attempt_body(body_json, &candidates[hedge_idx].model, &limits) {
+                        Ok((bytes, adjustments)) => {
+                            hedge_adjustments = adjustments;
+                            bytes
+                        }
+                        Err(resp) => return *resp,
+                    };
+                let hedge = send_attempt(&candidates[hedge_idx], hedge_body, header_timeout);
+                let (winner, fired) =
+                    send_with_hedge(primary, hedge, delay, retryable_statuses, &metrics).await;
+                hedge_fired = fired;
+                match winner {
+                    HedgeWinner::Primary(sent) => (primary_idx, sent),
+                    HedgeWinner::Hedge(sent) => {
+                        tracing::info!(
+                            req_id = %req_id,
+                            slow_model = %primary_candidate.model,
+                            hedge_model = %candidates[hedge_idx].model,
+                            hedge_delay_ms = delay.as_millis() as u64,
+                            "hedged request won"
+                        );
+                        (hedge_idx, sent)
+                    }
+                }
+            }
+            None => (primary_idx, primary.await),
+        };
+        // A fired hedge has already sent the request to `hedge_idx`; never send it there twice.
+        resume_at = if hedge_fired { hedge_idx + 1 } else { idx + 1 };
+        let adjustments = if idx == primary_idx {
+            primary_adjustments
+        } else {
+            hedge_adjustments
+        };
+        let candidate = &candidates[idx];
+        let model_name = &candidate.model;
+        let backend_name = state.config.backend_name(candidate.backend);
+        let has_next = resume_at < candidates.len();
+
+        let upstream = match sent {
+            Err(_) => {
+                rotate_sticky(candidate).await;
+                metrics.observe_failover("upstream_header_timeout");
+
+                if has_next {
+                    tracing::warn!(
+                        req_id = %req_id,
+                        failed_model = %model_name,
+                        backend = backend_name,
+                        attempt_idx = idx,
+                        candidates_total = candidates.len(),
+                        snapshot_age_ms = ?snapshot_age_ms,
+                        reason = "upstream_header_timeout",
+                        "retryable upstream failure; attempting failover"
+                    );
+                    continue;
+                }
+
+                return openai_error_response(
+                    StatusCode::GATEWAY_TIMEOUT,
+                    "server_error",
+                    "upstream timeout waiting for response headers",
+                    None,
+                    Some("upstream_header_timeout"),
+                );
+            }
+            Ok(Err(_)) => {
+                rotate_sticky(candidate).await;
+                metrics.observe_failover("upstream_connect_error");
+
+                if has_next {
+                    tracing::warn!(
+                        req_id = %req_id,
+                        failed_model = %model_name,
+                        backend = backend_name,
+                        attempt_idx = idx,
+                        candidates_total = candidates.len(),
+                        snapshot_age_ms = ?snapshot_age_ms,
+                        reason = "upstream_connect_error",
+                        "retryable upstream failure; attempting failover"
+                    );
+                    continue;
+                }
+
+                return openai_error_response(
+                    StatusCode::BAD_GATEWAY,
+                    "server_error",
+                    "upstream request failed",
+                    None,
+                    Some("upstream_connect_error"),
+                );
+            }
+            Ok(Ok(resp)) => resp,
+        };
+
+        let status = upstream.status();
+        let upstream_resp_headers = upstream.headers().clone();
+
+        // A 503 with `Retry-After` is the upstream telling every client to back off this model.
+        if status == StatusCode::SERVICE_UNAVAILABLE && candidate.backend == 0 {
+            if let Some(cooldown) = retry_after_cooldown(
+                &upstream_resp_headers,
+                state.config.retry_after_max_cooldown,
+            ) {
+                state.set_model_cooldown(model_name, cooldown).await;
+            }
+        }
+
+        // Retryable upstream status before committing bytes.
+        if has_next && status.is_server_error() && retryable_statuses.contains(&status) {
+            let reason = format!("upstream_{}", status.as_u16());
+            rotate_sticky(candidate).await;
+            metrics.observe_failover(&reason);
+            tracing::warn!(
+                req_id = %req_id,
+                failed_model = %model_name,
+                backend = backend_name,
+                attempt_idx = idx,
+                candidates_total = candidates.len(),
+                snapshot_age_ms = ?snapshot_age_ms,
+                reason = %reason,
+                "retryable upstream failure; attempting failover"
+            );
+            continue;
+        }
+
+        // For successful responses, delay commitment until we see the first body chunk.
+        // This keeps first-byte timeout enforcement consistent for both retry and single-candidate
+        // paths so requests do not hang indefinitely before any bytes are sent.
+        if status.is_success() {
+            let mut body_stream = upstream.bytes_stream();
+            match tokio::time::timeout(
+                retry_budget.clamp(state.config.upstream_first_body_byte_timeout),
+                body_stream.next(),
+            )
+            .await
+            {
+                Err(_) | Ok(None) => {
+                    rotate_sticky(candidate).await;
+                    metrics.observe_failover("upstream_first_body_byte_timeout");
+                    if has_next {
+                        tracing::warn!(
+                            req_id = %req_id,
+                            failed_model = %model_name,
+                            backend = backend_name,
+                            attempt_idx = idx,
+                            candidates_total = candidates.len(),
+                            snapshot_age_ms = ?snapshot_age_ms,
+                            reason = "upstream_first_body_byte_timeout",
+                            "retryable upstream failure; attempting failover"
+                        );
+                        continue;
+                    }
+
+                    return openai_error_response(
+                        StatusCode::GATEWAY_TIMEOUT,
+                        "server_error",
+                        "upstream timeout waiting for first response byte",
+                        None,
+                        Some("upstream_first_body_byte_timeout"),
+                    );
+                }
+                Ok(Some(Err(_))) => {
+                    rotate_sticky(candidate).await;
+                    metrics.observe_failover("upstream_first_body_byte_error");
+                    if has_next {
+                        tracing::warn!(
+                            req_id = %req_id,
+                            failed_model = %model_name,
+                            backend = backend_name,
+                            attempt_idx = idx,
+                            candidates_total = candidates.len(),
+                            snapshot_age_ms = ?snapshot_age_ms,
+                            reason = "upstream_first_body_byte_error",
+                            "retryable upstream failure; attempting failover"
+                        );
+                        continue;
+                    }
+
+                    return openai_error_response(
+                        StatusCode::BAD_GATEWAY,
+                        "server_error",
+                        "upstream response body error before first byte",
+                        None,
+                        Some("upstream_first_body_byte_error"),
+                    );
+                }
+                Ok(Some(Ok(first_chunk))) => {
+                    if candidate.backend == 0 {
+                        maybe_set_sticky_model(state, client_key, status, model_name).await;
+                    }
+
+                    let rest = body_stream.map(|item| item.map_err(map_reqwest_stream_error));
+                    let combined =
+                        stream::once(async move { Ok::<Bytes, std::io::Error>(first_chunk) })
+                            .chain(rest);
+
+                    let selected_model_header = add_selected_header.then_some(model_name.as_str());
+                    let mut resp = streaming_response(
+                        status,
+                        &upstream_resp_headers,
+                        combined,
+                        selected_model_header,
+                    );
+                    let selected = annotate_selected_response(
+                        state,
+                        &mut resp,
+                        candidate,
+                        add_selected_header,
+                        &adjustments,
+                    )
+                    .await;
+                    resp.extensions_mut()
+                        .insert(UpstreamModel(candidate.clone()));
+                    metrics.observe_selection(backend_name, model_name, status);
+                    log_selected_model(
+                        add_selected_header,
+                        selected,
+                        idx,
+                        candidates.len(),
+                        status,
+                        snapshot_age_ms,
+                        req_id,
+                    );
+                    return resp;
+                }
+            }
+        }
+
+        if candidate.backend == 0 {
+            maybe_set_sticky_model(state, client_key, status, model_name).await;
+        }
+
+        let selected_model_header = add_selected_header.then_some(model_name.as_str());
+        let is_error_status = status.is_client_error() || status.is_server_error();
+        let mut resp = if state.config.normalize_upstream_errors && is_error_status {
+            normalize_upstream_error(
+                status,
+                &upstream_resp_headers,
+                upstream,
+                selected_model_header,
+                retry_budget.clamp(state.config.upstream_first_body_byte_timeout),
+            )
+            .await
+        } else {
+            let stream = upstream
+                .bytes_stream()
+                .map(|item| item.map_err(map_reqwest_stream_error));
+            streaming_response(
+                status,
+                &upstream_resp_headers,
+                stream,
+                selected_model_header,
+            )
+        };
+        let selected = annotate_selected_response(
+            state,
+            &mut resp,
+            candidate,
+            add_selected_header,
+            &adjustments,
+        )
+        .await;
+        resp.extensions_mut()
+            .insert(UpstreamModel(candidate.clone()));
+        metrics.observe_selection(backend_name, model_name, status);
+        log_selected_model(
+            add_selected_header,
+            selected,
+            idx,
+            candidates.len(),
+            status,
+            snapshot_age_ms,
+            req_id,
+        );
+        return resp;
+    }
+
+    openai_error_response(
+        StatusCode::SERVICE_UNAVAILABLE,
+        "server_error",
+        "no eligible candidates available",
+        Some("model"),
+        Some("no_candidates"),
+    )
+}
+
+fn parse_leftmost_x_forwarded_for(headers: &HeaderMap) -> Option<IpAddr> {
+    let raw = headers.get("x-forwarded-for")?.to_str().ok()?;
+    let first = raw.split(',').next()?.trim();
+    if first.is_empty() {
+        return None;
+    }
+    if let Ok(ip) = first.parse::<IpAddr>() {
+        return Some(ip);
+    }
+    if let Ok(addr) = first.parse::<SocketAddr>() {
+        return Some(addr.ip());
+    }
+    None
+}
+
+fn is_trusted_proxy(peer_ip: IpAddr, trusted: &[IpNet]) -> bool {
+    trusted.iter().any(|net| net.contains(&peer_ip))
+}
+
+fn requester_ip_for_stickiness(
+    config: &AppConfig,
+    headers: &HeaderMap,
+    connect_info: &Option<ConnectInfo<SocketAddr>>,
+) -> Option<IpAddr> {
+    let peer_ip = connect_info.as_ref().map(|ConnectInfo(addr)| addr.ip())?;
+
+    if !config.trust_proxy_headers {
+        return Some(peer_ip);
+    }
+
+    if !is_trusted_proxy(peer_ip, &config.trusted_proxy_cidrs) {
+        return Some(peer_ip);
+    }
+
+    Some(parse_leftmost_x_forwarded_for(headers).unwrap_or(peer_ip))
+}
+
+fn derive_sticky_key(
+    config: &AppConfig,
+    headers: &HeaderMap,
+    connect_info: &Option<ConnectInfo<SocketAddr>>,
+) -> Option<String> {
+    if let Some(key) = auth_token_key(headers) {
+        return Some(key);
+    }
+
+    requester_ip_for_stickiness(config, headers, connect_info).map(|ip| format!("ip:{ip}"))
+}
+
+/// Returns a one-way key for the request's bearer token, if any.
+fn auth_token_key(headers: &HeaderMap) -> Option<String> {
+    let auth = headers
+        .get("authorization")
+        .and_then(|value| value.to_str().ok())?;
+    let token = auth.trim().strip_prefix("Bearer").unwrap_or(auth).trim();
+    if token.is_empty() {
+        return None;
+    }
+    Some(hash_auth_token(token))
+}
+
+fn hash_auth_token(token: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    token.hash(&mut hasher);
+    format!("auth:{:016x}", hasher.finish())
+}
+
+/// Calendar window (UTC) after which a client budget resets.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetWindow {
+    Daily,
+    Monthly,
+}
+
+impl BudgetWindow {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Daily => "daily",
+            Self::Monthly => "monthly",
+        }
+    }
+
+    /// Returns a monotonically increasing period id for `now` (days or months since the epoch).
+    fn period_at(self, now: SystemTime) -> u64 {
+        let days = now
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() / 86_400)
+            .unwrap_or(0);
+        match self {
+            Self::Daily => days,
+            Self::Monthly => {
+                let (year, month) = civil_year_month(days);
+                year * 12 + (month - 1)
+            }
+        }
+    }
+}
+
+/// Converts a proleptic Gregorian date to days since 1970-01-01 (inverse of [`civil_year_month`]).
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Converts days since 1970-01-01 to a (year, month) pair in the proleptic Gregorian calendar.
+fn civil_year_month(days: u64) -> (u64, u64) {
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year.max(0) as u64, month as u64)
+}
+
+/// Hard spending ceiling for a single client API key.
+///
+/// `key` is the raw bearer token as handed out to the client; only its hash is kept at runtime.
+#[derive(Clone, Deserialize)]
+pub struct ClientBudget {
+    pub name: String,
+    pub key: String,
+    pub window: BudgetWindow,
+    #[serde(default)]
+    pub max_tokens: Option<u64>,
+    #[serde(default)]
+    pub max_usd: Option<f64>,
+}
+
+impl std::fmt::Debug for ClientBudget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClientBudget")
+            .field("name", &self.name)
+            .field("key", &"<redacted>")
+            .field("window", &self.window)
+            .field("max_tokens", &self.max_tokens)
+            .field("max_usd", &self.max_usd)
+            .finish()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+struct BudgetSpend {
+    period: u64,
+    tokens: u64,
+    usd: f64,
+}
+
+/// In-memory spend counters for configured client budgets, optionally mirrored to a local file
+/// so restarts do not reset a window.
+///
+/// Spend is charged when a response finishes, so concurrent in-flight requests from one key are
+/// all admitted and can together overshoot its ceiling by their combined usage.
+struct BudgetLedger {
+    budgets: Vec<ClientBudget>,
+    by_key: HashMap<String, usize>,
+    spend: Mutex<HashMap<String, BudgetSpend>>,
+    state_path: Option<PathBuf>,
+    /// Set while a state file write is queued; charges in the meantime ride along with it.
+    flush_pending: AtomicBool,
+    /// Serializes state file writes so an older snapshot never replaces a newer one.
+    flush_lock: Mutex<()>,
+}
+
+impl BudgetLedger {
+    fn new(budgets: &[ClientBudget], state_path: Option<PathBuf>) -> Self {
+        let by_key = budgets
+            .iter()
+            .enumerate()
+            .map(|(idx, budget)| (hash_auth_token(budget.key.trim()), idx))
+            .collect();
+
+        let spend = state_path
+            .as_ref()
+            .and_then(|path| match std::fs::read(path) {
+                Ok(bytes) => match serde_json::from_slice(&bytes) {
+                    Ok(spend) => Some(spend),
+                    Err(err) => {
+                        tracing::warn!(error = %err, "ignoring unreadable client budget state file");
+                        None
+                    }
+                },
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+                Err(err) => {
+                    tracing::warn!(error = %err, "failed to read client budget state file");
+                    None
+                }
+            })
+            .unwrap_or_default();
+
+        Self {
+            budgets: budgets.to_vec(),
+            by_key,
+            spend: Mutex::new(spend),
+            state_path,
+            flush_pending: AtomicBool::new(false),
+            flush_lock: Mutex::new(()),
+        }
+    }
+
+    fn budget_for(&self, headers: &HeaderMap) -> Option<usize> {
+        if self.by_key.is_empty() {
+            return None;
+        }
+        self.by_key.get(&auth_token_key(headers)?).copied()
+    }
+
+    fn is_exhausted(&self, idx: usize, now: SystemTime) -> bool {
+        let budget = &self.budgets[idx];
+        let period = budget.window.period_at(now);
+        let spend = self.spend.lock().expect("budget ledger poisoned");
+        let Some(current) = spend.get(&budget.name).filter(|s| s.period == period) else {
+            return false;
+        };
+
+        budget.max_tokens.is_some_and(|max| current.tokens >= max)
+            || budget.max_usd.is_some_and(|max| current.usd >= max)
+    }
+
+    fn charge(
+        &self,
+        idx: usize,
+        usage: TokenUsage,
+        pricing: Option<ModelPricing>,
+        now: SystemTime,
+    ) {
+        let budget = &self.budgets[idx];
+        let period = budget.window.period_at(now);
+        let usd = pricing.map(|p| usage.cost_usd(p)).unwrap_or(0.0);
+
+        let mut spend = self.spend.lock().expect("budget ledger poisoned");
+        let entry = spend.entry(budget.name.clone()).or_default();
+        if entry.period != period {
+            *entry = BudgetSpend {
+                period,
+                ..Default::default()
+            };
+        }
+        entry.tokens = entry.tokens.saturating_add(usage.tokens());
+        entry.usd += usd;
+    }
+
+    /// Queues a write of the spend counters to the state file on the blocking pool, keeping file
+    /// IO off the response path. At most one write is queued at a time.
+    fn schedule_flush(self: &Arc<Self>) {
+        if self.state_path.is_none() || self.flush_pending.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        let ledger = self.clone();
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                handle.spawn_blocking(move || ledger.flush());
+            }
+            Err(_) => ledger.flush(),
+        }
+    }
+
+    fn flush(&self) {
+        let Some(path) = self.state_path.as_ref() else {
+            return;
+        };
+        let _writer = self.flush_lock.lock().expect("budget flush lock poisoned");
+        self.flush_pending.store(false, Ordering::Release);
+        let spend = self.spend.lock().expect("budget ledger poisoned").clone();
+        if let Err(err) = persist_budget_state(path, &spend) {
+            tracing::warn!(error = %err, "failed to persist client budget state");
+        }
+    }
+}
+
+fn persist_budget_state(
+    path: &std::path::Path,
+    spend: &HashMap<String, BudgetSpend>,
+) -> anyhow::Result<()> {
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, serde_json::to_vec(spend)?)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// Asks the upstream to report usage in the final chunk of a streamed request
+/// (`stream_options.include_usage`), so streamed responses are metered against client budgets.
+fn request_stream_usage(body: &mut serde_json::Map<String, Value>) {
+    let options = body.entry("stream_options").or_insert_with(|| json!({}));
+    if !options.is_object() {
+        *options = json!({});
+    }
+    options["include_usage"] = Value::Bool(true);
+}
+
+/// OpenAI `usage` object as reported by the upstream on completion.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct TokenUsage {
+    #[serde(default)]
+    prompt_tokens: u64,
+    #[serde(default)]
+    completion_tokens: u64,
+    #[serde(default)]
+    total_tokens: u64,
+}
+
+impl TokenUsage {
+    fn tokens(&self) -> u64 {
+        self.total_tokens
+            .max(self.prompt_tokens.saturating_add(self.completion_tokens))
+    }
+
+    fn cost_usd(&self, pricing: ModelPricing) -> f64 {
+        (self.prompt_tokens as f64 * pricing.prompt
+            + self.completion_tokens as f64 * pricing.completion)
+            / 1_000_000.0
+    }
+}
+
+/// Finds the last parseable `"usage": {...}` object in a response tail. Works for both plain JSON
+/// bodies and SSE streams, where usage arrives in the final chunk.
+fn find_usage_in_tail(tail: &[u8]) -> Option<TokenUsage> {
+    const NEEDLE: &[u8] = b"\"usage\"";
+
+    let mut end = tail.len();
+    while let Some(pos) = tail[..end]
+        .windows(NEEDLE.len())
+        .rposition(|window| window == NEEDLE)
+    {
+        let rest = tail[pos + NEEDLE.len()..].trim_ascii_start();
+        if let Some(rest) = rest.strip_prefix(b":") {
+            let mut values = serde_json::Deserializer::from_slice(rest).into_iter::<TokenUsage>();
+            if let Some(Ok(usage)) = values.next() {
+                return Some(usage);
+            }
+        }
+        end = pos;
+    }
+    None
+}
+
+/// Marker attached to proxied responses naming the backend and model that served them.
+#[derive(Clone, Debug)]
+struct UpstreamModel(Candidate);
+
+/// Keeps a bounded tail of a proxied response body and charges the reported usage against a
+/// client budget once the body is finished (or dropped).
+struct UsageRecorder {
+    ledger: Arc<BudgetLedger>,
+    budget_idx: usize,
+    pricing: Option<ModelPricing>,
+    tail: Vec<u8>,
+}
+
+impl UsageRecorder {
+    const MAX_TAIL_BYTES: usize = 16 * 1024;
+
+    fn observe(&mut self, chunk: &[u8]) {
+        self.tail.extend_from_slice(chunk);
+        if self.tail.len() > Self::MAX_TAIL_BYTES {
+            let excess = self.tail.len() - Self::MAX_TAIL_BYTES;
+            self.tail.drain(..excess);
+        }
+    }
+}
+
+impl Drop for UsageRecorder {
+    fn drop(&mut self) {
+        if let Some(usage) = find_usage_in_tail(&self.tail) {
+            self.ledger
+                .charge(self.budget_idx, usage, self.pricing, SystemTime::now());
+            self.ledger.schedule_flush();
+        }
+    }
+}
+
+fn meter_response_usage(resp: Response, mut recorder: UsageRecorder) -> Response {
+    resp.map(|body| {
+        Body::from_stream(body.into_data_stream().map(move |chunk| {
+            if let Ok(bytes) = &chunk {
+                recorder.observe(bytes);
+            }
+            chunk
+        }))
+    })
+}
+
+/// Marker attached to responses whose upstream request had `stream_options.include_usage` added.
+#[derive(Clone, Copy, Debug)]
+struct StreamUsageRequested;
+
+/// Buffers an SSE body into whole events and drops the usage-only chunk (`choices: []` with a
+/// `usage` object).
+#[derive(Default)]
+struct UsageChunkFilter {
+    pending: Vec<u8>,
+}
+
+impl UsageChunkFilter {
+    /// Returns the events completed by `chunk`, minus usage-only ones.
+    fn push(&mut self, chunk: &[u8]) -> Bytes {
+        self.pending.extend_from_slice(chunk);
+        let Some(end) = self.pending.windows(2).rposition(|w| w == b"\n\n") else {
+            return Bytes::new();
+        };
+        let complete: Vec<u8> = self.pending.drain(..end + 2).collect();
+        let mut out = Vec::with_capacity(complete.len());
+        let mut rest = complete.as_slice();
+        while let Some(pos) = rest.windows(2).position(|w| w == b"\n\n") {
+            let (event, tail) = rest.split_at(pos + 2);
+            if !is_usage_only_event(event) {
+                out.extend_from_slice(event);
+            }
+            rest = tail;
+        }
+        Bytes::from(out)
+    }
+}
+
+fn is_usage_only_event(event: &[u8]) -> bool {
+    let Some(data) = std::str::from_utf8(event)
+        .ok()
+        .and_then(|event| event.trim().strip_prefix("data:"))
+    else {
+        return false;
+    };
+    let Ok(chunk) = serde_json::from_str::<Value>(data) else {
+        return false;
+    };
+    chunk["usage"].is_object() && chunk["choices"].as_array().is_some_and(Vec::is_empty)
+}
+
+/// Removes the usage chunk requested for metering from a relayed stream, so the client gets the
+/// stream it asked for. Must wrap [`meter_response_usage`], which still needs to see the chunk.
+fn strip_stream_usage_chunk(resp: Response) -> Response {
+    resp.map(|body| {
+        let events = stream::unfold(
+            Some((body.into_data_stream(), UsageChunkFilter::default())),
+            |state| async move {
+                let (mut upstream, mut filter) = state?;
+                loop {
+                    match upstream.next().await {
+                        Some(Ok(chunk)) => {
+                            let events = filter.push(&chunk);
+                            if !events.is_empty() {
+                                return Some((Ok(events), Some((upstream, filter))));
+                            }
+                        }
+                        Some(Err(err)) => return Some((Err(err), None)),
+                        None => {
+                            let rest = std::mem::take(&mut filter.pending);
+                            return (!rest.is_empty()).then(|| (Ok(Bytes::from(rest)), None));
+                        }
+                    }
+                }
+            },
+        );
+        Body::from_stream(events)
+    })
+}
+
+/// Upper bound on requests with shadow traffic in flight; samples beyond it are skipped.
+const MAX_INFLIGHT_MIRRORS: usize = 32;
+/// Cap on the primary and shadow response bodies kept for the mirror log.
+const MAX_MIRROR_CAPTURE_BYTES: usize = 256 * 1024;
+
+struct MirrorRequest<'a> {
+    body: &'a Value,
+    headers: &'a HeaderMap,
+    routing_mode: RoutingMode,
+    req_id: &'a str,
+}
+
+/// Result of one shadow request.
+struct ShadowOutcome {
+    status: Option<u16>,
+    /// Why no response arrived: `timeout` or `request_failed`.
+    error: &'static str,
+    latency: Duration,
+    usage: Option<TokenUsage>,
+    body: Option<Bytes>,
+}
+
+/// Copies the primary response body (up to [`MAX_MIRROR_CAPTURE_BYTES`]) for the mirror log and
+/// hands it over once the body is finished or dropped.
+struct MirrorCapture {
+    bytes: Vec<u8>,
+    truncated: bool,
+    done: Option<oneshot::Sender<(Vec<u8>, bool)>>,
+}
+
+impl MirrorCapture {
+    fn observe(&mut self, chunk: &[u8]) {
+        let room = MAX_MIRROR_CAPTURE_BYTES - self.bytes.len();
+        self.truncated |= chunk.len() > room;
+        self.bytes
+            .extend_from_slice(&chunk[..chunk.len().min(room)]);
+    }
+}
+
+impl Drop for MirrorCapture {
+    fn drop(&mut self) {
+        if let Some(done) = self.done.take() {
+            let _ = done.send((std::mem::take(&mut self.bytes), self.truncated));
+        }
+    }
+}
+
+/// Fires shadow requests for the mirror rules that match and sample this request. The client's
+/// response is returned as is (wrapped to capture its body when a mirror log is configured); the
+/// shadows run detached and only report to metrics and the log.
+fn start_mirrors(state: &AppState, resp: Response, request: MirrorRequest<'_>) -> Response {
+    let Some(UpstreamModel(served)) = resp.extensions().get::<UpstreamModel>().cloned() else {
+        return resp;
+    };
+    let rules: Vec<MirrorRule> = state
+        .config
+        .mirror_rules
+        .iter()
+        .filter(|rule| rule.matches(request.routing_mode, &served) && rule.sampled())
+        .cloned()
+        .collect();
+    if rules.is_empty() {
+        return resp;
+    }
+    let Ok(permit) = state.mirror_slots.clone().try_acquire_owned() else {
+        for rule in &rules {
+            state.metrics.observe_mirror_skipped(&rule.name);
+        }
+        return resp;
+    };
+
+    let (resp, primary_body) = match state.config.mirror_log_path {
+        Some(_) => {
+            let (done, captured) = oneshot::channel();
+            let mut capture = MirrorCapture {
+                bytes: Vec::new(),
+                truncated: false,
+                done: Some(done),
+            };
+            let resp = resp.map(|body| {
+                Body::from_stream(body.into_data_stream().map(move |chunk| {
+                    if let Ok(bytes) = &chunk {
+                        capture.observe(bytes);
+                    }
+                    chunk
+                }))
+            });
+            (resp, Some(captured))
+        }
+        None => (resp, None),
+    };
+
+    let state = state.clone();
+    let body = request.body.clone();
+    let upstream_headers = filter_upstream_request_headers(request.headers);
+    let primary_status = resp.status().as_u16();
+    let req_id = request.req_id.to_string();
+    tokio::spawn(async move {
+        let _permit = permit;
+        let shadows = futures_util::future::join_all(
+            rules
+                .iter()
+                .map(|rule| send_shadow(&state, rule, &body, &upstream_headers)),
+        )
+        .await;
+        for (rule, shadow) in rules.iter().zip(&shadows) {
+            state.metrics.observe_mirror(&rule.name, shadow);
+            tracing::debug!(
+                req_id = %req_id,
+                rule = %rule.name,
+                shadow_model = %rule.shadow,
+                status = ?shadow.status,
+                latency_ms = shadow.latency.as_millis() as u64,
+                "shadow request finished"
+            );
+        }
+
+        let (Some(path), Some(primary_body)) = (state.config.mirror_log_path.clone(), primary_body)
+        else {
+            return;
+        };
+        let (primary_bytes, primary_truncated) = primary_body.await.unwrap_or_default();
+        let ts_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        let mut lines = String::new();
+        for (rule, shadow) in rules.iter().zip(&shadows) {
+            let line = json!({
+                "ts_ms": ts_ms,
+                "req_id": req_id,
+                "rule": rule.name,
+                "primary_model": served.model,
+                "primary_status": primary_status,
+                "primary_body": body_for_log(&primary_bytes),
+                "primary_truncated": primary_truncated,
+                "shadow_model": rule.shadow,
+                "shadow_status": shadow.status,
+                "shadow_error": shadow.status.is_none().then_some(shadow.error),
+                "shadow_latency_ms": shadow.latency.as_millis() as u64,
+                "shadow_usage": shadow.usage,
+                "shadow_body": shadow.body.as_deref().map(body_for_log),
+            });
+            lines.push_str(&line.to_string());
+            lines.push('\n');
+        }
+        let written = tokio::task::spawn_blocking(move || {
+            use std::io::Write;
+            std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)?
+                .write_all(lines.as_bytes())
+        })
+        .await;
+        if !matches!(written, Ok(Ok(()))) {
+            tracing::warn!(req_id = %req_id, "failed to append to the mirror log");
+        }
+    });
+
+    resp
+}
+
+/// Sends one non-streaming copy of the request to a rule's shadow model, billed to the rule's
+/// `api_key`, and reads up to [`MAX_MIRROR_CAPTURE_BYTES`] of the response.
+async fn send_shadow(
+    state: &AppState,
+    rule: &MirrorRule,
+    body: &Value,
+    headers: &HeaderMap,
+) -> ShadowOutcome {
+    let mut body = body.clone();
+    if let Some(map) = body.as_object_mut() {
+        map.insert("model".to_string(), json!(rule.shadow));
+        map.insert("stream".to_string(), json!(false));
+        map.remove("stream_options");
+    }
+    let mut headers = headers.clone();
+    headers.remove(axum::http::header::AUTHORIZATION);
+    if let Ok(mut value) = HeaderValue::from_str(&format!("Bearer {}", rule.api_key)) {
+        value.set_sensitive(true);
+        headers.insert(axum::http::header::AUTHORIZATION, value);
+    }
+
+    let request = state
+        .http_client
+        .post(upstream_chat_completions_url(&state.config, 0))
+        .headers(headers)
+        .body(body.to_string());
+    let started = Instant::now();
+    let sent = tokio::time::timeout(state.config.mirror_timeout, async {
+        let resp = request.send().await?;
+        let status = resp.status().as_u16();
+        let mut body = Vec::new();
+        let mut chunks = resp.bytes_stream();
+        while let Some(chunk) = chunks.next().await {
+            let chunk = chunk?;
+            let room = MAX_MIRROR_CAPTURE_BYTES - body.len();
+            body.extend_from_slice(&chunk[..chunk.len().min(room)]);
+            if chunk.len() >= room {
+                break;
+            }
+        }
+        Ok::<_, reqwest::Error>((status, Bytes::from(body)))
+    })
+    .await;
+    let latency = started.elapsed();
+
+    let (status, error, body) = match sent {
+        Ok(Ok((status, bytes))) => (Some(status), "", Some(bytes)),
+        Ok(Err(_)) => (None, "request_failed", None),
+        Err(_) => (None, "timeout", None),
+    };
+    ShadowOutcome {
+        status,
+        error,
+        latency,
+        usage: body.as_deref().and_then(find_usage_in_tail),
+        body,
+    }
+}
+
+/// JSON bodies are logged as JSON, anything else (e.g. SSE streams) as text.
+fn body_for_log(bytes: &[u8]) -> Value {
+    serde_json::from_slice(bytes)
+        .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(bytes).into_owned()))
+}
+
+fn is_autopilot_alias(model: &str) -> bool {
+    model == "chutesai/AutoPilot"
+}
+
+fn is_model_catalog_eligible(model: &str, models_allowlist: &HashSet<String>) -> bool {
+    if models_allowlist.is_empty() {
+        // Conservative fallback when we don't have an authoritative model catalog yet.
+        model.ends_with("-TEE")
+    } else {
+        models_allowlist.contains(model)
+    }
+}
+
+async fn refresh_models_allowlist(state: AppState) {
+    let client = state.http_client.clone();
+    let mut etag: Option<String> = None;
+    let mut failures = 0u32;
+    loop {
+        let fetched = fetch_models_catalog(
+            &client,
+            &state.config.models_url,
+            state.config.control_plane_timeout,
+            etag.as_deref(),
+        )
+        .await;
+        let outcome = match &fetched {
+            Ok(ModelsFetch::Modified { .. }) => Ok("success"),
+            Ok(ModelsFetch::NotModified) => Ok("not_modified"),
+            Err(err) => Err(err),
+        };
+        failures = record_control_plane_outcome(&state, "models", outcome, failures);
+
+        match fetched {
+            Ok(ModelsFetch::Modified {
+                models,
+                etag: new_etag,
+            }) => {
+                etag = new_etag;
+                let mut runtime = state.runtime.write().await;
+                runtime.models_allowlist = models.iter().map(|m| m.id.clone()).collect();
+                runtime.models_catalog = models.into_iter().map(|m| (m.id.clone(), m)).collect();
+                runtime.models_allowlist_at = Some(Instant::now());
+            }
+            // The catalog is unchanged, so the allowlist we hold is as fresh as a full fetch.
+            Ok(ModelsFetch::NotModified) => {
+                state.runtime.write().await.models_allowlist_at = Some(Instant::now());
+            }
+            Err(_) => {}
+        }
+
+        tokio::time::sleep(refresh_delay(
+            state.config.models_refresh_ms,
+            failures,
+            state.config.control_plane_max_backoff,
+        ))
+        .await;
+    }
+}
+
+async fn refresh_candidates(state: AppState) {
+    let client = state.http_client.clone();
+    let mut failures = 0u32;
+    let mut file_cache: UtilizationFileCache = None;
+    loop {
+        let (models_allowlist, chute_names) = {
+            let runtime = state.runtime.read().await;
+            (
+                runtime.models_allowlist.clone(),
+                catalog_chute_names(runtime.models_catalog.values()),
+            )
+        };
+
+        let candidates = fetch_ranked_candidates(
+            &client,
+            &state.config.utilization_url,
+            &models_allowlist,
+            &chute_names,
+            state.config.control_plane_timeout,
+            &mut file_cache,
+        )
+        .await
+        .and_then(|report| state.check_utilization_schema(report));
+        let outcome = candidates.as_ref().map(|_| "success");
+        failures = record_control_plane_outcome(&state, "utilization", outcome, failures);
+        state.update_candidate_snapshot(candidates).await;
+
+        tokio::time::sleep(refresh_delay(
+            state.config.utilization_refresh_ms,
+            failures,
+            state.config.control_plane_max_backoff,
+        ))
+        .await;
+    }
+}
+
+/// Keeps one additional backend's models and candidate order fresh. Both are fetched every
+/// `UTILIZATION_REFRESH_MS`; the models catalog uses conditional requests, so an unchanged
+/// catalog stays cheap.
+async fn refresh_backend(state: AppState, idx: usize) {
+    let backend = state.config.backends[idx].clone();
+    let models_url = backend.models_url();
+    let models_source = format!("{}_models", backend.name);
+    let utilization_source = format!("{}_utilization", backend.name);
+    let client = state.http_client.clone();
+    let mut etag: Option<String> = None;
+    let mut file_cache: UtilizationFileCache = None;
+    let mut models_failures = 0u32;
+    let mut utilization_failures = 0u32;
+    loop {
+        let fetched = fetch_models_catalog(
+            &client,
+            &models_url,
+            state.config.control_plane_timeout,
+            etag.as_deref(),
+        )
+        .await;
+        let outcome = match &fetched {
+            Ok(ModelsFetch::Modified { .. }) => Ok("success"),
+            Ok(ModelsFetch::NotModified) => Ok("not_modified"),
+            Err(err) => Err(err),
+        };
+        models_failures =
+            record_control_plane_outcome(&state, &models_source, outcome, models_failures);
+
+        let models = match fetched {
+            Ok(ModelsFetch::Modified {
+                models,
+                etag: new_etag,
+            }) => {
+                etag = new_etag;
+                let ids: Vec<String> = models.into_iter().map(|m| m.id).collect();
+                let mut runtime = state.runtime.write().await;
+                let snapshot = &mut runtime.backends[idx];
+                snapshot.models = ids.iter().cloned().collect();
+                if backend.utilization_url.is_none() {
+                    snapshot.ranked = ids;
+                }
+                snapshot.refreshed_at = Some(Instant::now());
+                snapshot.models.clone()
+            }
+            _ => state.runtime.read().await.backends[idx].models.clone(),
+        };
+
+        if let Some(utilization_url) = &backend.utilization_url {
+            let ranked = fetch_ranked_candidates(
+                &client,
+                utilization_url,
+                &models,
+                &HashMap::new(),
+                state.config.control_plane_timeout,
+                &mut file_cache,
+            )
+            .await;
+            let outcome = ranked.as_ref().map(|_| "success");
+            utilization_failures = record_control_plane_outcome(
+                &state,
+                &utilization_source,
+                outcome,
+                utilization_failures,
+            );
+            if let Ok(report) = ranked {
+                let mut runtime = state.runtime.write().await;
+                let snapshot = &mut runtime.backends[idx];
+                snapshot.ranked = report.ranked.into_iter().map(|c| c.name).collect();
+                snapshot.refreshed_at = Some(Instant::now());
+            }
+        }
+
+        tokio::time::sleep(refresh_delay(
+            state.config.utilization_refresh_ms,
+            models_failures.max(utilization_failures),
+            state.config.control_plane_max_backoff,
+        ))
+        .await;
+    }
+}
+
+/// Outcome of one `GET /chutes/{chute_id}/evidence` check.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum AttestationStatus {
+    /// A JSON object was returned for our nonce. The quote inside is not checked yet (no
+    /// success payload has been captured), so this is not a verified measurement.
+    EvidenceReturned,
+    /// TEE chute whose instances run a `chutes_version` below the evidence gate (0.6.0).
+    RuntimeTooOld,
+    /// The chute is not TEE-enabled.
+    NonTee,
+    /// Any other response (bad nonce, 5xx, timeout, transport error).
+    Error,
+}
+
+#[derive(Clone, Debug)]
+struct Attestation {
+    chute_id: String,
+    status: AttestationStatus,
+    checked_at: Instant,
+}
+
+fn is_attested(attestations: &HashMap<String, Attestation>, model: &str) -> bool {
+    attestations
+        .get(model)
+        .is_some_and(|a| a.status == AttestationStatus::EvidenceReturned)
+}
+
+/// `chutes_version` from which instances serve attestation evidence.
+const EVIDENCE_MIN_CHUTES_VERSION: (u64, u64, u64) = (0, 6, 0);
+
+/// Parses `0.6.0` / `0.5.4.rc7` into `(major, minor, patch)` plus whether it is a pre-release.
+fn parse_chutes_version(version: &str) -> Option<((u64, u64, u64), bool)> {
+    let mut parts = version.trim().trim_start_matches('v').split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next()?.parse().ok()?;
+    let mut patch = 0;
+    let mut prerelease = false;
+    for part in parts {
+        match part.parse::<u64>() {
+            Ok(n) if patch == 0 && !prerelease => patch = n,
+            _ => prerelease = true,
+        }
+    }
+    Some(((major, minor, patch), prerelease))
+}
+
+fn meets_evidence_gate(version: &str) -> Option<bool> {
+    let (triple, prerelease) = parse_chutes_version(version)?;
+    Some(
+        triple > EVIDENCE_MIN_CHUTES_VERSION
+            || (triple == EVIDENCE_MIN_CHUTES_VERSION && !prerelease),
+    )
+}
+
+/// Attestation view of one ranked candidate, for `/debug/candidates` and metrics.
+#[derive(Debug, Clone, Serialize)]
+struct CandidateAttestation {
+    model: String,
+    chute_id: Option<String>,
+    chutes_version: Option<String>,
+    /// `evidence_returned`, `unverified`, or `not_tee`.
+    summary: &'static str,
+    /// Latest evidence check, if any.
+    status: Option<AttestationStatus>,
+    checked_age_ms: Option<u64>,
+    /// `chutes_version >= 0.6.0` for TEE candidates: `meets`, `below`, or `unknown`. Taken from
+    /// `chutes_version` when the feed carries it, otherwise inferred from the evidence check.
+    runtime_gate: Option<&'static str>,
+}
+
+fn candidate_attestations(runtime: &RuntimeState) -> Vec<CandidateAttestation> {
+    runtime
+        .candidate_scores
+        .iter()
+        .map(|scored| {
+            let record = &scored.record;
+            let attestation = runtime.attestations.get(&record.name);
+            let status = attestation.map(|a| a.status);
+            let is_tee = record.name.ends_with("-TEE") && status != Some(AttestationStatus::NonTee);
+            let summary = match status {
+                Some(AttestationStatus::EvidenceReturned) => "evidence_returned",
+                _ if !is_tee => "not_tee",
+                _ => "unverified",
+            };
+            let runtime_gate = is_tee.then(|| {
+                let gate = record
+                    .chutes_version
+                    .as_deref()
+                    .and_then(meets_evidence_gate);
+                match (gate, status) {
+                    (Some(true), _) | (None, Some(AttestationStatus::EvidenceReturned)) => "meets",
+                    (Some(false), _) | (None, Some(AttestationStatus::RuntimeTooOld)) => "below",
+                    _ => "unknown",
+                }
+            });
+            CandidateAttestation {
+                model: record.name.clone(),
+                chute_id: record.chute_id.clone(),
+                chutes_version: record.chutes_version.clone(),
+                summary,
+                status,
+                checked_age_ms: attestation.map(|a| a.checked_at.elapsed().as_millis() as u64),
+                runtime_gate,
+            }
+        })
+        .collect()
+}
+
+/// A fresh 32-byte nonce as the 64 hex characters the evidence endpoint requires.
+fn attestation_nonce() -> String {
+    format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+/// Classifies an evidence response using the error shapes documented in
+/// `tests/testdata/chutes_live/evidence_*`.
+fn classify_evidence(status: StatusCode, body: &[u8]) -> AttestationStatus {
+    if status.is_success() {
+        return match serde_json::from_slice::<Value>(body) {
+            Ok(v) if v.is_object() => AttestationStatus::EvidenceReturned,
+            _ => AttestationStatus::Error,
+        };
+    }
+
+    let detail = serde_json::from_slice::<Value>(body)
+        .ok()
+        .and_then(|v| v.get("detail")?.as_str().map(ToString::to_string))
+        .unwrap_or_default();
+    if status != StatusCode::BAD_REQUEST {
+        AttestationStatus::Error
+    } else if detail.contains("requires chutes_version") {
+        AttestationStatus::RuntimeTooOld
+    } else if detail.ends_with("is not TEE-enabled") {
+        AttestationStatus::NonTee
+    } else {
+        AttestationStatus::Error
+    }
+}
+
+async fn fetch_attestation(
+    client: &Client,
+    evidence_url: &str,
+    chute_id: &str,
+    nonce: &str,
+    timeout: Duration,
+) -> AttestationStatus {
+    let url = evidence_url.replace("{chute_id}", chute_id);
+    let response = client
+        .get(url)
+        .query(&[("nonce", nonce)])
+        .timeout(timeout)
+        .send()
+        .await;
+    let response = match response {
+        Ok(response) => response,
+        Err(err) => {
+            tracing::warn!(chute_id, error = ?err, "attestation: evidence request failed");
+            return AttestationStatus::Error;
+        }
+    };
+    let status = response.status();
+    match response.bytes().await {
+        Ok(body) => classify_evidence(status, &body),
+        Err(_) => AttestationStatus::Error,
+    }
+}
+
+/// TEE candidates (`-TEE` models with a `chute_id`) whose cached evidence check is missing,
+/// for another chute, or older than `max_age`.
+fn attestation_due(runtime: &RuntimeState, max_age: Duration) -> Vec<(String, String)> {
+    runtime
+        .candidate_scores
+        .iter()
+        .filter(|scored| scored.record.name.ends_with("-TEE"))
+        .filter_map(|scored| {
+            let chute_id = scored.record.chute_id.clone()?;
+            let fresh = runtime
+                .attestations
+                .get(&scored.record.name)
+                .is_some_and(|a| a.chute_id == chute_id && a.checked_at.elapsed() < max_age);
+            (!fresh).then(|| (scored.record.name.clone(), chute_id))
+        })
+        .collect()
+}
+
+/// Checks TEE candidates against the evidence endpoint with a fresh nonce per round. New
+/// candidates are picked up on the utilization cadence; known ones are re-checked every
+/// `attestation_refresh`.
+async fn refresh_attestations(state: AppState) {
+    const CONCURRENT_CHECKS: usize = 4;
+    let client = state.http_client.clone();
+    loop {
+        let due = attestation_due(
+            &*state.runtime.read().await,
+            state.config.attestation_refresh,
+        );
+        if !due.is_empty() {
+            let nonce = attestation_nonce();
+            let checked: Vec<(String, Attestation)> = stream::iter(due)
+                .map(|(model, chute_id)| {
+                    let client = &client;
+                    let nonce = &nonce;
+                    let state = &state;
+                    async move {
+                        let status = fetch_attestation(
+                            client,
+                            &state.config.attestation_evidence_url,
+                            &chute_id,
+                            nonce,
+                            state.config.control_plane_timeout,
+                        )
+                        .await;
+                        tracing::info!(model = %model, chute_id = %chute_id, ?status, "attestation checked");
+                        let attestation = Attestation {
+                            chute_id,
+                            status,
+                            checked_at: Instant::now(),
+                        };
+                        (model, attestation)
+                    }
+                })
+                .buffer_unordered(CONCURRENT_CHECKS)
+                .collect()
+                .await;
+
+            let mut runtime = state.runtime.write().await;
+            runtime.attestations.extend(checked);
+            let current: HashSet<String> = runtime
+                .candidate_scores
+                .iter()
+                .map(|scored| scored.record.name.clone())
+                .collect();
+            runtime
+                .attestations
+                .retain(|model, _| current.contains(model));
+        }
+
+        let summary = candidate_attestations(&*state.runtime.read().await);
+        state.metrics.observe_attestations(&summary);
+        tokio::time::sleep(state.config.utilization_refresh_ms).await;
+    }
+}
+
+/// Records a control-plane fetch outcome and returns the updated consecutive-failure count.
+fn record_control_plane_outcome(
+    state: &AppState,
+    source: &str,
+    outcome: Result<&str, &anyhow::Error>,
+    failures: u32,
+) -> u32 {
+    match outcome {
+        Ok(outcome) => {
+            if failures > 0 {
+                tracing::info!(source, failures, "control-plane fetch recovered");
+            }
+            state
+                .metrics
+                .observe_control_plane_fetch(source, outcome, 0);
+            0
+        }
+        Err(err) => {
+            let failures = failures.saturating_add(1);
+            tracing::warn!(
+                source,
+                consecutive_failures = failures,
+                error = ?err,
+                "control-plane fetch failed; keeping last-known-good data"
+            );
+            state
+                .metrics
+                .observe_control_plane_fetch(source, "failure", failures);
+            failures
+        }
+    }
+}
+
+/// Delay before the next control-plane fetch: the normal interval when healthy, otherwise
+/// exponential backoff (capped at `max(interval, max_backoff)`). Equal jitter applies to the
+/// excess over the interval only, so replicas do not retry a failing upstream in lockstep and a
+/// failing source is never polled sooner than a healthy one.
+fn refresh_delay(interval: Duration, consecutive_failures: u32, max_backoff: Duration) -> Duration {
+    if consecutive_failures == 0 {
+        return interval;
+    }
+
+    let cap = interval.max(max_backoff);
+    let backoff = interval
+        .checked_mul(1u32 << consecutive_failures.min(16))
+        .map_or(cap, |d| d.min(cap));
+    let excess = backoff - interval;
+    let jitter = (Uuid::new_v4().as_u128() % 1_000) as u32;
+    interval + excess / 2 + excess / 2 * jitter / 1_000
+}
+
+enum ModelsFetch {
+    Modified {
+        models: Vec<OpenAiModelItem>,
+        etag: Option<String>,
+    },
+    NotModified,
+}
+
+/// Where models or utilization data comes from, parsed from `MODELS_URL` / `UTILIZATION_URL`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ControlPlaneSource {
+    Http(String),
+    /// `file:///path.json`: checked on every refresh, re-read only when its length or mtime changes.
+    File(PathBuf),
+    /// `static:a,b,c`: a fixed model list, used as-is (no utilization data).
+    Static(Vec<String>),
+}
+
+impl ControlPlaneSource {
+    fn parse(url: &str) -> Self {
+        if let Some(path) = url.strip_prefix("file://") {
+            Self::File(PathBuf::from(path))
+        } else if let Some(list) = url.strip_prefix("static:") {
+            let mut models: Vec<String> = Vec::new();
+            for model in list.split(',').map(str::trim).filter(|m| !m.is_empty()) {
+                if !models.iter().any(|m| m == model) {
+                    models.push(model.to_string());
+                }
+            }
+            Self::Static(models)
+        } else {
+            Self::Http(url.to_string())
+        }
+    }
+}
+
+/// Reads a local source file off the async runtime, returning its bytes and a change validator
+/// (length + mtime) that plays the role of an `ETag`. Returns `None` without reading the file
+/// when the validator still equals `known`.
+async fn read_source_file(
+    path: PathBuf,
+    known: Option<String>,
+) -> anyhow::Result<Option<(Vec<u8>, String)>> {
+    tokio::task::spawn_blocking(move || {
+        let read = || -> std::io::Result<Option<(Vec<u8>, String)>> {
+            let meta = std::fs::metadata(&path)?;
+            let mtime = meta
+                .modified()?
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos();
+            let validator = format!("{}-{mtime}", meta.len());
+            if known.as_deref() == Some(validator.as_str()) {
+                return Ok(None);
+            }
+            Ok(Some((std::fs::read(&path)?, validator)))
+        };
+        read().map_err(|e| anyhow::anyhow!("failed to read {}: {e}", path.display()))
+    })
+    .await?
+}
+
+async fn fetch_models_catalog(
+    client: &Client,
+    url: &str,
+    timeout: Duration,
+    etag: Option<&str>,
+) -> anyhow::Result<ModelsFetch> {
+    let url = match ControlPlaneSource::parse(url) {
+        ControlPlaneSource::Http(url) => url,
+        ControlPlaneSource::File(path) => {
+            let Some((bytes, validator)) = read_source_file(path, etag.map(String::from)).await?
+            else {
+                return Ok(ModelsFetch::NotModified);
+            };
+            let payload: OpenAiModelListResponse = serde_json::from_slice(&bytes)?;
+            return Ok(ModelsFetch::Modified {
+                models: payload.data,
+                etag: Some(validator),
+            });
+        }
+        ControlPlaneSource::Static(models) => {
+            if etag.is_some() {
+                return Ok(ModelsFetch::NotModified);
+            }
+            return Ok(ModelsFetch::Modified {
+                models: models
+                    .into_iter()
+                    .map(|id| OpenAiModelItem {
+                        id,
+                        pricing: None,
+                        chute_id: None,
+                        supported_sampling_parameters: None,
+                        max_output_length: None,
+                    })
+                    .collect(),
+                etag: Some("static".to_string()),
+            });
+        }
+    };
+
+    let mut request = client.get(url).timeout(timeout);
+    if let Some(etag) = etag {
+        request = request.header(axum::http::header::IF_NONE_MATCH, etag);
+    }
+    let response = request.send().await?;
+    if response.status() == StatusCode::NOT_MODIFIED {
+        return Ok(ModelsFetch::NotModified);
+    }
+
+    let response = response.error_for_status()?;
+    let etag = response
+        .headers()
+        .get(axum::http::header::ETAG)
+        .and_then(|v| v.to_str().ok())
+        .map(ToString::to_string);
+    let payload = response.json::<OpenAiModelListResponse>().await?;
+    Ok(ModelsFetch::Modified {
+        models: payload.data,
+        etag,
+    })
+}
+
+/// Keeps the parsed records of a `file://` utilization source together with the validator of the
+/// file they came from, so an unchanged file is only re-ranked, not re-read and re-parsed.
+type UtilizationFileCache = Option<(String, ParsedUtilization)>;
+
+async fn fetch_ranked_candidates(
+    client: &Client,
+    url: &str,
+    models_allowlist: &HashSet<String>,
+    chute_names: &HashMap<String, String>,
+    timeout: Duration,
+    file_cache: &mut UtilizationFileCache,
+) -> anyhow::Result<CandidateReport> {
+    let body = match ControlPlaneSource::parse(url) {
+        ControlPlaneSource::Http(url) => client
+            .get(url)
+            .timeout(timeout)
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?
+            .to_vec(),
+        ControlPlaneSource::File(path) => {
+            let known = file_cache.as_ref().map(|(validator, _)| validator.clone());
+            if let Some((bytes, validator)) = read_source_file(path, known).await? {
+                // Dropped first so a file that no longer parses keeps failing until it changes.
+                *file_cache = None;
+                *file_cache = Some((validator, ParsedUtilization::parse(&bytes)?));
+            }
+            let (_, parsed) = file_cache.as_ref().expect("cached after a successful read");
+            return Ok(parsed.rank(models_allowlist, chute_names));
+        }
+        ControlPlaneSource::Static(models) => {
+            return Ok(static_candidate_report(models, models_allowlist));
+        }
+    };
+
+    parse_utilization_payload(&body, models_allowlist, chute_names)
+}
+
+/// Candidates from a `static:` source keep the configured order. Scores descend from the list
+/// length so score-based selection modes also prefer earlier entries.
+fn static_candidate_report(
+    models: Vec<String>,
+    models_allowlist: &HashSet<String>,
+) -> CandidateReport {
+    let eligible: Vec<String> = models
+        .into_iter()
+        .filter(|model| models_allowlist.is_empty() || models_allowlist.contains(model))
+        .collect();
+    let len = eligible.len();
+    CandidateReport {
+        ranked: eligible
+            .into_iter()
+            .enumerate()
+            .map(|(idx, name)| RankedCandidate {
+                name,
+                chute_id: None,
+                active_instance_count: 1,
+                utilization_current: 0.0,
+                rate_limit_ratio_5m: 0.0,
+                score: (len - idx) as f64,
+            })
+            .collect(),
+        ..Default::default()
+    }
+}
+
+/// Maps `chute_id` to the catalog model id, the name the upstream accepts.
+fn catalog_chute_names<'a>(
+    items: impl IntoIterator<Item = &'a OpenAiModelItem>,
+) -> HashMap<String, String> {
+    items
+        .into_iter()
+        .filter_map(|item| Some((item.chute_id.clone()?, item.id.clone())))
+        .collect()
+}
+
+/// Joins utilization records to the catalog by `chute_id`, so a chute whose display name
+/// changed in one feed but not the other is still matched (and routed by its catalog name).
+fn join_catalog_names(records: &mut [UtilizationRecord], chute_names: &HashMap<String, String>) {
+    for record in records {
+        let Some(name) = record.chute_id.as_ref().and_then(|id| chute_names.get(id)) else {
+            continue;
+        };
+        if record.name != *name && !record.is_private_chute() {
+            tracing::debug!(
+                chute_id = ?record.chute_id,
+                utilization_name = %record.name,
+                catalog_name = %name,
+                "utilization name differs from catalog; using catalog name"
+            );
+            record.name = name.clone();
+        }
+    }
+}
+
+fn parse_utilization_payload(
+    body: &[u8],
+    models_allowlist: &HashSet<String>,
+    chute_names: &HashMap<String, String>,
+) -> anyhow::Result<CandidateReport> {
+    Ok(ParsedUtilization::parse(body)?.rank(models_allowlist, chute_names))
+}
+
+/// A utilization payload before catalog names are joined and candidates are ranked.
+#[derive(Debug, Clone)]
+struct ParsedUtilization {
+    records: Vec<UtilizationRecord>,
+    schema_drift: SchemaDrift,
+}
+
+impl ParsedUtilization {
+    fn parse(body: &[u8]) -> anyhow::Result<Self> {
+        // Parsed twice: typed records default missing fields, so drift is measured on the raw keys.
+        let raw: Vec<Value> = serde_json::from_slice(body)?;
+        Ok(Self {
+            records: serde_json::from_slice(body)?,
+            schema_drift: SchemaDrift::measure(&raw),
+        })
+    }
+
+    fn rank(
+        &self,
+        models_allowlist: &HashSet<String>,
+        chute_names: &HashMap<String, String>,
+    ) -> CandidateReport {
+        let mut records = self.records.clone();
+        join_catalog_names(&mut records, chute_names);
+
+        let mut report = explain_candidates(records, models_allowlist);
+        report.schema_drift = self.schema_drift.clone();
+        report
+    }
+}
+
+/// Utilization fields that feed the score; their absence silently degrades ranking.
+const UTILIZATION_SIGNAL_FIELDS: [&str; 10] = [
+    "active_instance_count",
+    "utilization_current",
+    "utilization_5m",
+    "utilization_15m",
+    "utilization_1h",
+    "rate_limit_ratio_5m",
+    "rate_limit_ratio_15m",
+    "rate_limit_ratio_1h",
+    "scalable",
+    "scale_allowance",
+];
+
+/// Per-refresh count of utilization records missing (absent or `null`) scoring fields.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct SchemaDrift {
+    records: usize,
+    missing: Vec<(&'static str, usize)>,
+    /// Records without `active_instance_count` or without any of `utilization_5m`/`utilization_current`.
+    missing_core: usize,
+}
+
+impl SchemaDrift {
+    fn measure(raw: &[Value]) -> Self {
+        let has = |record: &Value, field: &str| record.get(field).is_some_and(|v| !v.is_null());
+        let missing = UTILIZATION_SIGNAL_FIELDS
+            .iter()
+            .map(|field| (*field, raw.iter().filter(|r| !has(r, field)).count()))
+            .filter(|(_, count)| *count > 0)
+            .collect();
+        let missing_core = raw
+            .iter()
+            .filter(|r| {
+                !has(r, "active_instance_count")
+                    || !(has(r, "utilization_5m") || has(r, "utilization_current"))
+            })
+            .count();
+
+        Self {
+            records: raw.len(),
+            missing,
+            missing_core,
+        }
+    }
+
+    fn missing_count(&self, field: &str) -> usize {
+        self.missing
+            .iter()
+            .find(|(name, _)| *name == field)
+            .map_or(0, |(_, count)| *count)
+    }
+
+    fn core_missing_pct(&self) -> f64 {
+        if self.records == 0 {
+            return 0.0;
+        }
+        self.missing_core as f64 * 100.0 / self.records as f64
+    }
+}
+
+/// Ranking output plus the per-record reasoning behind it, served by `GET /debug/candidates`.
+#[derive(Debug, Default)]
+struct CandidateReport {
+    ranked: Vec<RankedCandidate>,
+    scored: Vec<ScoredRecord>,
+    excluded: Vec<ExcludedRecord>,
+    schema_drift: SchemaDrift,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ScoredRecord {
+    position: usize,
+    record: UtilizationRecord,
+    #[serde(flatten)]
+    breakdown: ScoreBreakdown,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ExcludedRecord {
+    reason: &'static str,
+    record: UtilizationRecord,
+}
+
+/// Runs the AutoPilot ranking pipeline over captured control-plane payloads: a
+/// `GET /chutes/utilization` array and, optionally, a `GET /v1/models` list (without it the
+/// `-TEE` fallback applies). Returns `ranked`/`excluded` in the `GET /debug/candidates` shape.
+pub fn explain_captured_ranking(
+    utilization: &[u8],
+    models: Option<&[u8]>,
+) -> anyhow::Result<Value> {
+    let mut records: Vec<UtilizationRecord> = serde_json::from_slice(utilization)
+        .map_err(|e| anyhow::anyhow!("invalid utilization payload: {e}"))?;
+    let catalog: Vec<OpenAiModelItem> = match models {
+        Some(bytes) => {
+            serde_json::from_slice::<OpenAiModelListResponse>(bytes)
+                .map_err(|e| anyhow::anyhow!("invalid models payload: {e}"))?
+                .data
+        }
+        None => Vec::new(),
+    };
+    join_catalog_names(&mut records, &catalog_chute_names(&catalog));
+    let models_allowlist: HashSet<String> = catalog.into_iter().map(|m| m.id).collect();
+
+    let report = explain_candidates(records, &models_allowlist);
+    Ok(json!({
+        "models_allowlist_len": models_allowlist.len(),
+        "ranked": report.scored,
+        "excluded": report.excluded,
+    }))
+}
+
+fn exclusion_reason(
+    record: &UtilizationRecord,
+    models_allowlist: &HashSet<String>,
+) -> Option<&'static str> {
+    if record.is_private_chute() {
+        Some("private")
+    } else if record.active_instance_count == 0 {
+        Some("zero_instances")
+    } else if is_model_catalog_eligible(&record.name, models_allowlist) {
+        None
+    } else if models_allowlist.is_empty() {
+        Some("not_tee")
+    } else {
+        Some("not_in_allowlist")
+    }
+}
+
+fn explain_candidates(
+    records: Vec<UtilizationRecord>,
+    models_allowlist: &HashSet<String>,
+) -> CandidateReport {
+    let mut excluded = Vec::new();
+    let mut eligible = Vec::new();
+    for record in records {
+        match exclusion_reason(&record, models_allowlist) {
+            Some(reason) => excluded.push(ExcludedRecord { reason, record }),
+            None => eligible.push(record),
+        }
+    }
+
+    let ranked = rank_candidates(eligible.clone(), models_allowlist);
+    let mut report = CandidateReport {
+        excluded,
+        ..Default::default()
+    };
+    for (position, candidate) in ranked.iter().enumerate() {
+        // Candidates are built from their record's name and chute id, so the first match is
+        // the record (or an identical duplicate of it).
+        let idx = eligible
+            .iter()
+            .position(|r| r.name == candidate.name && r.chute_id == candidate.chute_id)
+            .expect("every ranked candidate comes from an eligible record");
+        let record = eligible.swap_remove(idx);
+        report.scored.push(ScoredRecord {
+            position,
+            breakdown: ScoreBreakdown::for_record(&record),
+            record,
+        });
+    }
+    report.ranked = ranked;
+    report
+}
+
+fn rank_candidates(
+    records: Vec<UtilizationRecord>,
+    models_allowlist: &HashSet<String>,
+) -> Vec<RankedCandidate> {
+    let mut ranked: Vec<RankedCandidate> = records
+        .into_iter()
+        .filter(|record| exclusion_reason(record, models_allowlist).is_none())
+        .map(RankedCandidate::from)
+        .collect();
+
+    sort_ranked_candidates(&mut ranked);
+
+    ranked
+}
+
+fn sort_ranked_candidates(ranked: &mut [RankedCandidate]) {
+    ranked.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| b.active_instance_count.cmp(&a.active_instance_count))
+            .then_with(|| a.utilization_current.total_cmp(&b.utilization_current))
+            .then_with(|| a.rate_limit_ratio_5m.total_cmp(&b.rate_limit_ratio_5m))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+}
+
+#[derive(Debug, Clone, Default)]
+struct RankedCandidate {
+    name: String,
+    chute_id: Option<String>,
+    active_instance_count: u64,
+    utilization_current: f64,
+    rate_limit_ratio_5m: f64,
+    score: f64,
+}
+
+/// The top-ranked candidate of the published snapshot and when it took first place.
+#[derive(Debug, Clone)]
+struct Leader {
+    name: String,
+    since: Instant,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LeaderOutcome {
+    Unchanged,
+    Changed,
+    Held,
+}
+
+/// Keeps the current leader in first place unless a challenger outscores it by at least `margin`
+/// and the leader has held first place for `min_dwell`. Only the leader slot is affected; the
+/// rest of the snapshot keeps the deterministic ranking order. Disabled when both are zero.
+fn apply_leader_hysteresis(
+    report: &mut CandidateReport,
+    leader: &mut Option<Leader>,
+    margin: f64,
+    min_dwell: Duration,
+    now: Instant,
+) -> LeaderOutcome {
+    let Some(top) = report.ranked.first() else {
+        *leader = None;
+        return LeaderOutcome::Unchanged;
+    };
+
+    if let Some(current) = leader.as_ref() {
+        if current.name == top.name {
+            return LeaderOutcome::Unchanged;
+        }
+
+        let hysteresis_enabled = margin > 0.0 || !min_dwell.is_zero();
+        let incumbent = report.ranked.iter().position(|c| c.name == current.name);
+        if let Some(pos) = incumbent.filter(|_| hysteresis_enabled) {
+            let outscored = top.score - report.ranked[pos].score >= margin;
+            let dwelled = now.duration_since(current.since) >= min_dwell;
+            if !(outscored && dwelled) {
+                let held = report.ranked.remove(pos);
+                report.ranked.insert(0, held);
+                if pos < report.scored.len() {
+                    let held = report.scored.remove(pos);
+                    report.scored.insert(0, held);
+                    for (position, scored) in report.scored.iter_mut().enumerate() {
+                        scored.position = position;
+                    }
+                }
+                return LeaderOutcome::Held;
+            }
+        }
+    }
+
+    *leader = Some(Leader {
+        name: top.name.clone(),
+        since: now,
+    });
+    LeaderOutcome::Changed
+}
+
+/// Intermediate values of the ranking formula for one utilization record.
+#[derive(Debug, Clone, Copy, Serialize)]
+struct ScoreBreakdown {
+    util: f64,
+    utilization_current: f64,
+    rate_limit_ratio_5m: f64,
+    throttle_signal: f64,
+    free_capacity: f64,
+    scale_bonus: f64,
+    scaling_adjustment: f64,
+    score: f64,
+}
+
+impl ScoreBreakdown {
+    fn for_record(record: &UtilizationRecord) -> Self {
+        let u5 = record
+            .utilization_5m
+            .or(record.utilization_current)
+            .unwrap_or(1.0);
+        let u15 = record.utilization_15m.unwrap_or(u5);
+        let u1h = record
+            .utilization_1h
+            .or(record.avg_busy_ratio)
+            .unwrap_or(u15);
+        let utilization_current = record.utilization_current.unwrap_or(u5);
+        let util = 0.6 * u5 + 0.3 * u15 + 0.1 * u1h;
+
+        let rate_limit_ratio_5m = record
+            .rate_limit_ratio_5m
+            .or_else(|| {
+                UtilizationRecord::observed_rate_limit_ratio(
+                    record.rate_limited_requests_5m,
+                    record.total_requests_5m,
+                )
+            })
+            .unwrap_or(0.0);
+        let rate_limit_ratio_15m = record
+            .rate_limit_ratio_15m
+            .or_else(|| {
+                UtilizationRecord::observed_rate_limit_ratio(
+                    record.rate_limited_requests_15m,
+                    record.total_requests_15m,
+                )
+            })
+            .unwrap_or(rate_limit_ratio_5m);
+        let rate_limit_ratio_1h = record
+            .rate_limit_ratio_1h
+            .or_else(|| {
+                UtilizationRecord::observed_rate_limit_ratio(
+                    record.rate_limited_requests_1h,
+                    record.total_requests_1h,
+                )
+            })
+            .unwrap_or(rate_limit_ratio_15m);
+
+        let free_capacity = record.active_instance_count as f64 * (1.0 - util).max(0.0);
+        let scale_bonus = if record.scalable {
+            record.scale_allowance.unwrap_or(0.0).min(8.0) * 0.05
+        } else {
+            0.0
+        };
+        let scaling_adjustment = Self::scaling_adjustment(record, util, free_capacity, scale_bonus);
+        let throttle_signal = rate_limit_ratio_5m
+            .max(0.5 * rate_limit_ratio_15m)
+            .max(0.25 * rate_limit_ratio_1h);
+        let score = free_capacity + scale_bonus + scaling_adjustment
+            - (record.active_instance_count as f64 * throttle_signal * 2.0);
+
+        Self {
+            util,
+            utilization_current,
+            rate_limit_ratio_5m,
+            throttle_signal,
+            free_capacity,
+            scale_bonus,
+            scaling_adjustment,
+            score,
+        }
+    }
+
+    /// Pending scale-up (`target_count > instance_count`) earns the same per-instance bonus as
+    /// `scale_allowance`, minus whatever `scale_bonus` already credited, since both fields report
+    /// the same headroom; a chute that is scaling down loses the free capacity of the instances
+    /// being removed (at least one).
+    fn scaling_adjustment(
+        record: &UtilizationRecord,
+        util: f64,
+        free_capacity: f64,
+        scale_bonus: f64,
+    ) -> f64 {
+        let instances = record
+            .instance_count
+            .unwrap_or(record.active_instance_count);
+        let target = record.target_count.unwrap_or(instances);
+        if record.is_scaling_down() || target < instances {
+            let removed = instances.saturating_sub(target).max(1) as f64;
+            -(removed * (1.0 - util).max(0.0)).min(free_capacity)
+        } else {
+            ((target - instances).min(8) as f64 * 0.05 - scale_bonus).max(0.0)
+        }
+    }
+}
+
+impl From<UtilizationRecord> for RankedCandidate {
+    fn from(record: UtilizationRecord) -> Self {
+        let breakdown = ScoreBreakdown::for_record(&record);
+        Self {
+            name: record.name,
+            chute_id: record.chute_id,
+            active_instance_count: record.active_instance_count,
+            utilization_current: breakdown.utilization_current,
+            rate_limit_ratio_5m: breakdown.rate_limit_ratio_5m,
+            score: breakdown.score,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAiModelListResponse {
+    data: Vec<OpenAiModelItem>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct OpenAiModelItem {
+    id: String,
+    #[serde(default)]
+    pricing: Option<ModelPricing>,
+    #[serde(default)]
+    chute_id: Option<String>,
+    #[serde(default)]
+    supported_sampling_parameters: Option<Vec<String>>,
+    #[serde(default)]
+    max_output_length: Option<u64>,
+}
+
+/// Catalog prices in USD per million tokens.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+struct ModelPricing {
+    #[serde(default)]
+    prompt: f64,
+    #[serde(default)]
+    completion: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct UtilizationRecord {
+    #[serde(default)]
+    chute_id: Option<String>,
+    name: String,
+    #[serde(default)]
+    active_instance_count: u64,
+    #[serde(default)]
+    utilization_current: Option<f64>,
+    #[serde(default)]
+    utilization_5m: Option<f64>,
+    #[serde(default)]
+    utilization_15m: Option<f64>,
+    #[serde(default)]
+    utilization_1h: Option<f64>,
+    #[serde(default)]
+    rate_limit_ratio_5m: Option<f64>,
+    #[serde(default)]
+    rate_limit_ratio_15m: Option<f64>,
+    #[serde(default)]
+    rate_limit_ratio_1h: Option<f64>,
+    #[serde(default)]
+    scalable: bool,
+    #[serde(default)]
+    scale_allowance: Option<f64>,
+    #[serde(default)]
+    total_requests_5m: Option<f64>,
+    #[serde(default)]
+    total_requests_15m: Option<f64>,
+    #[serde(default)]
+    total_requests_1h: Option<f64>,
+    #[serde(default)]
+    rate_limited_requests_5m: Option<f64>,
+    #[serde(default)]
+    rate_limited_requests_15m: Option<f64>,
+    #[serde(default)]
+    rate_limited_requests_1h: Option<f64>,
+    #[serde(default)]
+    instance_count: Option<u64>,
+    #[serde(default)]
+    target_count: Option<u64>,
+    #[serde(default)]
+    action_taken: Option<String>,
+    #[serde(default)]
+    effective_multiplier: Option<f64>,
+    #[serde(default)]
+    avg_busy_ratio: Option<f64>,
+    #[serde(default)]
+    chutes_version: Option<String>,
+}
+
+impl UtilizationRecord {
+    fn is_private_chute(&self) -> bool {
+        self.name == "[private chute]"
+    }
+
+    fn is_scaling_down(&self) -> bool {
+        self.action_taken
+            .as_deref()
+            .is_some_and(|action| action.starts_with("scale_down"))
+    }
+
+    /// Rate-limited share of requests in a window, for feeds that omit `rate_limit_ratio_*`.
+    fn observed_rate_limit_ratio(limited: Option<f64>, total: Option<f64>) -> Option<f64> {
+        match (limited, total) {
+            (Some(limited), Some(total)) if total > 0.0 => Some((limited / total).clamp(0.0, 1.0)),
+            _ => None,
+        }
+    }
+}
+
+const MAX_MODEL_SUGGESTIONS: usize = 3;
+
+/// Ranks `known` ids by closeness to an unknown `model`, ignoring ASCII case: a case-only
+/// difference first, then a missing or extra `-TEE` suffix, then ids starting or ending with the
+/// input, `-TEE` aside (e.g. a dropped `owner/`), then edit distance up to a quarter of the input
+/// length (at least 2). Ties go to the smaller edit distance, then the name.
+///
+/// The edit distance is only computed for ids whose length is within that bound of the input's,
+/// so an oversized input costs linear time; other ids rank by the length difference instead.
+fn suggest_models<'a>(
+    model: &str,
+    known: impl Iterator<Item = &'a String>,
+    limit: usize,
+) -> Vec<String> {
+    let wanted = model.to_ascii_lowercase();
+    let wanted_base = wanted.strip_suffix("-tee").unwrap_or(&wanted);
+    let max_distance = (wanted.len() / 4).max(2);
+
+    let mut scored: Vec<(u8, usize, &String)> = known
+        .filter_map(|candidate| {
+            let lower = candidate.to_ascii_lowercase();
+            let length_gap = wanted.len().abs_diff(lower.len());
+            let distance = if length_gap <= max_distance {
+                edit_distance(&wanted, &lower)
+            } else {
+                length_gap
+            };
+            let base = lower.strip_suffix("-tee").unwrap_or(&lower);
+            let tier = if lower == wanted {
+                0
+            } else if base == wanted_base {
+                1
+            } else if wanted_base.len() >= 3
+                && (base.starts_with(wanted_base) || base.ends_with(wanted_base))
+            {
+                2
+            } else if distance <= max_distance {
+                3
+            } else {
+                return None;
+            };
+            Some((tier, distance, candidate))
+        })
+        .collect();
+    scored.sort();
+    scored.dedup_by(|a, b| a.2 == b.2);
+    scored
+        .into_iter()
+        .take(limit)
+        .map(|(_, _, candidate)| candidate.clone())
+        .collect()
+}
+
+/// Levenshtein distance over chars.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut row = vec![0; b.len() + 1];
+    for (i, ca) in a.chars().enumerate() {
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = prev[j] + usize::from(ca != *cb);
+            row[j + 1] = substitution.min(prev[j + 1] + 1).min(row[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut row);
+    }
+    prev[b.len()]
+}
+
+/// A preference-list entry that stands for several models.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ModelPattern<'a> {
+    /// `autopilot` (or the alias itself): the ranked pool.
+    AutoPilot,
+    /// An id containing `*`, which matches any run of characters.
+    Glob(&'a str),
+    /// `family:<name>`: models whose name after the owner starts with `<name>` (case-insensitive)
+    /// followed by a non-alphanumeric character or the end.
+    Family(&'a str),
+}
+
+impl<'a> ModelPattern<'a> {
+    fn parse(item: &'a str) -> Option<Self> {
+        if item.eq_ignore_ascii_case("autopilot") || is_autopilot_alias(item) {
+            Some(Self::AutoPilot)
+        } else if let Some(name) = item.strip_prefix("family:") {
+            Some(Self::Family(name.trim()))
+        } else if item.contains('*') {
+            Some(Self::Glob(item))
+        } else {
+            None
+        }
+    }
+
+    fn matches(&self, model: &str) -> bool {
+        match self {
+            Self::AutoPilot => true,
+            Self::Glob(pattern) => glob_matches(pattern, model),
+            Self::Family(family) => {
+                let name = model.rsplit_once('/').map_or(model, |(_, name)| name);
+                name.get(..family.len())
+                    .is_some_and(|prefix| prefix.eq_ignore_ascii_case(family))
+                    && !name[family.len()..].starts_with(|c: char| c.is_ascii_alphanumeric())
+            }
+        }
+    }
+}
+
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let Some(mut rest) = text.strip_prefix(parts.next().unwrap_or_default()) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(idx) => rest = &rest[idx + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ModelListError {
+    code: String,
+    message: String,
+}
+
+fn parse_model_preference_list(
+    model: &str,
+    max_items: usize,
+) -> Result<Vec<String>, ModelListError> {
+    let mut out: Vec<String> = Vec::new();
+    let mut seen: HashSet<String> = HashSet::new();
+
+    for raw in model.split(',') {
+        let item = raw.trim_matches(|c: char| c.is_ascii_whitespace());
+        if item.is_empty() {
+            continue;
+        }
+        if seen.insert(item.to_string()) {
+            out.push(item.to_string());
+        }
+    }
+
+    if out.is_empty() {
+        return Err(ModelListError {
+            code: "invalid_model_list".to_string(),
+            message: "model list is empty".to_string(),
+        });
+    }
+
+    if out.len() > max_items {
+        return Err(ModelListError {
+            code: "invalid_model_list".to_string(),
+            message: format!("model list exceeds MAX_MODEL_LIST_ITEMS ({max_items})"),
+        });
+    }
+
+    Ok(out)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OpenAiErrorResponse {
+    pub error: OpenAiError,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OpenAiError {
+    pub message: String,
+    #[serde(rename = "type")]
+    pub error_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub param: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+}
+
+pub fn openai_error_response(
+    status: StatusCode,
+    error_type: &str,
+    message: &str,
+    param: Option<&str>,
+    code: Option<&str>,
+) -> Response {
+    let body = OpenAiErrorResponse {
+        error: OpenAiError {
+            message: message.to_string(),
+            error_type: error_type.to_string(),
+            param: param.map(ToString::to_string),
+            code: code.map(ToString::to_string),
+        },
+    };
+    (status, Json(body)).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use axum::body::Body;
+    use axum::routing::post;
+    use axum::{Json, Router};
+    use http::Request;
+    use http_body_util::BodyExt;
+    use proptest::prelude::*;
+    use std::collections::HashSet;
+    use std::path::Path;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::TcpListener;
+    use tower::ServiceExt;
+
+    async fn spawn_upstream(app: Router) -> (String, tokio::task::JoinHandle<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let base_url = format!("http://{addr}");
+
+        let handle = tokio::spawn(async move {
+            axum::serve(listener, app.into_make_service())
+                .await
+                .unwrap();
+        });
+
+        (base_url, handle)
+    }
+
+    fn test_config(backend_base_url: String) -> AppConfig {
+        AppConfig {
+            backend_base_url,
+            upstream_connect_timeout: Duration::from_millis(200),
+            upstream_header_timeout: Duration::from_millis(200),
+            upstream_first_body_byte_timeout: Duration::from_millis(50),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn parse_model_list_basic() {
+        let got = parse_model_preference_list("a,b,c", 8).unwrap();
+        assert_eq!(got, vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn parse_model_list_trims_and_dedups_preserving_order() {
+        let got = parse_model_preference_list(" a , b , b ", 8).unwrap();
+        assert_eq!(got, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn parse_model_list_discards_empty_items() {
+        let got = parse_model_preference_list("a,,b,", 8).unwrap();
+        assert_eq!(got, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn parse_model_list_empty_returns_400_code() {
+        let err = parse_model_preference_list(",", 8).unwrap_err();
+        assert_eq!(err.code, "invalid_model_list");
+    }
+
+    #[test]
+    fn parse_model_list_enforces_max_items() {
+        let err = parse_model_preference_list("a,b,c", 2).unwrap_err();
+        assert_eq!(err.code, "invalid_model_list");
+    }
+
+    proptest! {
+        #[test]
+        fn parse_model_list_trims_and_dedups_property(items in proptest::collection::vec("[A-Za-z0-9_./-]{1,12}", 1..8)) {
+            let raw = items
+                .iter()
+                .map(|s| format!(" {s} "))
+                .collect::<Vec<_>>()
+                .join(",");
+
+            let mut seen = std::collections::HashSet::new();
+            let expected: Vec<String> = items
+                .iter()
+                .filter_map(|s| {
+                    if seen.insert(s.clone()) {
+                        Some(s.clone())
+                    } else {
+                        None
+                    }
+                })
+                .collect();
+
+            let got = parse_model_preference_list(&raw, 8).unwrap();
+            prop_assert_eq!(got, expected);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn parse_model_list_errors_when_unique_items_exceed_max(items in proptest::collection::vec("[A-Za-z0-9_./-]{1,12}", 9..16)) {
+            let unique: std::collections::HashSet<String> = items.iter().cloned().collect();
+            prop_assume!(unique.len() > 8);
+
+            let raw = items.join(",");
+            let result = parse_model_preference_list(&raw, 8);
+            prop_assert!(result.is_err());
+        }
+    }
+
+    #[test]
+    fn derive_sticky_key_ignores_xff_by_default() {
+        let cfg = AppConfig::default();
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-forwarded-for",
+            HeaderValue::from_static("198.51.100.7, 203.0.113.10"),
+        );
+        let connect_info = Some(ConnectInfo(
+            "203.0.113.10:1234".parse::<SocketAddr>().unwrap(),
+        ));
+
+        let got = derive_sticky_key(&cfg, &headers, &connect_info).unwrap();
+        assert_eq!(got, "ip:203.0.113.10");
+    }
+
+    #[test]
+    fn derive_sticky_key_does_not_use_xff_when_peer_is_not_trusted() {
+        let cfg = AppConfig {
+            trust_proxy_headers: true,
+            trusted_proxy_cidrs: vec!["192.0.2.0/24".parse::<IpNet>().unwrap()],
+            ..Default::default()
+        };
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", HeaderValue::from_static("198.51.100.7"));
+        let connect_info = Some(ConnectInfo(
+            "203.0.113.10:1234".parse::<SocketAddr>().unwrap(),
+        ));
+
+        let got = derive_sticky_key(&cfg, &headers, &connect_info).unwrap();
+        assert_eq!(got, "ip:203.0.113.10");
+    }
+
+    #[test]
+    fn derive_sticky_key_uses_xff_when_peer_is_trusted() {
+        let cfg = AppConfig {
+            trust_proxy_headers: true,
+            trusted_proxy_cidrs: vec!["203.0.113.0/24".parse::<IpNet>().unwrap()],
+            ..Default::default()
+        };
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-forwarded-for",
+            HeaderValue::from_static("198.51.100.7, 203.0.113.10"),
+        );
+        let connect_info = Some(ConnectInfo(
+            "203.0.113.10:1234".parse::<SocketAddr>().unwrap(),
+        ));
+
+        let got = derive_sticky_key(&cfg, &headers, &connect_info).unwrap();
+        assert_eq!(got, "ip:198.51.100.7");
+    }
+
+    proptest! {
+        #[test]
+        fn derive_sticky_key_prefers_bearer_token_even_with_whitespace(token in "[A-Za-z0-9]{1,32}") {
+            let cfg = AppConfig::default();
+
+            let mut headers_spaced = HeaderMap::new();
+            headers_spaced.insert(
+                "authorization",
+                HeaderValue::from_str(&format!("  Bearer   {token}   ")).unwrap(),
+            );
+
+            let mut headers_clean = HeaderMap::new();
+            headers_clean.insert(
+                "authorization",
+                HeaderValue::from_str(&format!("Bearer {token}")).unwrap(),
+            );
+
+            let connect_info = Some(ConnectInfo(
+                "203.0.113.10:1234".parse::<SocketAddr>().unwrap(),
+            ));
+
+            let spaced = derive_sticky_key(&cfg, &headers_spaced, &connect_info).unwrap();
+            let clean = derive_sticky_key(&cfg, &headers_clean, &connect_info).unwrap();
+
+            prop_assert!(spaced.starts_with("auth:"));
+            prop_assert_eq!(spaced, clean);
+        }
+    }
+
+    #[test]
+    fn rank_candidates_uses_tee_suffix_without_allowlist() {
+        let ranked = rank_candidates(
+            vec![
+                UtilizationRecord {
+                    name: "model-A-TEE".to_string(),
+                    active_instance_count: 1,
+                    utilization_current: Some(0.1),
+                    utilization_5m: Some(0.1),
+                    utilization_15m: Some(0.1),
+                    utilization_1h: Some(0.1),
+                    rate_limit_ratio_5m: Some(0.0),
+                    rate_limit_ratio_15m: Some(0.0),
+                    rate_limit_ratio_1h: Some(0.0),
+                    scalable: false,
+                    scale_allowance: Some(0.0),
+                    ..Default::default()
+                },
+                UtilizationRecord {
+                    name: "model-B".to_string(),
+                    active_instance_count: 10,
+                    utilization_current: Some(0.1),
+                    utilization_5m: Some(0.1),
+                    utilization_15m: Some(0.1),
+                    utilization_1h: Some(0.1),
+                    rate_limit_ratio_5m: Some(0.0),
+                    rate_limit_ratio_15m: Some(0.0),
+                    rate_limit_ratio_1h: Some(0.0),
+                    scalable: false,
+                    scale_allowance: Some(0.0),
+                    ..Default::default()
+                },
+            ],
+            &HashSet::new(),
+        );
+
+        assert_eq!(ranked.len(), 1);
+        assert_eq!(ranked[0].name, "model-A-TEE");
+    }
+
+    #[test]
+    fn rank_candidates_filters_by_model_allowlist_when_present() {
+        let allowlist = HashSet::from(["allow/Model".to_string(), "keep/Model".to_string()]);
+        let ranked = rank_candidates(
+            vec![
+                UtilizationRecord {
+                    name: "allow/Model".to_string(),
+                    active_instance_count: 2,
+                    utilization_current: Some(0.2),
+                    utilization_5m: Some(0.2),
+                    utilization_15m: Some(0.2),
+                    utilization_1h: Some(0.2),
+                    rate_limit_ratio_5m: Some(0.0),
+                    rate_limit_ratio_15m: Some(0.0),
+                    rate_limit_ratio_1h: Some(0.0),
+                    scalable: false,
+                    scale_allowance: Some(0.0),
+                    ..Default::default()
+                },
+                UtilizationRecord {
+                    name: "blocked/Model-TEE".to_string(),
+                    active_instance_count: 100,
+                    utilization_current: Some(0.0),
+                    utilization_5m: Some(0.0),
+                    utilization_15m: Some(0.0),
+                    utilization_1h: Some(0.0),
+                    rate_limit_ratio_5m: Some(0.0),
+                    rate_limit_ratio_15m: Some(0.0),
+                    rate_limit_ratio_1h: Some(0.0),
+                    scalable: false,
+                    scale_allowance: Some(0.0),
+                    ..Default::default()
+                },
+            ],
+            &allowlist,
+        );
+
+        assert_eq!(ranked.len(), 1);
+        assert_eq!(ranked[0].name, "allow/Model");
+    }
+
+    #[test]
+    fn rank_candidates_fixture_filters_and_orders_candidates() {
+        let records: Vec<UtilizationRecord> =
+            serde_json::from_str(include_str!("../tests/testdata/utilization_fixture.json"))
+                .unwrap();
+
+        let ranked = rank_candidates(records, &HashSet::new());
+        let names: Vec<String> = ranked.into_iter().map(|c| c.name).collect();
+        assert_eq!(names, vec!["alpha-TEE", "beta-TEE", "gamma-TEE"]);
+    }
+
+    #[test]
+    fn ranked_candidate_score_matches_spec_formula() {
+        let record = UtilizationRecord {
+            name: "score-TEE".to_string(),
+            active_instance_count: 4,
+            utilization_current: Some(0.5),
+            utilization_5m: Some(0.5),
+            utilization_15m: Some(0.25),
+            utilization_1h: None,
+            rate_limit_ratio_5m: Some(0.25),
+            rate_limit_ratio_15m: Some(0.5),
+            rate_limit_ratio_1h: Some(1.0),
+            scalable: true,
+            scale_allowance: Some(8.0),
+            ..Default::default()
+        };
+
+        let ranked = RankedCandidate::from(record);
+        let expected = 0.8;
+        assert!(
+            (ranked.score - expected).abs() < 1e-6,
+            "got {}, expected {}",
+            ranked.score,
+            expected
+        );
+    }
+
+    #[test]
+    fn sort_ranked_candidates_tiebreaks_by_active_instance_count_desc() {
+        let mut ranked = vec![
+            RankedCandidate {
+                name: "low-active".to_string(),
+                chute_id: None,
+                active_instance_count: 1,
+                utilization_current: 0.0,
+                rate_limit_ratio_5m: 0.0,
+                score: 1.0,
+            },
+            RankedCandidate {
+                name: "high-active".to_string(),
+                chute_id: None,
+                active_instance_count: 2,
+                utilization_current: 0.0,
+                rate_limit_ratio_5m: 0.0,
+                score: 1.0,
+            },
+        ];
+
+        sort_ranked_candidates(&mut ranked);
+        assert_eq!(ranked[0].name, "high-active");
+    }
+
+    #[test]
+    fn sort_ranked_candidates_tiebreaks_by_utilization_current_asc() {
+        let mut ranked = vec![
+            RankedCandidate {
+                name: "higher-util".to_string(),
+                chute_id: None,
+                active_instance_count: 1,
+                utilization_current: 0.5,
+                rate_limit_ratio_5m: 0.0,
+                score: 1.0,
+            },
+            RankedCandidate {
+                name: "lower-util".to_string(),
+                chute_id: None,
+                active_instance_count: 1,
+                utilization_current: 0.25,
+                rate_limit_ratio_5m: 0.0,
+                score: 1.0,
+            },
+        ];
+
+        sort_ranked_candidates(&mut ranked);
+        assert_eq!(ranked[0].name, "lower-util");
+    }
+
+    #[test]
+    fn sort_ranked_candidates_tiebreaks_by_rate_limit_ratio_5m_asc() {
+        let mut ranked = vec![
+            RankedCandidate {
+                name: "higher-rl".to_string(),
+                chute_id: None,
+                active_instance_count: 1,
+                utilization_current: 0.0,
+                rate_limit_ratio_5m: 0.5,
+                score: 1.0,
+            },
+            RankedCandidate {
+                name: "lower-rl".to_string(),
+                chute_id: None,
+                active_instance_count: 1,
+                utilization_current: 0.0,
+                rate_limit_ratio_5m: 0.25,
+                score: 1.0,
+            },
+        ];
+
+        sort_ranked_candidates(&mut ranked);
+        assert_eq!(ranked[0].name, "lower-rl");
+    }
+
+    #[test]
+    fn sort_ranked_candidates_tiebreaks_by_name_asc() {
+        let mut ranked = vec![
+            RankedCandidate {
+                name: "b".to_string(),
+                chute_id: None,
+                active_instance_count: 1,
+                utilization_current: 0.0,
+                rate_limit_ratio_5m: 0.0,
+                score: 1.0,
+            },
+            RankedCandidate {
+                name: "a".to_string(),
+                chute_id: None,
+                active_instance_count: 1,
+                utilization_current: 0.0,
+                rate_limit_ratio_5m: 0.0,
+                score: 1.0,
+            },
+        ];
+
+        sort_ranked_candidates(&mut ranked);
+        assert_eq!(ranked[0].name, "a");
+    }
+
+    #[tokio::test]
+    async fn candidate_snapshot_keeps_last_known_good_on_error() {
+        let state = AppState::new(AppConfig::default());
+        let snapshot_at = Instant::now() - Duration::from_secs(60);
+        {
+            let mut runtime = state.runtime.write().await;
+            runtime.candidates = vec![RankedCandidate {
+                name: "keep".to_string(),
+                chute_id: None,
+                active_instance_count: 1,
+                utilization_current: 0.0,
+                rate_limit_ratio_5m: 0.0,
+                score: 1.0,
+            }];
+            runtime.snapshot_at = Some(snapshot_at);
+        }
+
+        state
+            .update_candidate_snapshot(Err(anyhow::anyhow!("boom")))
+            .await;
+
+        let runtime = state.runtime.read().await;
+        assert_eq!(runtime.candidates.len(), 1);
+        assert_eq!(runtime.candidates[0].name, "keep");
+        assert_eq!(runtime.snapshot_at, Some(snapshot_at));
+    }
+
+    #[tokio::test]
+    async fn chat_completions_prefers_sticky_model_for_autopilot() {
+        let attempts: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+        let upstream_attempts = attempts.clone();
+        let upstream = Router::new().route(
+            "/v1/chat/completions",
+            post(move |Json(v): Json<Value>| {
+                let upstream_attempts = upstream_attempts.clone();
+                async move {
+                    let model = v
+                        .get("model")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string();
+                    upstream_attempts.lock().unwrap().push(model);
+                    (StatusCode::OK, "ok")
+                }
+            }),
+        );
+        let (base_url, upstream_handle) = spawn_upstream(upstream).await;
+
+        let state = AppState::new(test_config(base_url));
+        {
+            let mut runtime = state.runtime.write().await;
+            runtime.candidates = vec![
+                RankedCandidate {
+                    name: "first/TEE-Model".to_string(),
+                    chute_id: None,
+                    active_instance_count: 10,
+                    utilization_current: 0.2,
+                    rate_limit_ratio_5m: 0.0,
+                    score: 8.0,
+                },
+                RankedCandidate {
+                    name: "second/TEE-Model".to_string(),
+                    chute_id: None,
+                    active_instance_count: 1,
+                    utilization_current: 0.1,
+                    rate_limit_ratio_5m: 0.0,
+                    score: 1.0,
+                },
+            ];
+        }
+        let mut auth_headers = HeaderMap::new();
+        auth_headers.insert(
+            "authorization",
+            HeaderValue::from_static("Bearer sticky-api-token"),
+        );
+        let no_connect_info: Option<ConnectInfo<SocketAddr>> = None;
+
+        state
+            .set_sticky_model(
+                derive_sticky_key(&state.config, &auth_headers, &no_connect_info).unwrap(),
+                "second/TEE-Model".to_string(),
+            )
+            .await;
+
+        let app = app(state);
+        let resp = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/chat/completions")
+                    .header("authorization", "Bearer sticky-api-token")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"model":"chutesai/AutoPilot"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get("x-chutes-autopilot-selected").unwrap(),
+            &axum::http::header::HeaderValue::from_static("second/TEE-Model")
+        );
+
+        let got_attempts = attempts.lock().unwrap().clone();
+        assert_eq!(got_attempts, vec!["second/TEE-Model".to_string()]);
+
+        let _ = resp.into_body().collect().await.unwrap();
+        upstream_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn chat_completions_prefers_fresh_candidate_when_sticky_is_not_valid() {
+        let attempts: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+        let upstream_attempts = attempts.clone();
+        let upstream = Router::new().route(
+            "/v1/chat/completions",
+            post(move |Json(v): Json<Value>| {
+                let upstream_attempts = upstream_attempts.clone();
+                async move {
+                    let model = v
+                        .get("model")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string();
+                    upstream_attempts.lock().unwrap().push(model);
+                    (StatusCode::OK, "ok")
+                }
+            }),
+        );
+        let (base_url, upstream_handle) = spawn_upstream(upstream).await;
+
+        let state = AppState::new(test_config(base_url));
+        {
+            let mut runtime = state.runtime.write().await;
+            runtime.candidates = vec![RankedCandidate {
+                name: "only/TEE-Model".to_string(),
+                chute_id: None,
+                active_instance_count: 2,
+                utilization_current: 0.1,
+                rate_limit_ratio_5m: 0.0,
+                score: 4.0,
+            }];
+        }
+
+        let mut auth_headers = HeaderMap::new();
+        auth_headers.insert(
+            "authorization",
+            HeaderValue::from_static("Bearer stale-sticky-key"),
+        );
+        let no_connect_info: Option<ConnectInfo<SocketAddr>> = None;
+        state
+            .set_sticky_model(
+                derive_sticky_key(&state.config, &auth_headers, &no_connect_info).unwrap(),
+                "missing/TEE-Model".to_string(),
+            )
+            .await;
+
+        let app = app(state);
+        let resp = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/chat/completions")
+                    .header("authorization", "Bearer stale-sticky-key")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"model":"chutesai/AutoPilot"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get("x-chutes-autopilot-selected").unwrap(),
+            &axum::http::header::HeaderValue::from_static("only/TEE-Model")
+        );
+
+        let got_attempts = attempts.lock().unwrap().clone();
+        assert_eq!(got_attempts, vec!["only/TEE-Model".to_string()]);
+
+        let _ = resp.into_body().collect().await.unwrap();
+        upstream_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn rotate_sticky_model_moves_to_next_candidate() {
+        let state = AppState::new(AppConfig::default());
+        state
+            .set_sticky_model("client-key".to_string(), "first".to_string())
+            .await;
+
+        let candidates = vec![
+            "first".to_string(),
+            "second".to_string(),
+            "third".to_string(),
+        ];
+
+        state
+            .rotate_sticky_model("client-key", &candidates, "first")
+            .await;
+        assert_eq!(
+            state.sticky_model("client-key").await,
+            Some("second".to_string())
+        );
+
+        state
+            .rotate_sticky_model("client-key", &candidates, "second")
+            .await;
+        assert_eq!(
+            state.sticky_model("client-key").await,
+            Some("third".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn sticky_map_evicts_oldest_entries_when_over_cap() {
+        let cfg = AppConfig {
+            sticky_max_entries: 2,
+            ..Default::default()
+        };
+
+        let state = AppState::new(cfg);
+        let now = Instant::now();
+
+        {
+            let mut runtime = state.runtime.write().await;
+            runtime.sticky_models.insert(
+                "old".to_string(),
+                StickyModelSelection {
+                    model: "old-model".to_string(),
+                    last_used_at: now - Duration::from_secs(10),
+                },
+            );
+            runtime.sticky_models.insert(
+                "mid".to_string(),
+                StickyModelSelection {
+                    model: "mid-model".to_string(),
+                    last_used_at: now - Duration::from_secs(5),
+                },
+            );
+            runtime.sticky_models.insert(
+                "new".to_string(),
+                StickyModelSelection {
+                    model: "new-model".to_string(),
+                    last_used_at: now,
+                },
+            );
+
+            AppState::enforce_sticky_cap(&mut runtime, state.config.sticky_max_entries);
+            assert_eq!(runtime.sticky_models.len(), 2);
+            assert!(!runtime.sticky_models.contains_key("old"));
+            assert!(runtime.sticky_models.contains_key("mid"));
+            assert!(runtime.sticky_models.contains_key("new"));
+        }
+    }
+
+    #[tokio::test]
+    async fn chat_completions_allows_autopilot_alias_without_tee_suffix_when_ranked_model_exists() {
+        let attempts: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+        let upstream_attempts = attempts.clone();
+        let upstream = Router::new().route(
+            "/v1/chat/completions",
+            post(move |Json(v): Json<Value>| {
+                let upstream_attempts = upstream_attempts.clone();
+                async move {
+                    let model = v
+                        .get("model")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string();
+                    upstream_attempts.lock().unwrap().push(model);
+                    (StatusCode::OK, "ok")
+                }
+            }),
+        );
+        let (base_url, upstream_handle) = spawn_upstream(upstream).await;
+
+        let state = AppState::new(test_config(base_url));
+        {
+            let mut runtime = state.runtime.write().await;
+            runtime.candidates = vec![
+                RankedCandidate {
+                    name: "chosen/TEE-Model".to_string(),
+                    chute_id: None,
+                    active_instance_count: 10,
+                    utilization_current: 0.2,
+                    rate_limit_ratio_5m: 0.0,
+                    score: 8.0,
+                },
+                RankedCandidate {
+                    name: "fallback/TEE-Model".to_string(),
+                    chute_id: None,
+                    active_instance_count: 1,
+                    utilization_current: 0.1,
+                    rate_limit_ratio_5m: 0.0,
+                    score: 1.0,
+                },
+            ];
+        }
+
+        let app = app(state);
+
+        let resp = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/chat/completions")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"model":"chutesai/AutoPilot"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        assert_eq!(
+            resp.headers().get("x-chutes-autopilot-selected").unwrap(),
+            &axum::http::header::HeaderValue::from_static("chosen/TEE-Model")
+        );
+
+        let got_attempts = attempts.lock().unwrap().clone();
+        assert_eq!(got_attempts, vec!["chosen/TEE-Model".to_string()]);
+
+        let _ = resp.into_body().collect().await.unwrap();
+        upstream_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn healthz_ok() {
+        let state = AppState::new(AppConfig::default());
+        let app = app(state);
+
+        let resp = app
+            .oneshot(
+                Request::builder()
+                    .uri("/healthz")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn metrics_endpoint_returns_prometheus_text() {
+        let state = AppState::new(AppConfig::default());
+        let app = app(state);
+
+        let resp = app
+            .oneshot(
+                Request::builder()
+                    .uri("/metrics")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers()
+                .get(axum::http::header::CONTENT_TYPE.as_str())
+                .unwrap(),
+            &HeaderValue::from_static("text/plain; version=0.0.4")
+        );
+
+        let body = resp.into_body().collect().await.unwrap().to_bytes();
+        let text = String::from_utf8(body.to_vec()).unwrap();
+        assert!(text.contains("chutes_autopilot_requests_active"));
+    }
+
+    #[tokio::test]
+    async fn readyz_defaults_to_503() {
+        let state = AppState::new(AppConfig::default());
+        let app = app(state);
+
+        let resp = app
+            .oneshot(
+                Request::builder()
+                    .uri("/readyz")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let body = resp.into_body().collect().await.unwrap().to_bytes();
+        let parsed: OpenAiErrorResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(parsed.error.code.as_deref(), Some("missing_allowlist"));
+    }
+
+    #[tokio::test]
+    async fn readyz_returns_200_when_snapshot_is_fresh_and_non_empty() {
+        let state = AppState::new(AppConfig::default());
+        {
+            let mut runtime = state.runtime.write().await;
+            runtime.models_allowlist = HashSet::from(["ready/TEE-Model".to_string()]);
+            runtime.models_allowlist_at = Some(Instant::now());
+            runtime.candidates = vec![RankedCandidate {
+                name: "ready/TEE-Model".to_string(),
+                chute_id: None,
+                active_instance_count: 1,
+                utilization_current: 0.0,
+                rate_limit_ratio_5m: 0.0,
+                score: 1.0,
+            }];
+            runtime.snapshot_at = Some(Instant::now());
+        }
+        let app = app(state);
+
+        let resp = app
+            .oneshot(
+                Request::builder()
+                    .uri("/readyz")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn readyz_returns_503_when_snapshot_is_stale() {
+        let cfg = AppConfig {
+            readyz_max_snapshot_age: Duration::from_millis(1),
+            ..Default::default()
+        };
+
+        let state = AppState::new(cfg);
+        {
+            let mut runtime = state.runtime.write().await;
+            runtime.models_allowlist = HashSet::from(["stale/TEE-Model".to_string()]);
+            runtime.models_allowlist_at = Some(Instant::now());
+            runtime.candidates = vec![RankedCandidate {
+                name: "stale/TEE-Model".to_string(),
+                chute_id: None,
+                active_instance_count: 1,
+                utilization_current: 0.0,
+                rate_limit_ratio_5m: 0.0,
+                score: 1.0,
+            }];
+            runtime.snapshot_at = Some(Instant::now() - Duration::from_secs(1));
+        }
+        let app = app(state);
+
+        let resp = app
+            .oneshot(
+                Request::builder()
+                    .uri("/readyz")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+        let parsed: OpenAiErrorResponse = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(parsed.error.code.as_deref(), Some("stale_snapshot"));
+    }
+
+    #[tokio::test]
+    async fn readyz_returns_503_when_allowlist_is_empty() {
+        let state = AppState::new(AppConfig::default());
+        {
+            let mut runtime = state.runtime.write().await;
+            runtime.models_allowlist_at = Some(Instant::now());
+            runtime.candidates = vec![RankedCandidate {
+                name: "ready/TEE-Model".to_string(),
+                chute_id: None,
+                active_instance_count: 1,
+                utilization_current: 0.0,
+                rate_limit_ratio_5m: 0.0,
+                score: 1.0,
+            }];
+            runtime.snapshot_at = Some(Instant::now());
+        }
+        let app = app(state);
+
+        let resp = app
+            .oneshot(
+                Request::builder()
+                    .uri("/readyz")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+        let parsed: OpenAiErrorResponse = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(parsed.error.code.as_deref(), Some("allowlist_empty"));
+    }
+
+    #[tokio::test]
+    async fn readyz_returns_503_when_allowlist_is_stale() {
+        let cfg = AppConfig {
+            readyz_max_allowlist_age: Duration::from_millis(1),
+            ..Default::default()
+        };
+        let state = AppState::new(cfg);
+        {
+            let mut runtime = state.runtime.write().await;
+            runtime.models_allowlist = HashSet::from(["ready/TEE-Model".to_string()]);
+            runtime.models_allowlist_at = Some(Instant::now() - Duration::from_secs(1));
+            runtime.candidates = vec![RankedCandidate {
+                name: "ready/TEE-Model".to_string(),
+                chute_id: None,
+                active_instance_count: 1,
+                utilization_current: 0.0,
+                rate_limit_ratio_5m: 0.0,
+                score: 1.0,
+            }];
+            runtime.snapshot_at = Some(Instant::now());
+        }
+        let app = app(state);
+
+        let resp = app
+            .oneshot(
+                Request::builder()
+                    .uri("/readyz")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+        let parsed: OpenAiErrorResponse = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(parsed.error.code.as_deref(), Some("stale_allowlist"));
+    }
+
+    #[tokio::test]
+    async fn chat_completions_direct_mode_proxies_upstream_without_selected_header() {
+        let attempts: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+        let upstream_attempts = attempts.clone();
+        let upstream = Router::new().route(
+            "/v1/chat/completions",
+            post(move |Json(v): Json<Value>| {
+                let upstream_attempts = upstream_attempts.clone();
+                async move {
+                    let model = v
+                        .get("model")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string();
+                    upstream_attempts.lock().unwrap().push(model);
+                    (StatusCode::CREATED, "direct-ok")
+                }
+            }),
+        );
+        let (base_url, upstream_handle) = spawn_upstream(upstream).await;
+
+        let state = AppState::new(test_config(base_url));
+        let app = app(state);
+
+        let resp = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/chat/completions")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"model":"direct-nontee"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        assert!(resp.headers().get("x-chutes-autopilot-selected").is_none());
+
+        let got_attempts = attempts.lock().unwrap().clone();
+        assert_eq!(got_attempts, vec!["direct-nontee".to_string()]);
+
+        let body = resp.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, Bytes::from_static(b"direct-ok"));
+
+        upstream_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn chat_completions_streams_upstream_body_without_buffering() {
+        let upstream = Router::new().route(
+            "/v1/chat/completions",
+            post(|Json(_): Json<Value>| async move {
+                let first = stream::once(async move {
+                    Ok::<Bytes, std::io::Error>(Bytes::from_static(b"first"))
+                });
+                let second = stream::once(async move {
+                    tokio::time::sleep(Duration::from_millis(200)).await;
+                    Ok::<Bytes, std::io::Error>(Bytes::from_static(b"second"))
+                });
+                let combined = first.chain(second);
+
+                let mut resp = Response::new(Body::from_stream(combined));
+                *resp.status_mut() = StatusCode::OK;
+                resp
+            }),
+        );
+        let (upstream_url, upstream_handle) = spawn_upstream(upstream).await;
+
+        let state = AppState::new(test_config(upstream_url));
+        let router = app(state);
+        let (autopilot_url, autopilot_handle) = spawn_upstream(router).await;
+
+        let resp = reqwest::Client::new()
+            .post(format!("{autopilot_url}/v1/chat/completions"))
+            .header("content-type", "application/json")
+            .body(r#"{"model":"direct-TEE"}"#)
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let mut body_stream = resp.bytes_stream();
+        let first = tokio::time::timeout(Duration::from_millis(100), body_stream.next())
+            .await
+            .expect("timed out waiting for first body chunk")
+            .expect("upstream response ended early")
+            .expect("upstream chunk error");
+        assert_eq!(first, Bytes::from_static(b"first"));
+
+        let mut rest: Vec<u8> = Vec::new();
+        while let Some(item) = body_stream.next().await {
+            let chunk = item.expect("upstream chunk error");
+            rest.extend_from_slice(chunk.as_ref());
+        }
+
+        let mut combined: Vec<u8> = Vec::new();
+        combined.extend_from_slice(first.as_ref());
+        combined.extend_from_slice(&rest);
+        assert_eq!(combined, b"firstsecond");
+
+        autopilot_handle.abort();
+        upstream_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn chat_completions_does_not_set_sticky_model_on_upstream_error() {
+        let attempts: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+        let upstream_attempts = attempts.clone();
+        let upstream = Router::new().route(
+            "/v1/chat/completions",
+            post(move |Json(v): Json<Value>| {
+                let upstream_attempts = upstream_attempts.clone();
+                async move {
+                    let model = v
+                        .get("model")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string();
+                    upstream_attempts.lock().unwrap().push(model);
+                    (StatusCode::INTERNAL_SERVER_ERROR, "nope")
+                }
+            }),
+        );
+        let (base_url, upstream_handle) = spawn_upstream(upstream).await;
+
+        let state = AppState::new(test_config(base_url));
+        let app = app(state.clone());
+
+        let auth_headers = HeaderMap::from_iter([(
+            axum::http::header::AUTHORIZATION,
+            HeaderValue::from_static("Bearer sticky-api-token"),
+        )]);
+        let client_key = derive_sticky_key(&state.config, &auth_headers, &None).unwrap();
+
+        let resp = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/chat/completions")
+                    .header("content-type", "application/json")
+                    .header("authorization", "Bearer sticky-api-token")
+                    .body(Body::from(r#"{"model":"direct-nontee"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(state.sticky_model(&client_key).await.is_none());
+
+        let _ = resp.into_body().collect().await.unwrap();
+        upstream_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn chat_completions_preserves_compressed_upstream_bytes_and_headers() {
+        use flate2::write::GzEncoder;
+        use flate2::Compression;
+        use std::io::Write;
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(b"hello").unwrap();
+        let gz = encoder.finish().unwrap();
+
+        let upstream_gz = gz.clone();
+        let upstream = Router::new().route(
+            "/v1/chat/completions",
+            post(move |Json(_): Json<Value>| {
+                let upstream_gz = upstream_gz.clone();
+                async move {
+                    let mut resp = Response::new(Body::from(upstream_gz));
+                    *resp.status_mut() = StatusCode::OK;
+                    resp.headers_mut().insert(
+                        axum::http::header::CONTENT_ENCODING,
+                        HeaderValue::from_static("gzip"),
+                    );
+                    resp
+                }
+            }),
+        );
+        let (upstream_url, upstream_handle) = spawn_upstream(upstream).await;
+
+        let state = AppState::new(test_config(upstream_url));
+        let router = app(state);
+        let (autopilot_url, autopilot_handle) = spawn_upstream(router).await;
+
+        let resp = reqwest::Client::new()
+            .post(format!("{autopilot_url}/v1/chat/completions"))
+            .header("content-type", "application/json")
+            .body(r#"{"model":"direct-nontee"}"#)
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers()
+                .get(axum::http::header::CONTENT_ENCODING.as_str())
+                .map(|v| v.to_str().unwrap()),
+            Some("gzip")
+        );
+
+        let body = resp.bytes().await.unwrap();
+        assert_eq!(body.as_ref(), gz.as_slice());
+
+        autopilot_handle.abort();
+        upstream_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn chat_completions_returns_openai_error_shape() {
+        let state = AppState::new(AppConfig::default());
+        let app = app(state);
+
+        let resp = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/chat/completions")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"model":"chutesai/AutoPilot"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+        let parsed: OpenAiErrorResponse = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(parsed.error.error_type, "server_error");
+        assert_eq!(parsed.error.param.as_deref(), Some("model"));
+        assert_eq!(parsed.error.code.as_deref(), Some("no_candidates"));
+    }
+
+    #[tokio::test]
+    async fn chat_completions_rejects_model_list_items_not_in_allowlist_when_present() {
+        let state = AppState::new(AppConfig::default());
+        {
+            let mut runtime = state.runtime.write().await;
+            runtime.models_allowlist =
+                HashSet::from(["allowed/Model".to_string(), "other/Model".to_string()]);
+        }
+
+        let app = app(state);
+        let resp = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/chat/completions")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"model":"allowed/Model,missing/Model"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+        let parsed: OpenAiErrorResponse = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(parsed.error.error_type, "invalid_request_error");
+        assert_eq!(parsed.error.param.as_deref(), Some("model"));
+        assert_eq!(parsed.error.code.as_deref(), Some("unknown_model"));
+    }
+
+    #[tokio::test]
+    async fn chat_completions_model_list_failover_on_503() {
+        let attempts: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+        let upstream_attempts = attempts.clone();
+        let upstream = Router::new().route(
+            "/v1/chat/completions",
+            post(move |Json(v): Json<Value>| {
+                let upstream_attempts = upstream_attempts.clone();
+                async move {
+                    let model = v
+                        .get("model")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string();
+                    upstream_attempts.lock().unwrap().push(model.clone());
+
+                    if model == "first-TEE" {
+                        return (StatusCode::SERVICE_UNAVAILABLE, "try later").into_response();
+                    }
+
+                    (StatusCode::OK, "ok").into_response()
+                }
+            }),
+        );
+        let (base_url, upstream_handle) = spawn_upstream(upstream).await;
+
+        let state = AppState::new(test_config(base_url));
+        let app = app(state);
+
+        let resp = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/chat/completions")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"model":"first-TEE,second-TEE"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get("x-chutes-autopilot-selected").unwrap(),
+            &axum::http::header::HeaderValue::from_static("second-TEE")
+        );
+
+        let got_attempts = attempts.lock().unwrap().clone();
+        assert_eq!(
+            got_attempts,
+            vec!["first-TEE".to_string(), "second-TEE".to_string()]
+        );
+
+        let _ = resp.into_body().collect().await.unwrap();
+        upstream_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn chat_completions_model_list_failover_on_header_timeout() {
+        let attempts: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+        let upstream_attempts = attempts.clone();
+        let upstream = Router::new().route(
+            "/v1/chat/completions",
+            post(move |Json(v): Json<Value>| {
+                let upstream_attempts = upstream_attempts.clone();
+                async move {
+                    let model = v
+                        .get("model")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string();
+                    upstream_attempts.lock().unwrap().push(model.clone());
+
+                    if model == "slow-TEE" {
+                        tokio::time::sleep(Duration::from_millis(200)).await;
+                        return (StatusCode::OK, "slow").into_response();
+                    }
+
+                    (StatusCode::OK, "fast").into_response()
+                }
+            }),
+        );
+        let (base_url, upstream_handle) = spawn_upstream(upstream).await;
+
+        let mut cfg = test_config(base_url);
+        cfg.upstream_header_timeout = Duration::from_millis(50);
+        let state = AppState::new(cfg);
+        let app = app(state);
+
+        let resp = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/chat/completions")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"model":"slow-TEE,fast-TEE"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get("x-chutes-autopilot-selected").unwrap(),
+            &axum::http::header::HeaderValue::from_static("fast-TEE")
+        );
+
+        let got_attempts = attempts.lock().unwrap().clone();
+        assert_eq!(
+            got_attempts,
+            vec!["slow-TEE".to_string(), "fast-TEE".to_string()]
+        );
+
+        let body = resp.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, Bytes::from_static(b"fast"));
+
+        upstream_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn chat_completions_failover_on_connection_reset_before_headers() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let base_url = format!("http://{addr}");
+
+        let upstream_handle = tokio::spawn(async move {
+            let mut seen = 0u32;
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                seen += 1;
+
+                if seen == 1 {
+                    // Drop the connection immediately to simulate a reset before headers.
+                    continue;
+                }
+
+                let response = b"HTTP/1.1 200 OK\r\ncontent-length:2\r\n\r\nok";
+                if let Err(err) = socket.write_all(response).await {
+                    tracing::warn!(error = ?err, "failed to write upstream response in test");
+                }
+            }
+        });
+
+        let mut cfg = test_config(base_url);
+        cfg.upstream_header_timeout = Duration::from_millis(200);
+        let state = AppState::new(cfg);
+        {
+            let mut runtime = state.runtime.write().await;
+            runtime.candidates = vec![
+                RankedCandidate {
+                    name: "first-TEE".to_string(),
+                    chute_id: None,
+                    active_instance_count: 1,
+                    utilization_current: 0.1,
+                    rate_limit_ratio_5m: 0.0,
+                    score: 1.0,
+                },
+                RankedCandidate {
+                    name: "second-TEE".to_string(),
+                    chute_id: None,
+                    active_instance_count: 1,
+                    utilization_current: 0.1,
+                    rate_limit_ratio_5m: 0.0,
+                    score: 0.9,
+                },
+            ];
+            runtime.snapshot_at = Some(Instant::now());
+        }
+
+        let app = app(state);
+        let resp = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/chat/completions")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"model":"first-TEE,second-TEE"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get("x-chutes-autopilot-selected").unwrap(),
+            &axum::http::header::HeaderValue::from_static("second-TEE")
+        );
+
+        let body = resp.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, Bytes::from_static(b"ok"));
+
+        upstream_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn chat_completions_connect_error_reports_bad_gateway_after_exhaustion() {
+        // Reserve a local port and drop the listener so connects fail immediately.
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+
+        let mut cfg = test_config(format!("http://{addr}"));
+        cfg.upstream_connect_timeout = Duration::from_millis(50);
+        cfg.upstream_header_timeout = Duration::from_millis(200);
+        let state = AppState::new(cfg);
+        {
+            let mut runtime = state.runtime.write().await;
+            runtime.candidates = vec![
+                RankedCandidate {
+                    name: "first-TEE".to_string(),
+                    chute_id: None,
+                    active_instance_count: 1,
+                    utilization_current: 0.1,
+                    rate_limit_ratio_5m: 0.0,
+                    score: 1.0,
+                },
+                RankedCandidate {
+                    name: "second-TEE".to_string(),
+                    chute_id: None,
+                    active_instance_count: 1,
+                    utilization_current: 0.1,
+                    rate_limit_ratio_5m: 0.0,
+                    score: 0.9,
+                },
+            ];
+            runtime.snapshot_at = Some(Instant::now());
+        }
+
+        let app = app(state);
+        let resp = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/chat/completions")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"model":"first-TEE,second-TEE"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::BAD_GATEWAY);
+        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+        let parsed: OpenAiErrorResponse = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(parsed.error.code.as_deref(), Some("upstream_connect_error"));
+    }
+
+    #[tokio::test]
+    async fn chat_completions_model_list_does_not_retry_on_429() {
+        let attempts: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+        let upstream_attempts = attempts.clone();
+        let upstream = Router::new().route(
+            "/v1/chat/completions",
+            post(move |Json(v): Json<Value>| {
+                let upstream_attempts = upstream_attempts.clone();
+                async move {
+                    let model = v
+                        .get("model")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string();
+                    upstream_attempts.lock().unwrap().push(model.clone());
+
+                    if model == "first-TEE" {
+                        return (StatusCode::TOO_MANY_REQUESTS, "rate limited").into_response();
+                    }
+
+                    (StatusCode::OK, "ok").into_response()
+                }
+            }),
+        );
+        let (base_url, upstream_handle) = spawn_upstream(upstream).await;
+
+        let state = AppState::new(test_config(base_url));
+        let app = app(state);
+
+        let resp = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/chat/completions")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"model":"first-TEE,second-TEE"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(
+            resp.headers().get("x-chutes-autopilot-selected").unwrap(),
+            &axum::http::header::HeaderValue::from_static("first-TEE")
+        );
+
+        let got_attempts = attempts.lock().unwrap().clone();
+        assert_eq!(got_attempts, vec!["first-TEE".to_string()]);
+
+        let _ = resp.into_body().collect().await.unwrap();
+        upstream_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn chat_completions_model_list_failover_on_first_body_timeout() {
+        let attempts: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+        let upstream_attempts = attempts.clone();
+        let upstream = Router::new().route(
+            "/v1/chat/completions",
+            post(move |Json(v): Json<Value>| {
+                let upstream_attempts = upstream_attempts.clone();
+                async move {
+                    let model = v
+                        .get("model")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string();
+                    upstream_attempts.lock().unwrap().push(model.clone());
+
+                    if model == "stall-TEE" {
+                        let delayed = stream::once(async move {
+                            tokio::time::sleep(Duration::from_millis(200)).await;
+                            Ok::<Bytes, std::io::Error>(Bytes::from_static(b"late"))
+                        });
+                        let mut resp = Response::new(Body::from_stream(delayed));
+                        *resp.status_mut() = StatusCode::OK;
+                        return resp;
+                    }
+
+                    (StatusCode::OK, "ok").into_response()
+                }
+            }),
+        );
+        let (base_url, upstream_handle) = spawn_upstream(upstream).await;
+
+        let state = AppState::new(test_config(base_url));
+        let app = app(state);
+
+        let resp = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/chat/completions")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"model":"stall-TEE,second-TEE"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get("x-chutes-autopilot-selected").unwrap(),
+            &axum::http::header::HeaderValue::from_static("second-TEE")
+        );
+
+        let got_attempts = attempts.lock().unwrap().clone();
+        assert_eq!(
+            got_attempts,
+            vec!["stall-TEE".to_string(), "second-TEE".to_string()]
+        );
+
+        let body = resp.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, Bytes::from_static(b"ok"));
+
+        upstream_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn chat_completions_single_candidate_returns_504_on_first_body_timeout() {
+        let upstream = Router::new().route(
+            "/v1/chat/completions",
+            post(|Json(_): Json<Value>| async move {
+                let delayed = stream::once(async move {
+                    tokio::time::sleep(Duration::from_millis(200)).await;
+                    Ok::<Bytes, std::io::Error>(Bytes::from_static(b"late"))
+                });
+                let mut resp = Response::new(Body::from_stream(delayed));
+                *resp.status_mut() = StatusCode::OK;
+                resp
+            }),
+        );
+        let (base_url, upstream_handle) = spawn_upstream(upstream).await;
+
+        let mut cfg = test_config(base_url);
+        cfg.upstream_first_body_byte_timeout = Duration::from_millis(50);
+        let state = AppState::new(cfg);
+        let app = app(state);
+
+        let resp = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/chat/completions")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"model":"single-TEE"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::GATEWAY_TIMEOUT);
+        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+        let parsed: OpenAiErrorResponse = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(
+            parsed.error.code.as_deref(),
+            Some("upstream_first_body_byte_timeout")
+        );
+
+        upstream_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn chat_completions_model_list_does_not_retry_after_committing_body_bytes() {
+        let attempts: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+        let upstream_attempts = attempts.clone();
+        let upstream = Router::new().route(
+            "/v1/chat/completions",
+            post(move |Json(v): Json<Value>| {
+                let upstream_attempts = upstream_attempts.clone();
+                async move {
+                    let model = v
+                        .get("model")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string();
+                    upstream_attempts.lock().unwrap().push(model.clone());
+
+                    if model == "first-TEE" {
+                        let first = stream::once(async move {
+                            Ok::<Bytes, std::io::Error>(Bytes::from_static(b"first"))
+                        });
+                        let second = stream::once(async move {
+                            tokio::time::sleep(Duration::from_millis(10)).await;
+                            Err::<Bytes, std::io::Error>(std::io::Error::other("boom"))
+                        });
+                        let combined = first.chain(second);
+
+                        let mut resp = Response::new(Body::from_stream(combined));
+                        *resp.status_mut() = StatusCode::OK;
+                        return resp;
+                    }
+
+                    (StatusCode::OK, "ok").into_response()
+                }
+            }),
+        );
+        let (upstream_url, upstream_handle) = spawn_upstream(upstream).await;
+
+        let state = AppState::new(test_config(upstream_url));
+        let router = app(state);
+        let (autopilot_url, autopilot_handle) = spawn_upstream(router).await;
+
+        let resp = reqwest::Client::new()
+            .post(format!("{autopilot_url}/v1/chat/completions"))
+            .header("content-type", "application/json")
+            .body(r#"{"model":"first-TEE,second-TEE"}"#)
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers()
+                .get("x-chutes-autopilot-selected")
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            "first-TEE"
+        );
+
+        let mut body_stream = resp.bytes_stream();
+        let first = tokio::time::timeout(Duration::from_millis(100), body_stream.next())
+            .await
+            .expect("timed out waiting for first body chunk")
+            .expect("body ended early")
+            .expect("chunk error");
+        assert_eq!(first, Bytes::from_static(b"first"));
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let got_attempts = attempts.lock().unwrap().clone();
+        assert_eq!(got_attempts, vec!["first-TEE".to_string()]);
+
+        autopilot_handle.abort();
+        upstream_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn chat_completions_invalid_model_list_returns_400_openai_shape() {
+        let state = AppState::new(AppConfig::default());
+        let app = app(state);
+
+        let resp = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/chat/completions")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"model":","}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
 
-    if out.is_empty() {
-        return Err(ModelListError {
-            code: "invalid_model_list".to_string(),
-            message: "model list is empty".to_string(),
-        });
-    }
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
 
-    if out.len() > max_items {
-        return Err(ModelListError {
-            code: "invalid_model_list".to_string(),
-            message: format!("model list exceeds MAX_MODEL_LIST_ITEMS ({max_items})"),
-        });
+        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+        let parsed: OpenAiErrorResponse = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(parsed.error.error_type, "invalid_request_error");
+        assert_eq!(parsed.error.param.as_deref(), Some("model"));
+        assert_eq!(parsed.error.code.as_deref(), Some("invalid_model_list"));
     }
 
-    Ok(out)
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct OpenAiErrorResponse {
-    pub error: OpenAiError,
-}
+    #[tokio::test]
+    async fn chat_completions_allows_non_tee_items_in_model_list_when_allowlist_is_empty() {
+        let attempts: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+        let upstream_attempts = attempts.clone();
+        let upstream = Router::new().route(
+            "/v1/chat/completions",
+            post(move |Json(v): Json<Value>| {
+                let upstream_attempts = upstream_attempts.clone();
+                async move {
+                    let model = v
+                        .get("model")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string();
+                    upstream_attempts.lock().unwrap().push(model.clone());
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct OpenAiError {
-    pub message: String,
-    #[serde(rename = "type")]
-    pub error_type: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub param: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub code: Option<String>,
-}
+                    if model == "a-TEE" {
+                        return (StatusCode::SERVICE_UNAVAILABLE, "try later").into_response();
+                    }
 
-pub fn openai_error_response(
-    status: StatusCode,
-    error_type: &str,
-    message: &str,
-    param: Option<&str>,
-    code: Option<&str>,
-) -> Response {
-    let body = OpenAiErrorResponse {
-        error: OpenAiError {
-            message: message.to_string(),
-            error_type: error_type.to_string(),
-            param: param.map(ToString::to_string),
-            code: code.map(ToString::to_string),
-        },
-    };
-    (status, Json(body)).into_response()
-}
+                    (StatusCode::OK, "ok").into_response()
+                }
+            }),
+        );
+        let (base_url, upstream_handle) = spawn_upstream(upstream).await;
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        let state = AppState::new(test_config(base_url));
+        let app = app(state);
 
-    use axum::body::Body;
-    use axum::routing::post;
-    use axum::{Json, Router};
-    use http::Request;
-    use http_body_util::BodyExt;
-    use proptest::prelude::*;
-    use std::collections::HashSet;
-    use std::path::Path;
-    use std::sync::{Arc, Mutex};
-    use tokio::io::AsyncWriteExt;
-    use tokio::net::TcpListener;
-    use tower::ServiceExt;
+        let resp = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/chat/completions")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"model":"a-TEE,b"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
 
-    async fn spawn_upstream(app: Router) -> (String, tokio::task::JoinHandle<()>) {
-        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
-        let addr = listener.local_addr().unwrap();
-        let base_url = format!("http://{addr}");
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers()
+                .get("x-chutes-autopilot-selected")
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            "b"
+        );
 
-        let handle = tokio::spawn(async move {
-            axum::serve(listener, app.into_make_service())
-                .await
-                .unwrap();
-        });
+        let got_attempts = attempts.lock().unwrap().clone();
+        assert_eq!(got_attempts, vec!["a-TEE".to_string(), "b".to_string()]);
 
-        (base_url, handle)
+        upstream_handle.abort();
     }
 
-    fn test_config(backend_base_url: String) -> AppConfig {
-        AppConfig {
-            backend_base_url,
-            upstream_connect_timeout: Duration::from_millis(200),
-            upstream_header_timeout: Duration::from_millis(200),
-            upstream_first_body_byte_timeout: Duration::from_millis(50),
+    #[test]
+    fn derive_sticky_key_parses_xff_socketaddr_when_peer_is_trusted() {
+        let cfg = AppConfig {
+            trust_proxy_headers: true,
+            trusted_proxy_cidrs: vec!["203.0.113.0/24".parse::<IpNet>().unwrap()],
             ..Default::default()
-        }
-    }
+        };
 
-    #[test]
-    fn parse_model_list_basic() {
-        let got = parse_model_preference_list("a,b,c", 8).unwrap();
-        assert_eq!(got, vec!["a", "b", "c"]);
-    }
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-forwarded-for",
+            HeaderValue::from_static("198.51.100.7:1234, 203.0.113.10"),
+        );
+        let connect_info = Some(ConnectInfo(
+            "203.0.113.10:5678".parse::<SocketAddr>().unwrap(),
+        ));
 
-    #[test]
-    fn parse_model_list_trims_and_dedups_preserving_order() {
-        let got = parse_model_preference_list(" a , b , b ", 8).unwrap();
-        assert_eq!(got, vec!["a", "b"]);
+        let got = derive_sticky_key(&cfg, &headers, &connect_info).unwrap();
+        assert_eq!(got, "ip:198.51.100.7");
     }
 
-    #[test]
-    fn parse_model_list_discards_empty_items() {
-        let got = parse_model_preference_list("a,,b,", 8).unwrap();
-        assert_eq!(got, vec!["a", "b"]);
+    fn live_fixture(name: &str) -> Vec<u8> {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+        std::fs::read(root.join("tests/testdata/chutes_live").join(name)).unwrap()
     }
 
     #[test]
-    fn parse_model_list_empty_returns_400_code() {
-        let err = parse_model_preference_list(",", 8).unwrap_err();
-        assert_eq!(err.code, "invalid_model_list");
+    fn live_models_fixture_parses_allowlist() {
+        let bytes = live_fixture("models_2026-02-18.json");
+        let parsed: OpenAiModelListResponse = serde_json::from_slice(&bytes).unwrap();
+        assert!(parsed.data.len() > 50);
+        assert!(
+            parsed.data.iter().any(|m| m.id.contains("TEE")),
+            "fixture should include TEE-prefixed models"
+        );
     }
 
     #[test]
-    fn parse_model_list_enforces_max_items() {
-        let err = parse_model_preference_list("a,b,c", 2).unwrap_err();
-        assert_eq!(err.code, "invalid_model_list");
+    fn live_utilization_fixture_ranks_candidates_with_allowlist() {
+        let util_bytes = live_fixture("utilization_2026-02-18.json");
+        let records: Vec<UtilizationRecord> = serde_json::from_slice(&util_bytes).unwrap();
+        assert!(records.len() > 100);
+
+        let models_bytes = live_fixture("models_2026-02-18.json");
+        let allowlist: HashSet<String> =
+            serde_json::from_slice::<OpenAiModelListResponse>(&models_bytes)
+                .unwrap()
+                .data
+                .into_iter()
+                .map(|m| m.id)
+                .collect();
+
+        let ranked = rank_candidates(records, &allowlist);
+        assert!(!ranked.is_empty());
+        assert!(ranked.iter().all(|c| allowlist.contains(&c.name)));
+        assert!(ranked.iter().all(|c| c.active_instance_count > 0));
     }
 
-    proptest! {
-        #[test]
-        fn parse_model_list_trims_and_dedups_property(items in proptest::collection::vec("[A-Za-z0-9_./-]{1,12}", 1..8)) {
-            let raw = items
-                .iter()
-                .map(|s| format!(" {s} "))
-                .collect::<Vec<_>>()
-                .join(",");
+    #[tokio::test]
+    async fn chat_completions_does_not_failover_on_upstream_401() {
+        let attempts: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+        let upstream_attempts = attempts.clone();
+        let upstream_body = live_fixture("chat_completions_invalid_token_2026-02-18.json");
+        let upstream = Router::new().route(
+            "/v1/chat/completions",
+            post(move |Json(v): Json<Value>| {
+                let upstream_attempts = upstream_attempts.clone();
+                let body = upstream_body.clone();
+                async move {
+                    let model = v
+                        .get("model")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string();
+                    upstream_attempts.lock().unwrap().push(model);
+                    let mut resp = Response::new(Body::from(body));
+                    *resp.status_mut() = StatusCode::UNAUTHORIZED;
+                    resp
+                }
+            }),
+        );
+        let (base_url, upstream_handle) = spawn_upstream(upstream).await;
+
+        let state = AppState::new(test_config(base_url));
+        {
+            let mut runtime = state.runtime.write().await;
+            runtime.candidates = vec![
+                RankedCandidate {
+                    name: "primary-TEE".to_string(),
+                    chute_id: None,
+                    active_instance_count: 1,
+                    utilization_current: 0.1,
+                    rate_limit_ratio_5m: 0.0,
+                    score: 0.9,
+                },
+                RankedCandidate {
+                    name: "secondary-TEE".to_string(),
+                    chute_id: None,
+                    active_instance_count: 1,
+                    utilization_current: 0.2,
+                    rate_limit_ratio_5m: 0.0,
+                    score: 0.8,
+                },
+            ];
+            runtime.snapshot_at = Some(Instant::now());
+            runtime.models_allowlist =
+                HashSet::from(["primary-TEE".to_string(), "secondary-TEE".to_string()]);
+            runtime.models_allowlist_at = Some(Instant::now());
+        }
+
+        let app = app(state);
+        let resp = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/chat/completions")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"model":"primary-TEE,secondary-TEE"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            resp.headers().get("x-chutes-autopilot-selected").unwrap(),
+            &axum::http::header::HeaderValue::from_static("primary-TEE")
+        );
+        assert_eq!(attempts.lock().unwrap().len(), 1);
 
-            let mut seen = std::collections::HashSet::new();
-            let expected: Vec<String> = items
-                .iter()
-                .filter_map(|s| {
-                    if seen.insert(s.clone()) {
-                        Some(s.clone())
-                    } else {
-                        None
-                    }
-                })
-                .collect();
+        let body = resp.into_body().collect().await.unwrap().to_bytes();
+        let expected = live_fixture("chat_completions_invalid_token_2026-02-18.json");
+        assert_eq!(body.as_ref(), expected.as_slice());
 
-            let got = parse_model_preference_list(&raw, 8).unwrap();
-            prop_assert_eq!(got, expected);
-        }
+        upstream_handle.abort();
     }
 
-    proptest! {
-        #[test]
-        fn parse_model_list_errors_when_unique_items_exceed_max(items in proptest::collection::vec("[A-Za-z0-9_./-]{1,12}", 9..16)) {
-            let unique: std::collections::HashSet<String> = items.iter().cloned().collect();
-            prop_assume!(unique.len() > 8);
+    #[tokio::test]
+    async fn chat_completions_preserves_html_body_on_429() {
+        let attempts: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+        let upstream_attempts = attempts.clone();
+        let upstream_body = live_fixture("chat_completions_no_auth_429_2026-02-18.html");
+        let upstream = Router::new().route(
+            "/v1/chat/completions",
+            post(move |Json(v): Json<Value>| {
+                let upstream_attempts = upstream_attempts.clone();
+                let body = upstream_body.clone();
+                async move {
+                    let model = v
+                        .get("model")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string();
+                    upstream_attempts.lock().unwrap().push(model);
+                    let mut resp = Response::new(Body::from(body));
+                    *resp.status_mut() = StatusCode::TOO_MANY_REQUESTS;
+                    resp
+                }
+            }),
+        );
+        let (base_url, upstream_handle) = spawn_upstream(upstream).await;
 
-            let raw = items.join(",");
-            let result = parse_model_preference_list(&raw, 8);
-            prop_assert!(result.is_err());
+        let state = AppState::new(test_config(base_url));
+        {
+            let mut runtime = state.runtime.write().await;
+            runtime.candidates = vec![
+                RankedCandidate {
+                    name: "preferred-TEE".to_string(),
+                    chute_id: None,
+                    active_instance_count: 1,
+                    utilization_current: 0.1,
+                    rate_limit_ratio_5m: 0.0,
+                    score: 0.9,
+                },
+                RankedCandidate {
+                    name: "backup-TEE".to_string(),
+                    chute_id: None,
+                    active_instance_count: 1,
+                    utilization_current: 0.2,
+                    rate_limit_ratio_5m: 0.0,
+                    score: 0.8,
+                },
+            ];
+            runtime.snapshot_at = Some(Instant::now());
+            runtime.models_allowlist =
+                HashSet::from(["preferred-TEE".to_string(), "backup-TEE".to_string()]);
+            runtime.models_allowlist_at = Some(Instant::now());
         }
-    }
 
-    #[test]
-    fn derive_sticky_key_ignores_xff_by_default() {
-        let cfg = AppConfig::default();
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            "x-forwarded-for",
-            HeaderValue::from_static("198.51.100.7, 203.0.113.10"),
+        let app = app(state);
+        let resp = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/chat/completions")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"model":"preferred-TEE,backup-TEE"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(
+            resp.headers().get("x-chutes-autopilot-selected").unwrap(),
+            &axum::http::header::HeaderValue::from_static("preferred-TEE")
         );
-        let connect_info = Some(ConnectInfo(
-            "203.0.113.10:1234".parse::<SocketAddr>().unwrap(),
-        ));
+        assert_eq!(attempts.lock().unwrap().len(), 1);
 
-        let got = derive_sticky_key(&cfg, &headers, &connect_info).unwrap();
-        assert_eq!(got, "ip:203.0.113.10");
+        let body = resp.into_body().collect().await.unwrap().to_bytes();
+        let expected = live_fixture("chat_completions_no_auth_429_2026-02-18.html");
+        assert_eq!(body.as_ref(), expected.as_slice());
+
+        upstream_handle.abort();
     }
 
-    #[test]
-    fn derive_sticky_key_does_not_use_xff_when_peer_is_not_trusted() {
+    #[tokio::test]
+    async fn chat_completions_rejects_body_over_max_request_bytes_with_openai_shape() {
         let cfg = AppConfig {
-            trust_proxy_headers: true,
-            trusted_proxy_cidrs: vec!["192.0.2.0/24".parse::<IpNet>().unwrap()],
+            max_request_bytes: 32,
             ..Default::default()
         };
 
-        let mut headers = HeaderMap::new();
-        headers.insert("x-forwarded-for", HeaderValue::from_static("198.51.100.7"));
-        let connect_info = Some(ConnectInfo(
-            "203.0.113.10:1234".parse::<SocketAddr>().unwrap(),
-        ));
-
-        let got = derive_sticky_key(&cfg, &headers, &connect_info).unwrap();
-        assert_eq!(got, "ip:203.0.113.10");
-    }
+        let state = AppState::new(cfg);
+        let app = app(state);
 
-    #[test]
-    fn derive_sticky_key_uses_xff_when_peer_is_trusted() {
-        let cfg = AppConfig {
-            trust_proxy_headers: true,
-            trusted_proxy_cidrs: vec!["203.0.113.0/24".parse::<IpNet>().unwrap()],
-            ..Default::default()
-        };
+        let resp = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/chat/completions")
+                    .header("content-type", "application/json")
+                    .body(Body::from(vec![b'a'; 128]))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
 
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            "x-forwarded-for",
-            HeaderValue::from_static("198.51.100.7, 203.0.113.10"),
-        );
-        let connect_info = Some(ConnectInfo(
-            "203.0.113.10:1234".parse::<SocketAddr>().unwrap(),
-        ));
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
 
-        let got = derive_sticky_key(&cfg, &headers, &connect_info).unwrap();
-        assert_eq!(got, "ip:198.51.100.7");
+        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+        let parsed: OpenAiErrorResponse = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(parsed.error.error_type, "invalid_request_error");
+        assert_eq!(parsed.error.code.as_deref(), Some("request_too_large"));
+        assert_eq!(parsed.error.message, "request body too large");
     }
 
     proptest! {
         #[test]
-        fn derive_sticky_key_prefers_bearer_token_even_with_whitespace(token in "[A-Za-z0-9]{1,32}") {
-            let cfg = AppConfig::default();
-
-            let mut headers_spaced = HeaderMap::new();
-            headers_spaced.insert(
-                "authorization",
-                HeaderValue::from_str(&format!("  Bearer   {token}   ")).unwrap(),
-            );
-
-            let mut headers_clean = HeaderMap::new();
-            headers_clean.insert(
-                "authorization",
-                HeaderValue::from_str(&format!("Bearer {token}")).unwrap(),
-            );
+        fn chat_completions_rejects_any_body_exceeding_limit(extra in 1usize..64) {
+            let status = tokio::runtime::Runtime::new()
+                .unwrap()
+                .block_on(async move {
+                    let cfg = AppConfig {
+                        max_request_bytes: 32,
+                        ..Default::default()
+                    };
 
-            let connect_info = Some(ConnectInfo(
-                "203.0.113.10:1234".parse::<SocketAddr>().unwrap(),
-            ));
+                    let app = app(AppState::new(cfg));
+                    let resp = app
+                        .oneshot(
+                            Request::builder()
+                                .method("POST")
+                                .uri("/v1/chat/completions")
+                                .header("content-type", "application/json")
+                                .body(Body::from(vec![b'a'; 32 + extra]))
+                                .unwrap(),
+                        )
+                        .await
+                        .unwrap();
+                    resp.status()
+                });
 
-            let spaced = derive_sticky_key(&cfg, &headers_spaced, &connect_info).unwrap();
-            let clean = derive_sticky_key(&cfg, &headers_clean, &connect_info).unwrap();
+            prop_assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+        }
+    }
 
-            prop_assert!(spaced.starts_with("auth:"));
-            prop_assert_eq!(spaced, clean);
+    fn test_budget(max_tokens: Option<u64>, max_usd: Option<f64>) -> ClientBudget {
+        ClientBudget {
+            name: "team-a".to_string(),
+            key: "budget-key".to_string(),
+            window: BudgetWindow::Daily,
+            max_tokens,
+            max_usd,
         }
     }
 
-    #[test]
-    fn rank_candidates_uses_tee_suffix_without_allowlist() {
-        let ranked = rank_candidates(
-            vec![
-                UtilizationRecord {
-                    name: "model-A-TEE".to_string(),
-                    active_instance_count: 1,
-                    utilization_current: Some(0.1),
-                    utilization_5m: Some(0.1),
-                    utilization_15m: Some(0.1),
-                    utilization_1h: Some(0.1),
-                    rate_limit_ratio_5m: Some(0.0),
-                    rate_limit_ratio_15m: Some(0.0),
-                    rate_limit_ratio_1h: Some(0.0),
-                    scalable: false,
-                    scale_allowance: Some(0.0),
-                },
-                UtilizationRecord {
-                    name: "model-B".to_string(),
-                    active_instance_count: 10,
-                    utilization_current: Some(0.1),
-                    utilization_5m: Some(0.1),
-                    utilization_15m: Some(0.1),
-                    utilization_1h: Some(0.1),
-                    rate_limit_ratio_5m: Some(0.0),
-                    rate_limit_ratio_15m: Some(0.0),
-                    rate_limit_ratio_1h: Some(0.0),
-                    scalable: false,
-                    scale_allowance: Some(0.0),
-                },
-            ],
-            &HashSet::new(),
+    #[test]
+    fn find_usage_in_tail_reads_json_and_sse_bodies() {
+        let json_body = br#"{"id":"x","choices":[],"usage":{"prompt_tokens":3,"completion_tokens":4,"total_tokens":7}}"#;
+        assert_eq!(
+            find_usage_in_tail(json_body),
+            Some(TokenUsage {
+                prompt_tokens: 3,
+                completion_tokens: 4,
+                total_tokens: 7,
+            })
         );
 
-        assert_eq!(ranked.len(), 1);
-        assert_eq!(ranked[0].name, "model-A-TEE");
+        let sse_body = b"data: {\"choices\":[],\"usage\":null}\n\ndata: {\"choices\":[],\"usage\": {\"prompt_tokens\":10,\"completion_tokens\":2}}\n\ndata: [DONE]\n\n";
+        let usage = find_usage_in_tail(sse_body).unwrap();
+        assert_eq!(usage.tokens(), 12);
+
+        assert_eq!(find_usage_in_tail(b"data: {\"usage\":null}\n\n"), None);
     }
 
     #[test]
-    fn rank_candidates_filters_by_model_allowlist_when_present() {
-        let allowlist = HashSet::from(["allow/Model".to_string(), "keep/Model".to_string()]);
-        let ranked = rank_candidates(
-            vec![
-                UtilizationRecord {
-                    name: "allow/Model".to_string(),
-                    active_instance_count: 2,
-                    utilization_current: Some(0.2),
-                    utilization_5m: Some(0.2),
-                    utilization_15m: Some(0.2),
-                    utilization_1h: Some(0.2),
-                    rate_limit_ratio_5m: Some(0.0),
-                    rate_limit_ratio_15m: Some(0.0),
-                    rate_limit_ratio_1h: Some(0.0),
-                    scalable: false,
-                    scale_allowance: Some(0.0),
-                },
-                UtilizationRecord {
-                    name: "blocked/Model-TEE".to_string(),
-                    active_instance_count: 100,
-                    utilization_current: Some(0.0),
-                    utilization_5m: Some(0.0),
-                    utilization_15m: Some(0.0),
-                    utilization_1h: Some(0.0),
-                    rate_limit_ratio_5m: Some(0.0),
-                    rate_limit_ratio_15m: Some(0.0),
-                    rate_limit_ratio_1h: Some(0.0),
-                    scalable: false,
-                    scale_allowance: Some(0.0),
-                },
-            ],
-            &allowlist,
+    fn budget_window_monthly_period_rolls_over_on_calendar_month() {
+        let day = |d: u64| UNIX_EPOCH + Duration::from_secs(d * 86_400);
+        // 2024-02-29 and 2024-03-01.
+        let leap_day = day(19_782);
+        let march_first = day(19_783);
+
+        assert_eq!(civil_year_month(19_782), (2024, 2));
+        assert_eq!(civil_year_month(19_783), (2024, 3));
+        assert_ne!(
+            BudgetWindow::Monthly.period_at(leap_day),
+            BudgetWindow::Monthly.period_at(march_first)
+        );
+        assert_eq!(
+            BudgetWindow::Monthly.period_at(day(19_754)),
+            BudgetWindow::Monthly.period_at(leap_day)
         );
-
-        assert_eq!(ranked.len(), 1);
-        assert_eq!(ranked[0].name, "allow/Model");
     }
 
     #[test]
-    fn rank_candidates_fixture_filters_and_orders_candidates() {
-        let records: Vec<UtilizationRecord> =
-            serde_json::from_str(include_str!("../tests/testdata/utilization_fixture.json"))
-                .unwrap();
+    fn budget_ledger_exhausts_and_resets_per_window() {
+        let ledger = BudgetLedger::new(&[test_budget(Some(10), None)], None);
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "authorization",
+            HeaderValue::from_static("Bearer budget-key"),
+        );
+        let idx = ledger.budget_for(&headers).unwrap();
 
-        let ranked = rank_candidates(records, &HashSet::new());
-        let names: Vec<String> = ranked.into_iter().map(|c| c.name).collect();
-        assert_eq!(names, vec!["alpha-TEE", "beta-TEE", "gamma-TEE"]);
+        let today = UNIX_EPOCH + Duration::from_secs(20_000 * 86_400);
+        let tomorrow = today + Duration::from_secs(86_400);
+        assert!(!ledger.is_exhausted(idx, today));
+
+        let usage = TokenUsage {
+            prompt_tokens: 6,
+            completion_tokens: 4,
+            total_tokens: 10,
+        };
+        ledger.charge(idx, usage, None, today);
+        assert!(ledger.is_exhausted(idx, today));
+        assert!(!ledger.is_exhausted(idx, tomorrow));
     }
 
     #[test]
-    fn ranked_candidate_score_matches_spec_formula() {
-        let record = UtilizationRecord {
-            name: "score-TEE".to_string(),
-            active_instance_count: 4,
-            utilization_current: Some(0.5),
-            utilization_5m: Some(0.5),
-            utilization_15m: Some(0.25),
-            utilization_1h: None,
-            rate_limit_ratio_5m: Some(0.25),
-            rate_limit_ratio_15m: Some(0.5),
-            rate_limit_ratio_1h: Some(1.0),
-            scalable: true,
-            scale_allowance: Some(8.0),
+    fn budget_ledger_prices_usage_from_catalog() {
+        let ledger = BudgetLedger::new(&[test_budget(None, Some(1.0))], None);
+        let now = SystemTime::now();
+        let pricing = ModelPricing {
+            prompt: 100_000.0,
+            completion: 400_000.0,
         };
 
-        let ranked = RankedCandidate::from(record);
-        let expected = 0.8;
-        assert!(
-            (ranked.score - expected).abs() < 1e-6,
-            "got {}, expected {}",
-            ranked.score,
-            expected
+        ledger.charge(
+            0,
+            TokenUsage {
+                prompt_tokens: 2,
+                completion_tokens: 1,
+                total_tokens: 3,
+            },
+            Some(pricing),
+            now,
         );
+        assert!(!ledger.is_exhausted(0, now));
+
+        ledger.charge(
+            0,
+            TokenUsage {
+                prompt_tokens: 2,
+                completion_tokens: 1,
+                total_tokens: 3,
+            },
+            Some(pricing),
+            now,
+        );
+        assert!(ledger.is_exhausted(0, now));
     }
 
-    #[test]
-    fn sort_ranked_candidates_tiebreaks_by_active_instance_count_desc() {
-        let mut ranked = vec![
-            RankedCandidate {
-                name: "low-active".to_string(),
-                active_instance_count: 1,
-                utilization_current: 0.0,
-                rate_limit_ratio_5m: 0.0,
-                score: 1.0,
-            },
-            RankedCandidate {
-                name: "high-active".to_string(),
-                active_instance_count: 2,
-                utilization_current: 0.0,
-                rate_limit_ratio_5m: 0.0,
-                score: 1.0,
-            },
-        ];
+    #[tokio::test]
+    async fn chat_completions_charges_usage_and_rejects_exhausted_budget() {
+        let attempts: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+        let upstream_attempts = attempts.clone();
+        let upstream = Router::new().route(
+            "/v1/chat/completions",
+            post(move |Json(v): Json<Value>| {
+                let upstream_attempts = upstream_attempts.clone();
+                async move {
+                    let model = v
+                        .get("model")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string();
+                    upstream_attempts.lock().unwrap().push(model);
+                    Json(json!({
+                        "choices": [],
+                        "usage": {"prompt_tokens": 8, "completion_tokens": 4, "total_tokens": 12}
+                    }))
+                }
+            }),
+        );
+        let (base_url, upstream_handle) = spawn_upstream(upstream).await;
 
-        sort_ranked_candidates(&mut ranked);
-        assert_eq!(ranked[0].name, "high-active");
+        let cfg = AppConfig {
+            client_budgets: vec![test_budget(Some(10), None)],
+            ..test_config(base_url)
+        };
+        let app = app(AppState::new(cfg));
+        let request = || {
+            Request::builder()
+                .method("POST")
+                .uri("/v1/chat/completions")
+                .header("authorization", "Bearer budget-key")
+                .header("content-type", "application/json")
+                .body(Body::from(r#"{"model":"direct-model"}"#))
+                .unwrap()
+        };
+
+        let resp = app.clone().oneshot(request()).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let _ = resp.into_body().collect().await.unwrap();
+
+        let resp = app.oneshot(request()).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+        let parsed: OpenAiErrorResponse = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(parsed.error.error_type, "insufficient_quota");
+        assert_eq!(parsed.error.code.as_deref(), Some("budget_exceeded"));
+
+        assert_eq!(attempts.lock().unwrap().len(), 1);
+        upstream_handle.abort();
     }
 
-    #[test]
-    fn sort_ranked_candidates_tiebreaks_by_utilization_current_asc() {
-        let mut ranked = vec![
-            RankedCandidate {
-                name: "higher-util".to_string(),
-                active_instance_count: 1,
-                utilization_current: 0.5,
-                rate_limit_ratio_5m: 0.0,
-                score: 1.0,
-            },
-            RankedCandidate {
-                name: "lower-util".to_string(),
-                active_instance_count: 1,
-                utilization_current: 0.25,
-                rate_limit_ratio_5m: 0.0,
-                score: 1.0,
-            },
-        ];
+    fn hedge_config(base_url: String) -> AppConfig {
+        AppConfig {
+            hedge_enabled: true,
+            hedge_delay: Duration::from_millis(50),
+            upstream_header_timeout: Duration::from_secs(2),
+            upstream_first_body_byte_timeout: Duration::from_secs(2),
+            ..test_config(base_url)
+        }
+    }
 
-        sort_ranked_candidates(&mut ranked);
-        assert_eq!(ranked[0].name, "lower-util");
+    async fn post_chat(app: Router, body: &'static str) -> Response {
+        app.oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/chat/completions")
+                .header("content-type", "application/json")
+                .body(Body::from(body))
+                .unwrap(),
+        )
+        .await
+        .unwrap()
     }
 
     #[test]
-    fn sort_ranked_candidates_tiebreaks_by_rate_limit_ratio_5m_asc() {
-        let mut ranked = vec![
-            RankedCandidate {
-                name: "higher-rl".to_string(),
-                active_instance_count: 1,
-                utilization_current: 0.0,
-                rate_limit_ratio_5m: 0.5,
-                score: 1.0,
-            },
-            RankedCandidate {
-                name: "lower-rl".to_string(),
-                active_instance_count: 1,
-                utilization_current: 0.0,
-                rate_limit_ratio_5m: 0.25,
-                score: 1.0,
-            },
-        ];
+    fn latency_window_reports_p90_only_with_enough_samples() {
+        let mut window = LatencyWindow::default();
+        for ms in 1..=10 {
+            window.record(Duration::from_millis(ms));
+        }
+        assert_eq!(window.quantile(0.9), None);
 
-        sort_ranked_candidates(&mut ranked);
-        assert_eq!(ranked[0].name, "lower-rl");
+        for ms in 11..=100 {
+            window.record(Duration::from_millis(ms));
+        }
+        assert_eq!(window.quantile(0.9), Some(Duration::from_millis(90)));
     }
 
-    #[test]
-    fn sort_ranked_candidates_tiebreaks_by_name_asc() {
-        let mut ranked = vec![
-            RankedCandidate {
-                name: "b".to_string(),
-                active_instance_count: 1,
-                utilization_current: 0.0,
-                rate_limit_ratio_5m: 0.0,
-                score: 1.0,
-            },
-            RankedCandidate {
-                name: "a".to_string(),
-                active_instance_count: 1,
-                utilization_current: 0.0,
-                rate_limit_ratio_5m: 0.0,
-                score: 1.0,
-            },
-        ];
+    #[tokio::test]
+    async fn chat_completions_hedge_wins_when_primary_is_slow() {
+        let (upstream, attempts) = test_upstream(StatusCode::OK);
+        let (base_url, upstream_handle) = spawn_upstream(upstream).await;
+
+        let state = AppState::new(hedge_config(base_url));
+        let metrics = state.metrics.clone();
+        let started = Instant::now();
+        let resp = post_chat(app(state), r#"{"model":"slow-TEE,fast-TEE"}"#).await;
+
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get("x-chutes-autopilot-selected").unwrap(),
+            &HeaderValue::from_static("fast-TEE")
+        );
+        let body = resp.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["choices"][0]["message"]["content"], "from fast-TEE");
+        assert!(started.elapsed() < Duration::from_millis(400));
+
+        assert_eq!(
+            take_models(&attempts),
+            vec!["slow-TEE".to_string(), "fast-TEE".to_string()]
+        );
+        assert_eq!(metrics.hedge_fired_total.get(), 1);
+        assert_eq!(metrics.hedge_wins_total.get(), 1);
+
+        upstream_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn chat_completions_hedge_503_does_not_cancel_a_healthy_primary() {
+        let (upstream, attempts) = test_upstream(StatusCode::OK);
+        let (base_url, upstream_handle) = spawn_upstream(upstream).await;
+
+        let state = AppState::new(hedge_config(base_url));
+        let resp = post_chat(app(state.clone()), r#"{"model":"slow-TEE,down-TEE"}"#).await;
+
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get("x-chutes-autopilot-selected").unwrap(),
+            &HeaderValue::from_static("slow-TEE")
+        );
+        let body = resp.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["choices"][0]["message"]["content"], "from slow-TEE");
+        assert_eq!(take_models(&attempts), vec!["slow-TEE", "down-TEE"]);
+        assert_eq!(state.metrics.hedge_fired_total.get(), 1);
+        assert_eq!(state.metrics.hedge_wins_total.get(), 0);
+        upstream_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn chat_completions_never_resends_to_a_fired_hedge() {
+        let (upstream, attempts) = test_upstream(StatusCode::OK);
+        let (base_url, upstream_handle) = spawn_upstream(upstream).await;
+
+        let state = AppState::new(hedge_config(base_url));
+        let resp = post_chat(
+            app(state),
+            r#"{"model":"slow-down-a-TEE,slow-down-b-TEE,fast-TEE"}"#,
+        )
+        .await;
+
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get("x-chutes-autopilot-selected").unwrap(),
+            &HeaderValue::from_static("fast-TEE")
+        );
+        let _ = resp.into_body().collect().await.unwrap();
+        let mut hits: HashMap<String, usize> = HashMap::new();
+        for model in take_models(&attempts) {
+            *hits.entry(model).or_default() += 1;
+        }
+        assert_eq!(
+            hits,
+            HashMap::from([
+                ("slow-down-a-TEE".to_string(), 1),
+                ("slow-down-b-TEE".to_string(), 1),
+                ("fast-TEE".to_string(), 1),
+            ])
+        );
+        upstream_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn chat_completions_does_not_hedge_streaming_or_when_disabled() {
+        let (upstream, attempts) = test_upstream(StatusCode::OK);
+        let (base_url, upstream_handle) = spawn_upstream(upstream).await;
+
+        let streaming = AppState::new(hedge_config(base_url.clone()));
+        let resp = post_chat(
+            app(streaming.clone()),
+            r#"{"model":"slow-TEE,fast-TEE","stream":true}"#,
+        )
+        .await;
+        assert_eq!(
+            resp.headers().get("x-chutes-autopilot-selected").unwrap(),
+            &HeaderValue::from_static("slow-TEE")
+        );
+        let _ = resp.into_body().collect().await.unwrap();
+        assert_eq!(streaming.metrics.hedge_fired_total.get(), 0);
+
+        let disabled = AppState::new(AppConfig {
+            hedge_enabled: false,
+            ..hedge_config(base_url)
+        });
+        let resp = post_chat(app(disabled.clone()), r#"{"model":"slow-TEE,fast-TEE"}"#).await;
+        assert_eq!(
+            resp.headers().get("x-chutes-autopilot-selected").unwrap(),
+            &HeaderValue::from_static("slow-TEE")
+        );
+        let _ = resp.into_body().collect().await.unwrap();
+        assert_eq!(disabled.metrics.hedge_fired_total.get(), 0);
 
-        sort_ranked_candidates(&mut ranked);
-        assert_eq!(ranked[0].name, "a");
+        assert_eq!(
+            take_models(&attempts),
+            vec!["slow-TEE".to_string(), "slow-TEE".to_string()]
+        );
+        upstream_handle.abort();
     }
 
     #[tokio::test]
-    async fn candidate_snapshot_keeps_last_known_good_on_error() {
-        let state = AppState::new(AppConfig::default());
-        let snapshot_at = Instant::now() - Duration::from_secs(60);
-        {
-            let mut runtime = state.runtime.write().await;
-            runtime.candidates = vec![RankedCandidate {
-                name: "keep".to_string(),
-                active_instance_count: 1,
-                utilization_current: 0.0,
-                rate_limit_ratio_5m: 0.0,
-                score: 1.0,
-            }];
-            runtime.snapshot_at = Some(snapshot_at);
-        }
+    async fn chat_completions_never_hedges_after_429() {
+        let (upstream, attempts) = test_upstream(StatusCode::OK);
+        let (base_url, upstream_handle) = spawn_upstream(upstream).await;
 
-        state
-            .update_candidate_snapshot(Err(anyhow::anyhow!("boom")))
-            .await;
+        let state = AppState::new(hedge_config(base_url));
+        let resp = post_chat(app(state.clone()), r#"{"model":"limited-TEE,fast-TEE"}"#).await;
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        let _ = resp.into_body().collect().await.unwrap();
 
-        let runtime = state.runtime.read().await;
-        assert_eq!(runtime.candidates.len(), 1);
-        assert_eq!(runtime.candidates[0].name, "keep");
-        assert_eq!(runtime.snapshot_at, Some(snapshot_at));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(take_models(&attempts), vec!["limited-TEE".to_string()]);
+        assert_eq!(state.metrics.hedge_fired_total.get(), 0);
+        upstream_handle.abort();
+    }
+
+    async fn assert_retry_budget_exhausted(resp: Response) {
+        assert_eq!(resp.status(), StatusCode::GATEWAY_TIMEOUT);
+        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+        let parsed: OpenAiErrorResponse = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(parsed.error.code.as_deref(), Some("retry_budget_exhausted"));
+    }
+
+    #[test]
+    fn retry_limits_headers_can_only_lower_configured_limits() {
+        let cfg = AppConfig {
+            max_upstream_attempts: 3,
+            upstream_retry_budget: Duration::from_secs(5),
+            ..Default::default()
+        };
+
+        let mut headers = HeaderMap::new();
+        headers.insert(MAX_ATTEMPTS_HEADER, HeaderValue::from_static("10"));
+        headers.insert(RETRY_BUDGET_HEADER, HeaderValue::from_static("1000"));
+        let limits = RetryLimits::for_request(&cfg, &headers).unwrap();
+        assert_eq!(limits.max_attempts, 3);
+        assert_eq!(limits.budget, Some(Duration::from_secs(1)));
+
+        headers.insert(MAX_ATTEMPTS_HEADER, HeaderValue::from_static("2"));
+        headers.insert(RETRY_BUDGET_HEADER, HeaderValue::from_static("60000"));
+        let limits = RetryLimits::for_request(&cfg, &headers).unwrap();
+        assert_eq!(limits.max_attempts, 2);
+        assert_eq!(limits.budget, Some(Duration::from_secs(5)));
+
+        headers.insert(MAX_ATTEMPTS_HEADER, HeaderValue::from_static("0"));
+        assert!(RetryLimits::for_request(&cfg, &headers).is_err());
+        headers.insert(MAX_ATTEMPTS_HEADER, HeaderValue::from_static("2"));
+        headers.insert(RETRY_BUDGET_HEADER, HeaderValue::from_static("0"));
+        let err = RetryLimits::for_request(&cfg, &headers).unwrap_err();
+        assert_eq!(err.name, RETRY_BUDGET_HEADER);
+        headers.remove(RETRY_BUDGET_HEADER);
+        headers.insert(MAX_ATTEMPTS_HEADER, HeaderValue::from_static("many"));
+        let err = RetryLimits::for_request(&cfg, &headers).unwrap_err();
+        assert_eq!(err.name, MAX_ATTEMPTS_HEADER);
     }
 
     #[tokio::test]
-    async fn chat_completions_prefers_sticky_model_for_autopilot() {
-        let attempts: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
-        let upstream_attempts = attempts.clone();
-        let upstream = Router::new().route(
-            "/v1/chat/completions",
-            post(move |Json(v): Json<Value>| {
-                let upstream_attempts = upstream_attempts.clone();
-                async move {
-                    let model = v
-                        .get("model")
-                        .and_then(Value::as_str)
-                        .unwrap_or_default()
-                        .to_string();
-                    upstream_attempts.lock().unwrap().push(model);
-                    (StatusCode::OK, "ok")
-                }
-            }),
-        );
+    async fn chat_completions_stops_at_max_upstream_attempts() {
+        let (upstream, attempts) = test_upstream(StatusCode::SERVICE_UNAVAILABLE);
         let (base_url, upstream_handle) = spawn_upstream(upstream).await;
 
-        let state = AppState::new(test_config(base_url));
-        {
-            let mut runtime = state.runtime.write().await;
-            runtime.candidates = vec![
-                RankedCandidate {
-                    name: "first/TEE-Model".to_string(),
-                    active_instance_count: 10,
-                    utilization_current: 0.2,
-                    rate_limit_ratio_5m: 0.0,
-                    score: 8.0,
-                },
-                RankedCandidate {
-                    name: "second/TEE-Model".to_string(),
-                    active_instance_count: 1,
-                    utilization_current: 0.1,
-                    rate_limit_ratio_5m: 0.0,
-                    score: 1.0,
-                },
-            ];
-        }
-        let mut auth_headers = HeaderMap::new();
-        auth_headers.insert(
-            "authorization",
-            HeaderValue::from_static("Bearer sticky-api-token"),
+        let cfg = AppConfig {
+            max_upstream_attempts: 2,
+            ..test_config(base_url)
+        };
+        let resp = post_chat(app(AppState::new(cfg)), r#"{"model":"a-TEE,b-TEE,c-TEE"}"#).await;
+        assert_retry_budget_exhausted(resp).await;
+        assert_eq!(
+            take_models(&attempts),
+            vec!["a-TEE".to_string(), "b-TEE".to_string()]
         );
-        let no_connect_info: Option<ConnectInfo<SocketAddr>> = None;
 
-        state
-            .set_sticky_model(
-                derive_sticky_key(&state.config, &auth_headers, &no_connect_info).unwrap(),
-                "second/TEE-Model".to_string(),
-            )
-            .await;
+        upstream_handle.abort();
+    }
 
-        let app = app(state);
-        let resp = app
+    #[tokio::test]
+    async fn chat_completions_max_attempts_header_lowers_limit() {
+        let (upstream, attempts) = test_upstream(StatusCode::SERVICE_UNAVAILABLE);
+        let (base_url, upstream_handle) = spawn_upstream(upstream).await;
+
+        let resp = app(AppState::new(test_config(base_url)))
             .oneshot(
                 Request::builder()
                     .method("POST")
                     .uri("/v1/chat/completions")
-                    .header("authorization", "Bearer sticky-api-token")
                     .header("content-type", "application/json")
-                    .body(Body::from(r#"{"model":"chutesai/AutoPilot"}"#))
+                    .header(MAX_ATTEMPTS_HEADER, "1")
+                    .body(Body::from(r#"{"model":"a-TEE,b-TEE"}"#))
                     .unwrap(),
             )
             .await
             .unwrap();
+        assert_retry_budget_exhausted(resp).await;
+        assert_eq!(take_models(&attempts), vec!["a-TEE".to_string()]);
 
-        assert_eq!(resp.status(), StatusCode::OK);
-        assert_eq!(
-            resp.headers().get("x-chutes-autopilot-selected").unwrap(),
-            &axum::http::header::HeaderValue::from_static("second/TEE-Model")
-        );
-
-        let got_attempts = attempts.lock().unwrap().clone();
-        assert_eq!(got_attempts, vec!["second/TEE-Model".to_string()]);
-
-        let _ = resp.into_body().collect().await.unwrap();
         upstream_handle.abort();
     }
 
     #[tokio::test]
-    async fn chat_completions_prefers_fresh_candidate_when_sticky_is_not_valid() {
-        let attempts: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
-        let upstream_attempts = attempts.clone();
-        let upstream = Router::new().route(
-            "/v1/chat/completions",
-            post(move |Json(v): Json<Value>| {
-                let upstream_attempts = upstream_attempts.clone();
-                async move {
-                    let model = v
-                        .get("model")
-                        .and_then(Value::as_str)
-                        .unwrap_or_default()
-                        .to_string();
-                    upstream_attempts.lock().unwrap().push(model);
-                    (StatusCode::OK, "ok")
-                }
-            }),
-        );
+    async fn chat_completions_stops_when_retry_deadline_passes() {
+        let (upstream, attempts) = test_upstream(StatusCode::SERVICE_UNAVAILABLE);
         let (base_url, upstream_handle) = spawn_upstream(upstream).await;
 
-        let state = AppState::new(test_config(base_url));
-        {
-            let mut runtime = state.runtime.write().await;
-            runtime.candidates = vec![RankedCandidate {
-                name: "only/TEE-Model".to_string(),
-                active_instance_count: 2,
-                utilization_current: 0.1,
-                rate_limit_ratio_5m: 0.0,
-                score: 4.0,
-            }];
-        }
+        let cfg = AppConfig {
+            upstream_retry_budget: Duration::from_millis(100),
+            ..test_config(base_url)
+        };
+        let started = Instant::now();
+        let resp = post_chat(app(AppState::new(cfg)), r#"{"model":"stall-TEE,b-TEE"}"#).await;
+        assert_retry_budget_exhausted(resp).await;
+        assert!(started.elapsed() < Duration::from_millis(200));
+        assert_eq!(take_models(&attempts), vec!["stall-TEE".to_string()]);
 
-        let mut auth_headers = HeaderMap::new();
-        auth_headers.insert(
-            "authorization",
-            HeaderValue::from_static("Bearer stale-sticky-key"),
-        );
-        let no_connect_info: Option<ConnectInfo<SocketAddr>> = None;
-        state
-            .set_sticky_model(
-                derive_sticky_key(&state.config, &auth_headers, &no_connect_info).unwrap(),
-                "missing/TEE-Model".to_string(),
-            )
-            .await;
+        upstream_handle.abort();
+    }
 
-        let app = app(state);
-        let resp = app
+    #[tokio::test]
+    async fn chat_completions_rejects_invalid_retry_budget_header() {
+        let resp = app(AppState::new(AppConfig::default()))
             .oneshot(
                 Request::builder()
                     .method("POST")
                     .uri("/v1/chat/completions")
-                    .header("authorization", "Bearer stale-sticky-key")
                     .header("content-type", "application/json")
-                    .body(Body::from(r#"{"model":"chutesai/AutoPilot"}"#))
+                    .header(RETRY_BUDGET_HEADER, "soon")
+                    .body(Body::from(r#"{"model":"a-TEE,b-TEE"}"#))
                     .unwrap(),
             )
             .await
             .unwrap();
 
-        assert_eq!(resp.status(), StatusCode::OK);
-        assert_eq!(
-            resp.headers().get("x-chutes-autopilot-selected").unwrap(),
-            &axum::http::header::HeaderValue::from_static("only/TEE-Model")
-        );
-
-        let got_attempts = attempts.lock().unwrap().clone();
-        assert_eq!(got_attempts, vec!["only/TEE-Model".to_string()]);
-
-        let _ = resp.into_body().collect().await.unwrap();
-        upstream_handle.abort();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+        let parsed: OpenAiErrorResponse = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(parsed.error.code.as_deref(), Some("invalid_header"));
+        assert_eq!(parsed.error.param.as_deref(), Some(RETRY_BUDGET_HEADER));
     }
 
-    #[tokio::test]
-    async fn rotate_sticky_model_moves_to_next_candidate() {
-        let state = AppState::new(AppConfig::default());
-        state
-            .set_sticky_model("client-key".to_string(), "first".to_string())
-            .await;
-
-        let candidates = vec![
-            "first".to_string(),
-            "second".to_string(),
-            "third".to_string(),
-        ];
+    #[test]
+    fn retry_after_cooldown_parses_delta_seconds_and_caps() {
+        let max = Duration::from_secs(30);
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after_cooldown(&headers, max), None);
 
-        state
-            .rotate_sticky_model("client-key", &candidates, "first")
-            .await;
+        headers.insert("retry-after", HeaderValue::from_static("5"));
         assert_eq!(
-            state.sticky_model("client-key").await,
-            Some("second".to_string())
+            retry_after_cooldown(&headers, max),
+            Some(Duration::from_secs(5))
         );
 
-        state
-            .rotate_sticky_model("client-key", &candidates, "second")
-            .await;
-        assert_eq!(
-            state.sticky_model("client-key").await,
-            Some("third".to_string())
+        headers.insert("retry-after", HeaderValue::from_static("3600"));
+        assert_eq!(retry_after_cooldown(&headers, max), Some(max));
+
+        headers.insert(
+            "retry-after",
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
         );
+        assert_eq!(retry_after_cooldown(&headers, max), None);
     }
 
     #[tokio::test]
-    async fn sticky_map_evicts_oldest_entries_when_over_cap() {
+    async fn chat_completions_retryable_statuses_are_configured_per_mode() {
+        let (upstream, attempts) = test_upstream(StatusCode::OK);
+        let (base_url, upstream_handle) = spawn_upstream(upstream).await;
+
+        // Default policy: 502 is proxied back as-is.
+        let resp = post_chat(
+            app(AppState::new(test_config(base_url.clone()))),
+            r#"{"model":"bad-gateway-TEE,ok-TEE"}"#,
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::BAD_GATEWAY);
+        let _ = resp.into_body().collect().await.unwrap();
+        assert_eq!(take_models(&attempts), vec!["bad-gateway-TEE".to_string()]);
+
         let cfg = AppConfig {
-            sticky_max_entries: 2,
-            ..Default::default()
+            retryable_statuses: RetryableStatuses {
+                list: vec![StatusCode::BAD_GATEWAY, StatusCode::SERVICE_UNAVAILABLE],
+                ..Default::default()
+            },
+            ..test_config(base_url.clone())
         };
-
         let state = AppState::new(cfg);
-        let now = Instant::now();
+        let resp = post_chat(app(state.clone()), r#"{"model":"bad-gateway-TEE,ok-TEE"}"#).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let _ = resp.into_body().collect().await.unwrap();
+        assert_eq!(
+            take_models(&attempts),
+            vec!["bad-gateway-TEE".to_string(), "ok-TEE".to_string()]
+        );
 
+        // The alias policy is independent of the list policy.
         {
             let mut runtime = state.runtime.write().await;
-            runtime.sticky_models.insert(
-                "old".to_string(),
-                StickyModelSelection {
-                    model: "old-model".to_string(),
-                    last_used_at: now - Duration::from_secs(10),
-                },
-            );
-            runtime.sticky_models.insert(
-                "mid".to_string(),
-                StickyModelSelection {
-                    model: "mid-model".to_string(),
-                    last_used_at: now - Duration::from_secs(5),
-                },
-            );
-            runtime.sticky_models.insert(
-                "new".to_string(),
-                StickyModelSelection {
-                    model: "new-model".to_string(),
-                    last_used_at: now,
+            runtime.candidates = vec![
+                RankedCandidate {
+                    name: "bad-gateway-TEE".to_string(),
+                    active_instance_count: 2,
+                    utilization_current: 0.1,
+                    score: 2.0,
+                    ..Default::default()
                 },
-            );
-
-            AppState::enforce_sticky_cap(&mut runtime, state.config.sticky_max_entries);
-            assert_eq!(runtime.sticky_models.len(), 2);
-            assert!(!runtime.sticky_models.contains_key("old"));
-            assert!(runtime.sticky_models.contains_key("mid"));
-            assert!(runtime.sticky_models.contains_key("new"));
+                RankedCandidate {
+                    name: "ok-TEE".to_string(),
+                    active_instance_count: 1,
+                    utilization_current: 0.1,
+                    score: 1.0,
+                    ..Default::default()
+                },
+            ];
         }
+        let resp = post_chat(app(state), r#"{"model":"chutesai/AutoPilot"}"#).await;
+        assert_eq!(resp.status(), StatusCode::BAD_GATEWAY);
+        let _ = resp.into_body().collect().await.unwrap();
+
+        // 4xx entries are ignored even when configured.
+        take_models(&attempts);
+        let state = AppState::new(AppConfig {
+            retryable_statuses: RetryableStatuses {
+                list: vec![StatusCode::TOO_MANY_REQUESTS],
+                ..Default::default()
+            },
+            ..test_config(base_url)
+        });
+        let resp = post_chat(app(state), r#"{"model":"limited-TEE,ok-TEE"}"#).await;
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        let _ = resp.into_body().collect().await.unwrap();
+        assert_eq!(take_models(&attempts), vec!["limited-TEE".to_string()]);
+
+        upstream_handle.abort();
     }
 
     #[tokio::test]
-    async fn chat_completions_allows_autopilot_alias_without_tee_suffix_when_ranked_model_exists() {
-        let attempts: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
-        let upstream_attempts = attempts.clone();
-        let upstream = Router::new().route(
-            "/v1/chat/completions",
-            post(move |Json(v): Json<Value>| {
-                let upstream_attempts = upstream_attempts.clone();
-                async move {
-                    let model = v
-                        .get("model")
-                        .and_then(Value::as_str)
-                        .unwrap_or_default()
-                        .to_string();
-                    upstream_attempts.lock().unwrap().push(model);
-                    (StatusCode::OK, "ok")
-                }
-            }),
-        );
+    async fn chat_completions_503_retry_after_cools_model_down_for_all_clients() {
+        let (upstream, attempts) = test_upstream(StatusCode::OK);
         let (base_url, upstream_handle) = spawn_upstream(upstream).await;
 
         let state = AppState::new(test_config(base_url));
@@ -2126,624 +8629,1307 @@ mod tests {
             let mut runtime = state.runtime.write().await;
             runtime.candidates = vec![
                 RankedCandidate {
-                    name: "chosen/TEE-Model".to_string(),
-                    active_instance_count: 10,
-                    utilization_current: 0.2,
-                    rate_limit_ratio_5m: 0.0,
-                    score: 8.0,
+                    name: "backoff-TEE".to_string(),
+                    active_instance_count: 2,
+                    utilization_current: 0.1,
+                    score: 2.0,
+                    ..Default::default()
                 },
                 RankedCandidate {
-                    name: "fallback/TEE-Model".to_string(),
+                    name: "ok-TEE".to_string(),
                     active_instance_count: 1,
                     utilization_current: 0.1,
-                    rate_limit_ratio_5m: 0.0,
                     score: 1.0,
+                    ..Default::default()
                 },
             ];
         }
 
-        let app = app(state);
+        let resp = post_chat(app(state.clone()), r#"{"model":"chutesai/AutoPilot"}"#).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let _ = resp.into_body().collect().await.unwrap();
+        assert_eq!(
+            take_models(&attempts),
+            vec!["backoff-TEE".to_string(), "ok-TEE".to_string()]
+        );
 
-        let resp = app
+        let resp = app(state)
             .oneshot(
                 Request::builder()
                     .method("POST")
                     .uri("/v1/chat/completions")
+                    .header("authorization", "Bearer another-client")
                     .header("content-type", "application/json")
                     .body(Body::from(r#"{"model":"chutesai/AutoPilot"}"#))
                     .unwrap(),
             )
             .await
             .unwrap();
-
         assert_eq!(resp.status(), StatusCode::OK);
+        let _ = resp.into_body().collect().await.unwrap();
+        assert_eq!(take_models(&attempts), vec!["ok-TEE".to_string()]);
+
+        upstream_handle.abort();
+    }
 
+    #[test]
+    fn upstream_error_detail_extracts_message_from_live_fixtures() {
+        let html = live_fixture("chat_completions_no_auth_429_2026-02-18.html");
         assert_eq!(
-            resp.headers().get("x-chutes-autopilot-selected").unwrap(),
-            &axum::http::header::HeaderValue::from_static("chosen/TEE-Model")
+            upstream_error_detail(&html).as_deref(),
+            Some("429 Too Many Requests")
+        );
+        let json = live_fixture("chat_completions_invalid_token_2026-02-18.json");
+        assert_eq!(
+            upstream_error_detail(&json).as_deref(),
+            Some("Invalid token.")
         );
 
-        let got_attempts = attempts.lock().unwrap().clone();
-        assert_eq!(got_attempts, vec!["chosen/TEE-Model".to_string()]);
+        let validation = br#"{"detail":[{"msg":"field required"},{"msg":"bad nonce"}]}"#;
+        assert_eq!(
+            upstream_error_detail(validation).as_deref(),
+            Some("field required; bad nonce")
+        );
+        assert_eq!(
+            upstream_error_detail(b"  upstream\n  exploded ").as_deref(),
+            Some("upstream exploded")
+        );
+        assert_eq!(upstream_error_detail(b""), None);
 
-        let _ = resp.into_body().collect().await.unwrap();
+        let long = "x".repeat(MAX_NORMALIZED_ERROR_MESSAGE_CHARS + 10);
+        let trimmed = upstream_error_detail(long.as_bytes()).unwrap();
+        assert_eq!(trimmed.len(), MAX_NORMALIZED_ERROR_MESSAGE_CHARS + 3);
+    }
+
+    async fn normalized_error_response(status: StatusCode, body: Vec<u8>) -> Response {
+        let upstream = Router::new().route(
+            "/v1/chat/completions",
+            post(move || {
+                let body = body.clone();
+                async move {
+                    let mut resp = Response::new(Body::from(body));
+                    *resp.status_mut() = status;
+                    resp.headers_mut()
+                        .insert("x-upstream-trace", HeaderValue::from_static("abc"));
+                    resp
+                }
+            }),
+        );
+        let (base_url, upstream_handle) = spawn_upstream(upstream).await;
+        let state = AppState::new(AppConfig {
+            normalize_upstream_errors: true,
+            ..test_config(base_url)
+        });
+        {
+            let mut runtime = state.runtime.write().await;
+            runtime.models_allowlist = HashSet::from(["primary-TEE".to_string()]);
+            runtime.models_allowlist_at = Some(Instant::now());
+        }
+        let resp = post_chat(app(state), r#"{"model":"primary-TEE,primary-TEE"}"#).await;
+        let (parts, body) = resp.into_parts();
+        let bytes = body.collect().await.unwrap().to_bytes();
         upstream_handle.abort();
+        Response::from_parts(parts, Body::from(bytes))
     }
 
     #[tokio::test]
-    async fn healthz_ok() {
-        let state = AppState::new(AppConfig::default());
-        let app = app(state);
-
-        let resp = app
-            .oneshot(
-                Request::builder()
-                    .uri("/healthz")
-                    .body(Body::empty())
-                    .unwrap(),
-            )
-            .await
-            .unwrap();
+    async fn chat_completions_normalizes_html_error_body_when_enabled() {
+        let resp = normalized_error_response(
+            StatusCode::TOO_MANY_REQUESTS,
+            live_fixture("chat_completions_no_auth_429_2026-02-18.html"),
+        )
+        .await;
 
-        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(
+            resp.headers().get("content-type").unwrap(),
+            "application/json"
+        );
+        assert_eq!(resp.headers().get("x-upstream-trace").unwrap(), "abc");
+        assert_eq!(
+            resp.headers().get("x-chutes-autopilot-selected").unwrap(),
+            "primary-TEE"
+        );
+        let body = resp.into_body().collect().await.unwrap().to_bytes();
+        let v: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(v["error"]["type"], "rate_limit_error");
+        assert_eq!(v["error"]["code"], "upstream_error");
+        assert_eq!(v["error"]["message"], "429 Too Many Requests");
     }
 
     #[tokio::test]
-    async fn metrics_endpoint_returns_prometheus_text() {
-        let state = AppState::new(AppConfig::default());
-        let app = app(state);
+    async fn chat_completions_normalizes_detail_json_and_keeps_openai_errors_when_enabled() {
+        let resp = normalized_error_response(
+            StatusCode::UNAUTHORIZED,
+            live_fixture("chat_completions_invalid_token_2026-02-18.json"),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        let body = resp.into_body().collect().await.unwrap().to_bytes();
+        let v: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(v["error"]["type"], "authentication_error");
+        assert_eq!(v["error"]["message"], "Invalid token.");
 
-        let resp = app
-            .oneshot(
-                Request::builder()
-                    .uri("/metrics")
-                    .body(Body::empty())
-                    .unwrap(),
-            )
-            .await
-            .unwrap();
+        let openai = br#"{"error":{"message":"context too long","type":"invalid_request_error"}}"#;
+        let resp = normalized_error_response(StatusCode::BAD_REQUEST, openai.to_vec()).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let body = resp.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body.as_ref(), openai.as_slice());
+    }
 
-        assert_eq!(resp.status(), StatusCode::OK);
-        assert_eq!(
-            resp.headers()
-                .get(axum::http::header::CONTENT_TYPE.as_str())
-                .unwrap(),
-            &HeaderValue::from_static("text/plain; version=0.0.4")
+    #[tokio::test]
+    async fn chat_completions_relays_error_bodies_that_stall_before_completing() {
+        let upstream = Router::new().route(
+            "/v1/chat/completions",
+            post(|| async {
+                let chunks = stream::iter([&b"<html>partial"[..], &b" page</html>"[..]]).then(
+                    |chunk| async move {
+                        if chunk.starts_with(b" ") {
+                            tokio::time::sleep(Duration::from_millis(200)).await;
+                        }
+                        Ok::<_, std::io::Error>(Bytes::from_static(chunk))
+                    },
+                );
+                let mut resp = Response::new(Body::from_stream(chunks));
+                *resp.status_mut() = StatusCode::BAD_GATEWAY;
+                resp
+            }),
         );
+        let (base_url, upstream_handle) = spawn_upstream(upstream).await;
+        let state = AppState::new(AppConfig {
+            normalize_upstream_errors: true,
+            ..test_config(base_url)
+        });
 
+        let resp = post_chat(app(state), r#"{"model":"primary-TEE"}"#).await;
+        assert_eq!(resp.status(), StatusCode::BAD_GATEWAY);
         let body = resp.into_body().collect().await.unwrap().to_bytes();
-        let text = String::from_utf8(body.to_vec()).unwrap();
-        assert!(text.contains("chutes_autopilot_requests_active"));
+        assert_eq!(body.as_ref(), b"<html>partial page</html>");
+        upstream_handle.abort();
     }
 
-    #[tokio::test]
-    async fn readyz_defaults_to_503() {
-        let state = AppState::new(AppConfig::default());
-        let app = app(state);
+    fn test_override(id: u64, action: OverrideAction, model: &str, ttl: Duration) -> ModelOverride {
+        ModelOverride {
+            id,
+            action,
+            model: model.to_string(),
+            reason: "incident".to_string(),
+            created_at: SystemTime::now(),
+            ttl,
+            expires_at: Instant::now() + ttl,
+        }
+    }
 
-        let resp = app
-            .oneshot(
-                Request::builder()
-                    .uri("/readyz")
-                    .body(Body::empty())
-                    .unwrap(),
-            )
-            .await
-            .unwrap();
+    #[test]
+    fn alias_overrides_drain_boost_pin_and_expire() {
+        let ranked = || vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        let hour = Duration::from_secs(3_600);
+        let now = Instant::now();
 
-        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let drain_and_boost = vec![
+            test_override(1, OverrideAction::Drain, "a", hour),
+            test_override(2, OverrideAction::Boost, "c", hour),
+            test_override(3, OverrideAction::Boost, "new", hour),
+        ];
+        assert_eq!(
+            apply_alias_overrides(&drain_and_boost, ranked(), now),
+            vec!["c", "new", "b"]
+        );
 
-        let body = resp.into_body().collect().await.unwrap().to_bytes();
-        let parsed: OpenAiErrorResponse = serde_json::from_slice(&body).unwrap();
-        assert_eq!(parsed.error.code.as_deref(), Some("missing_allowlist"));
+        let pins = vec![
+            test_override(1, OverrideAction::Pin, "a", hour),
+            test_override(2, OverrideAction::Pin, "b", hour),
+        ];
+        assert_eq!(apply_alias_overrides(&pins, ranked(), now), vec!["b"]);
+
+        // A blocked pin is ignored, and blocks also remove the model from the ranking.
+        let blocked_pin = vec![
+            test_override(1, OverrideAction::Pin, "b", hour),
+            test_override(2, OverrideAction::Block, "b", hour),
+        ];
+        assert_eq!(
+            apply_alias_overrides(&blocked_pin, ranked(), now),
+            vec!["a", "c"]
+        );
+
+        let expired = vec![test_override(1, OverrideAction::Drain, "a", hour)];
+        assert_eq!(
+            apply_alias_overrides(&expired, ranked(), now + hour + Duration::from_secs(1)),
+            ranked()
+        );
     }
 
-    #[tokio::test]
-    async fn readyz_returns_200_when_snapshot_is_fresh_and_non_empty() {
-        let state = AppState::new(AppConfig::default());
-        {
-            let mut runtime = state.runtime.write().await;
-            runtime.models_allowlist = HashSet::from(["ready/TEE-Model".to_string()]);
-            runtime.models_allowlist_at = Some(Instant::now());
-            runtime.candidates = vec![RankedCandidate {
-                name: "ready/TEE-Model".to_string(),
-                active_instance_count: 1,
-                utilization_current: 0.0,
-                rate_limit_ratio_5m: 0.0,
-                score: 1.0,
-            }];
-            runtime.snapshot_at = Some(Instant::now());
+    async fn admin_request(
+        app: Router,
+        method: &str,
+        uri: &str,
+        token: Option<&str>,
+        body: &str,
+    ) -> (StatusCode, Value) {
+        let mut req = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json");
+        if let Some(token) = token {
+            req = req.header("authorization", format!("Bearer {token}"));
         }
-        let app = app(state);
-
         let resp = app
-            .oneshot(
-                Request::builder()
-                    .uri("/readyz")
-                    .body(Body::empty())
-                    .unwrap(),
-            )
+            .oneshot(req.body(Body::from(body.to_string())).unwrap())
             .await
             .unwrap();
-
-        assert_eq!(resp.status(), StatusCode::OK);
+        let status = resp.status();
+        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+        (
+            status,
+            serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+        )
     }
 
     #[tokio::test]
-    async fn readyz_returns_503_when_snapshot_is_stale() {
-        let cfg = AppConfig {
-            readyz_max_snapshot_age: Duration::from_millis(1),
+    async fn admin_api_requires_configured_token() {
+        let app = admin_app(AppState::new(AppConfig::default()));
+        let (status, body) =
+            admin_request(app, "GET", "/admin/overrides", Some("anything"), "").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["error"]["code"], "admin_disabled");
+
+        let app = app_with_admin_token();
+        let (status, body) = admin_request(app.clone(), "GET", "/admin/overrides", None, "").await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body["error"]["code"], "invalid_admin_token");
+        let (status, _) =
+            admin_request(app, "GET", "/admin/overrides", Some("wrong-token"), "").await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    fn app_with_admin_token() -> Router {
+        admin_app(AppState::new(AppConfig {
+            admin_token: Some(AdminToken("s3cret".to_string())),
             ..Default::default()
-        };
+        }))
+    }
 
-        let state = AppState::new(cfg);
-        {
-            let mut runtime = state.runtime.write().await;
-            runtime.models_allowlist = HashSet::from(["stale/TEE-Model".to_string()]);
-            runtime.models_allowlist_at = Some(Instant::now());
-            runtime.candidates = vec![RankedCandidate {
-                name: "stale/TEE-Model".to_string(),
-                active_instance_count: 1,
-                utilization_current: 0.0,
-                rate_limit_ratio_5m: 0.0,
-                score: 1.0,
-            }];
-            runtime.snapshot_at = Some(Instant::now() - Duration::from_secs(1));
+    #[tokio::test]
+    async fn admin_routes_are_not_served_on_the_public_app() {
+        let state = AppState::new(AppConfig {
+            admin_token: Some(AdminToken("s3cret".to_string())),
+            ..Default::default()
+        });
+        for uri in ["/admin/overrides", "/debug/candidates"] {
+            let (status, _) =
+                admin_request(app(state.clone()), "GET", uri, Some("s3cret"), "").await;
+            assert_eq!(status, StatusCode::NOT_FOUND, "{uri}");
+            let (status, _) =
+                admin_request(admin_app(state.clone()), "GET", uri, Some("s3cret"), "").await;
+            assert_eq!(status, StatusCode::OK, "{uri}");
         }
-        let app = app(state);
-
-        let resp = app
-            .oneshot(
-                Request::builder()
-                    .uri("/readyz")
-                    .body(Body::empty())
-                    .unwrap(),
-            )
-            .await
-            .unwrap();
+    }
 
-        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+    #[tokio::test]
+    async fn admin_overrides_are_listed_with_reasons_and_deletable() {
+        let app = app_with_admin_token();
+        let (status, created) = admin_request(
+            app.clone(),
+            "POST",
+            "/admin/overrides/drain",
+            Some("s3cret"),
+            r#"{"model":"flaky-TEE","reason":"elevated 5xx","ttl_secs":60}"#,
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(created["action"], "drain");
+        assert_eq!(
+            created["expires_at"].as_u64().unwrap() - created["created_at"].as_u64().unwrap(),
+            60
+        );
 
-        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
-        let parsed: OpenAiErrorResponse = serde_json::from_slice(&bytes).unwrap();
-        assert_eq!(parsed.error.code.as_deref(), Some("stale_snapshot"));
+        let (status, body) = admin_request(
+            app.clone(),
+            "POST",
+            "/admin/overrides/block",
+            Some("s3cret"),
+            r#"{"model":"flaky-TEE","reason":"  "}"#,
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"]["param"], "reason");
+
+        let (status, list) =
+            admin_request(app.clone(), "GET", "/admin/overrides", Some("s3cret"), "").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(list["data"].as_array().unwrap().len(), 1);
+        assert_eq!(list["data"][0]["model"], "flaky-TEE");
+        assert_eq!(list["data"][0]["reason"], "elevated 5xx");
+
+        let uri = format!("/admin/overrides/{}", created["id"]);
+        let (status, _) = admin_request(app.clone(), "DELETE", &uri, Some("s3cret"), "").await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        let (status, _) = admin_request(app.clone(), "DELETE", &uri, Some("s3cret"), "").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (_, list) = admin_request(app, "GET", "/admin/overrides", Some("s3cret"), "").await;
+        assert!(list["data"].as_array().unwrap().is_empty());
     }
 
     #[tokio::test]
-    async fn readyz_returns_503_when_allowlist_is_empty() {
-        let state = AppState::new(AppConfig::default());
-        {
-            let mut runtime = state.runtime.write().await;
-            runtime.models_allowlist_at = Some(Instant::now());
-            runtime.candidates = vec![RankedCandidate {
-                name: "ready/TEE-Model".to_string(),
-                active_instance_count: 1,
-                utilization_current: 0.0,
-                rate_limit_ratio_5m: 0.0,
-                score: 1.0,
-            }];
+    async fn admin_overrides_reshape_routing() {
+        let (upstream, attempts) = test_upstream(StatusCode::OK);
+        let (base_url, upstream_handle) = spawn_upstream(upstream).await;
+        let state = AppState::new(AppConfig {
+            admin_token: Some(AdminToken("s3cret".to_string())),
+            ..test_config(base_url)
+        });
+        {
+            let mut runtime = state.runtime.write().await;
+            runtime.candidates = ["first-TEE", "second-TEE"]
+                .into_iter()
+                .map(|name| RankedCandidate {
+                    name: name.to_string(),
+                    active_instance_count: 1,
+                    utilization_current: 0.1,
+                    score: 1.0,
+                    ..Default::default()
+                })
+                .collect();
             runtime.snapshot_at = Some(Instant::now());
         }
+        let admin = admin_app(state.clone());
         let app = app(state);
 
-        let resp = app
-            .oneshot(
-                Request::builder()
-                    .uri("/readyz")
-                    .body(Body::empty())
-                    .unwrap(),
-            )
-            .await
-            .unwrap();
+        let (status, _) = admin_request(
+            admin.clone(),
+            "POST",
+            "/admin/overrides/block",
+            Some("s3cret"),
+            r#"{"model":"first-TEE","reason":"bad outputs"}"#,
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+
+        let resp = post_chat(app.clone(), r#"{"model":"chutesai/AutoPilot"}"#).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get("x-chutes-autopilot-selected").unwrap(),
+            "second-TEE"
+        );
 
+        let resp = post_chat(app.clone(), r#"{"model":"first-TEE"}"#).await;
         assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
-        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
-        let parsed: OpenAiErrorResponse = serde_json::from_slice(&bytes).unwrap();
-        assert_eq!(parsed.error.code.as_deref(), Some("allowlist_empty"));
+        let body = resp.into_body().collect().await.unwrap().to_bytes();
+        let v: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(v["error"]["code"], "model_blocked");
+
+        let (status, _) = admin_request(
+            admin.clone(),
+            "POST",
+            "/admin/overrides/pin",
+            Some("s3cret"),
+            r#"{"model":"pinned-TEE","reason":"canary"}"#,
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        let resp = post_chat(app, r#"{"model":"chutesai/AutoPilot"}"#).await;
+        assert_eq!(
+            resp.headers().get("x-chutes-autopilot-selected").unwrap(),
+            "pinned-TEE"
+        );
+
+        assert_eq!(
+            take_models(&attempts),
+            vec!["second-TEE".to_string(), "pinned-TEE".to_string()]
+        );
+        upstream_handle.abort();
     }
 
     #[tokio::test]
-    async fn readyz_returns_503_when_allowlist_is_stale() {
-        let cfg = AppConfig {
-            readyz_max_allowlist_age: Duration::from_millis(1),
+    async fn debug_candidates_explains_scores_and_exclusions() {
+        let records: Vec<UtilizationRecord> =
+            serde_json::from_str(include_str!("../tests/testdata/utilization_fixture.json"))
+                .unwrap();
+        let state = AppState::new(AppConfig {
+            admin_token: Some(AdminToken("s3cret".to_string())),
             ..Default::default()
-        };
-        let state = AppState::new(cfg);
-        {
-            let mut runtime = state.runtime.write().await;
-            runtime.models_allowlist = HashSet::from(["ready/TEE-Model".to_string()]);
-            runtime.models_allowlist_at = Some(Instant::now() - Duration::from_secs(1));
-            runtime.candidates = vec![RankedCandidate {
-                name: "ready/TEE-Model".to_string(),
-                active_instance_count: 1,
-                utilization_current: 0.0,
-                rate_limit_ratio_5m: 0.0,
-                score: 1.0,
-            }];
-            runtime.snapshot_at = Some(Instant::now());
-        }
-        let app = app(state);
+        });
+        state
+            .update_candidate_snapshot(Ok(explain_candidates(records, &HashSet::new())))
+            .await;
 
-        let resp = app
+        let resp = admin_app(state.clone())
             .oneshot(
                 Request::builder()
-                    .uri("/readyz")
+                    .uri("/debug/candidates")
                     .body(Body::empty())
                     .unwrap(),
             )
             .await
             .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
 
-        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
-        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
-        let parsed: OpenAiErrorResponse = serde_json::from_slice(&bytes).unwrap();
-        assert_eq!(parsed.error.code.as_deref(), Some("stale_allowlist"));
-    }
-
-    #[tokio::test]
-    async fn chat_completions_direct_mode_proxies_upstream_without_selected_header() {
-        let attempts: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
-        let upstream_attempts = attempts.clone();
-        let upstream = Router::new().route(
-            "/v1/chat/completions",
-            post(move |Json(v): Json<Value>| {
-                let upstream_attempts = upstream_attempts.clone();
-                async move {
-                    let model = v
-                        .get("model")
-                        .and_then(Value::as_str)
-                        .unwrap_or_default()
-                        .to_string();
-                    upstream_attempts.lock().unwrap().push(model);
-                    (StatusCode::CREATED, "direct-ok")
-                }
-            }),
-        );
-        let (base_url, upstream_handle) = spawn_upstream(upstream).await;
-
-        let state = AppState::new(test_config(base_url));
-        let app = app(state);
-
-        let resp = app
+        let resp = admin_app(state)
             .oneshot(
                 Request::builder()
-                    .method("POST")
-                    .uri("/v1/chat/completions")
-                    .header("content-type", "application/json")
-                    .body(Body::from(r#"{"model":"direct-nontee"}"#))
+                    .uri("/debug/candidates")
+                    .header("authorization", "Bearer s3cret")
+                    .body(Body::empty())
                     .unwrap(),
             )
             .await
             .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = resp.into_body().collect().await.unwrap().to_bytes();
+        let v: Value = serde_json::from_slice(&body).unwrap();
 
-        assert_eq!(resp.status(), StatusCode::CREATED);
-        assert!(resp.headers().get("x-chutes-autopilot-selected").is_none());
-
-        let got_attempts = attempts.lock().unwrap().clone();
-        assert_eq!(got_attempts, vec!["direct-nontee".to_string()]);
+        assert_eq!(
+            v["autopilot_order"],
+            json!(["alpha-TEE", "beta-TEE", "gamma-TEE"])
+        );
+        let ranked = v["ranked"].as_array().unwrap();
+        assert_eq!(ranked[0]["position"], 0);
+        assert_eq!(ranked[0]["record"]["name"], "alpha-TEE");
+        assert_eq!(ranked[0]["util"], 0.0);
+        assert_eq!(ranked[0]["free_capacity"], 10.0);
+        assert_eq!(ranked[0]["score"], 10.0);
+        for key in ["throttle_signal", "scale_bonus", "record"] {
+            assert!(ranked[1].get(key).is_some(), "missing {key}");
+        }
 
-        let body = resp.into_body().collect().await.unwrap().to_bytes();
-        assert_eq!(body, Bytes::from_static(b"direct-ok"));
+        let excluded: Vec<(String, String)> = v["excluded"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| {
+                (
+                    e["record"]["name"].as_str().unwrap().to_string(),
+                    e["reason"].as_str().unwrap().to_string(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            excluded,
+            vec![
+                ("[private chute]".to_string(), "private".to_string()),
+                ("not-tee-model".to_string(), "not_tee".to_string()),
+                (
+                    "zero-instance-TEE".to_string(),
+                    "zero_instances".to_string()
+                ),
+            ]
+        );
+    }
 
-        upstream_handle.abort();
+    #[test]
+    fn explain_candidates_reports_allowlist_exclusions() {
+        let records: Vec<UtilizationRecord> =
+            serde_json::from_str(include_str!("../tests/testdata/utilization_fixture.json"))
+                .unwrap();
+        let allowlist = HashSet::from(["beta-TEE".to_string(), "not-tee-model".to_string()]);
+        let report = explain_candidates(records, &allowlist);
+
+        let ranked: Vec<&str> = report.ranked.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(ranked, vec!["not-tee-model", "beta-TEE"]);
+        assert_eq!(report.scored.len(), 2);
+        assert_eq!(report.scored[1].record.name, "beta-TEE");
+        assert_eq!(report.scored[1].position, 1);
+        assert!(report
+            .excluded
+            .iter()
+            .any(|e| e.record.name == "alpha-TEE" && e.reason == "not_in_allowlist"));
     }
 
-    #[tokio::test]
-    async fn chat_completions_streams_upstream_body_without_buffering() {
-        let upstream = Router::new().route(
-            "/v1/chat/completions",
-            post(|Json(_): Json<Value>| async move {
-                let first = stream::once(async move {
-                    Ok::<Bytes, std::io::Error>(Bytes::from_static(b"first"))
-                });
-                let second = stream::once(async move {
-                    tokio::time::sleep(Duration::from_millis(200)).await;
-                    Ok::<Bytes, std::io::Error>(Bytes::from_static(b"second"))
-                });
-                let combined = first.chain(second);
+    #[test]
+    fn explain_captured_ranking_matches_live_ranking() {
+        let v = explain_captured_ranking(
+            &live_fixture("utilization_2026-02-18.json"),
+            Some(&live_fixture("models_2026-02-18.json")),
+        )
+        .unwrap();
 
-                let mut resp = Response::new(Body::from_stream(combined));
-                *resp.status_mut() = StatusCode::OK;
-                resp
-            }),
+        let ranked: Vec<&str> = v["ranked"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|c| c["record"]["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(ranked.len(), 61);
+        assert_eq!(
+            ranked[..5],
+            [
+                "Qwen/Qwen3-32B",
+                "chutesai/Mistral-Small-3.1-24B-Instruct-2503",
+                "deepseek-ai/DeepSeek-V3-0324-TEE",
+                "openai/gpt-oss-120b-TEE",
+                "moonshotai/Kimi-K2.5-TEE",
+            ]
         );
-        let (upstream_url, upstream_handle) = spawn_upstream(upstream).await;
+        assert_eq!(ranked[60], "deepseek-ai/DeepSeek-R1-0528-TEE");
+        assert_eq!(v["models_allowlist_len"], 61);
 
-        let state = AppState::new(test_config(upstream_url));
-        let router = app(state);
-        let (autopilot_url, autopilot_handle) = spawn_upstream(router).await;
+        let excluded = v["excluded"].as_array().unwrap();
+        let count = |reason: &str| excluded.iter().filter(|e| e["reason"] == reason).count();
+        assert_eq!(excluded.len(), 477);
+        assert_eq!(count("private"), 442);
+        assert_eq!(count("not_in_allowlist"), 35);
 
-        let resp = reqwest::Client::new()
-            .post(format!("{autopilot_url}/v1/chat/completions"))
-            .header("content-type", "application/json")
-            .body(r#"{"model":"direct-TEE"}"#)
-            .send()
-            .await
-            .unwrap();
+        assert!(explain_captured_ranking(b"{}", None).is_err());
+    }
 
-        assert_eq!(resp.status(), StatusCode::OK);
+    fn report_with_scores(scores: &[(&str, u64)]) -> CandidateReport {
+        let records = scores
+            .iter()
+            .map(|(name, instances)| UtilizationRecord {
+                name: name.to_string(),
+                active_instance_count: *instances,
+                utilization_current: Some(0.0),
+                utilization_5m: Some(0.0),
+                utilization_15m: Some(0.0),
+                utilization_1h: Some(0.0),
+                rate_limit_ratio_5m: None,
+                rate_limit_ratio_15m: None,
+                rate_limit_ratio_1h: None,
+                scalable: false,
+                scale_allowance: None,
+                ..Default::default()
+            })
+            .collect();
+        explain_candidates(records, &HashSet::new())
+    }
 
-        let mut body_stream = resp.bytes_stream();
-        let first = tokio::time::timeout(Duration::from_millis(100), body_stream.next())
-            .await
-            .expect("timed out waiting for first body chunk")
-            .expect("upstream response ended early")
-            .expect("upstream chunk error");
-        assert_eq!(first, Bytes::from_static(b"first"));
+    fn ranked_names(report: &CandidateReport) -> Vec<&str> {
+        report.ranked.iter().map(|c| c.name.as_str()).collect()
+    }
 
-        let mut rest: Vec<u8> = Vec::new();
-        while let Some(item) = body_stream.next().await {
-            let chunk = item.expect("upstream chunk error");
-            rest.extend_from_slice(chunk.as_ref());
+    #[test]
+    fn leader_hysteresis_requires_margin_and_dwell() {
+        let margin = 2.0;
+        let dwell = Duration::from_secs(30);
+        let t0 = Instant::now();
+        let mut leader = None;
+
+        let mut report = report_with_scores(&[("a-TEE", 10), ("b-TEE", 9), ("c-TEE", 1)]);
+        let outcome = apply_leader_hysteresis(&mut report, &mut leader, margin, dwell, t0);
+        assert_eq!(outcome, LeaderOutcome::Changed);
+        assert_eq!(leader.as_ref().unwrap().name, "a-TEE");
+
+        // Challenger wins on noise, but the margin is not met: the leader is held.
+        let mut report = report_with_scores(&[("a-TEE", 10), ("b-TEE", 11), ("c-TEE", 1)]);
+        let later = t0 + Duration::from_secs(60);
+        let outcome = apply_leader_hysteresis(&mut report, &mut leader, margin, dwell, later);
+        assert_eq!(outcome, LeaderOutcome::Held);
+        assert_eq!(ranked_names(&report), vec!["a-TEE", "b-TEE", "c-TEE"]);
+        assert_eq!(report.scored[0].record.name, "a-TEE");
+        assert_eq!(report.scored[1].position, 1);
+
+        // Margin met but dwell not elapsed: still held.
+        let mut report = report_with_scores(&[("a-TEE", 10), ("b-TEE", 20)]);
+        let early = t0 + Duration::from_secs(5);
+        let outcome = apply_leader_hysteresis(&mut report, &mut leader, margin, dwell, early);
+        assert_eq!(outcome, LeaderOutcome::Held);
+        assert_eq!(ranked_names(&report), vec!["a-TEE", "b-TEE"]);
+
+        // Both met: the challenger takes over.
+        let mut report = report_with_scores(&[("a-TEE", 10), ("b-TEE", 20)]);
+        let outcome = apply_leader_hysteresis(&mut report, &mut leader, margin, dwell, later);
+        assert_eq!(outcome, LeaderOutcome::Changed);
+        assert_eq!(ranked_names(&report), vec!["b-TEE", "a-TEE"]);
+        assert_eq!(leader.as_ref().unwrap().since, later);
+
+        // A leader that disappears from the snapshot is replaced immediately.
+        let mut report = report_with_scores(&[("c-TEE", 1)]);
+        let outcome = apply_leader_hysteresis(&mut report, &mut leader, margin, dwell, later);
+        assert_eq!(outcome, LeaderOutcome::Changed);
+        assert_eq!(leader.as_ref().unwrap().name, "c-TEE");
+    }
+
+    #[test]
+    fn leader_hysteresis_disabled_keeps_deterministic_order() {
+        let mut leader = Some(Leader {
+            name: "b-TEE".to_string(),
+            since: Instant::now(),
+        });
+
+        // Equal scores: the name tie-break still decides within a refresh.
+        let mut report = report_with_scores(&[("b-TEE", 10), ("a-TEE", 10)]);
+        let outcome = apply_leader_hysteresis(
+            &mut report,
+            &mut leader,
+            0.0,
+            Duration::ZERO,
+            Instant::now(),
+        );
+        assert_eq!(outcome, LeaderOutcome::Changed);
+        assert_eq!(ranked_names(&report), vec!["a-TEE", "b-TEE"]);
+        assert_eq!(leader.as_ref().unwrap().name, "a-TEE");
+    }
+
+    #[test]
+    fn spread_choice_weighted_tracks_scores_and_p2c_skips_worst() {
+        let scores = [Some(3.0), Some(1.0), Some(0.0)];
+        let mut weighted = [0usize; 3];
+        let mut p2c = [0usize; 3];
+        for key in 0..2_000u32 {
+            let mut hasher = DefaultHasher::new();
+            key.hash(&mut hasher);
+            let seed = hasher.finish();
+            weighted[spread_choice(SelectionMode::Weighted, &scores, seed)] += 1;
+            p2c[spread_choice(SelectionMode::PowerOfTwo, &scores, seed)] += 1;
+            assert_eq!(spread_choice(SelectionMode::Top, &scores, seed), 0);
         }
 
-        let mut combined: Vec<u8> = Vec::new();
-        combined.extend_from_slice(first.as_ref());
-        combined.extend_from_slice(&rest);
-        assert_eq!(combined, b"firstsecond");
+        assert_eq!(weighted[2], 0);
+        assert!((1_350..1_650).contains(&weighted[0]), "{weighted:?}");
+        assert_eq!(p2c[2], 0);
+        assert!(p2c[0] > p2c[1] && p2c[1] > 0, "{p2c:?}");
 
-        autopilot_handle.abort();
-        upstream_handle.abort();
+        // Non-positive scores fall back to the top candidate; unknown scores count as the best.
+        assert_eq!(
+            spread_choice(SelectionMode::Weighted, &[Some(-1.0), Some(-2.0)], 42),
+            0
+        );
+        assert_eq!(
+            spread_choice(SelectionMode::PowerOfTwo, &[Some(1.0), None], 7),
+            0
+        );
     }
 
     #[tokio::test]
-    async fn chat_completions_does_not_set_sticky_model_on_upstream_error() {
-        let attempts: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
-        let upstream_attempts = attempts.clone();
-        let upstream = Router::new().route(
-            "/v1/chat/completions",
-            post(move |Json(v): Json<Value>| {
-                let upstream_attempts = upstream_attempts.clone();
-                async move {
-                    let model = v
-                        .get("model")
-                        .and_then(Value::as_str)
-                        .unwrap_or_default()
-                        .to_string();
-                    upstream_attempts.lock().unwrap().push(model);
-                    (StatusCode::INTERNAL_SERVER_ERROR, "nope")
-                }
-            }),
-        );
-        let (base_url, upstream_handle) = spawn_upstream(upstream).await;
+    async fn spread_alias_selection_is_deterministic_per_key_and_keeps_failover_order() {
+        let state = AppState::new(AppConfig {
+            selection_mode: SelectionMode::Weighted,
+            selection_top_k: 3,
+            ..Default::default()
+        });
+        state
+            .update_candidate_snapshot(Ok(report_with_scores(&[
+                ("a-TEE", 10),
+                ("b-TEE", 10),
+                ("c-TEE", 10),
+                ("d-TEE", 10),
+            ])))
+            .await;
 
-        let state = AppState::new(test_config(base_url));
-        let app = app(state.clone());
+        let mut firsts = HashSet::new();
+        for client in 0..32 {
+            let key = format!("auth:{client:016x}");
+            let mut once = state.candidate_models().await;
+            state.spread_alias_selection(&mut once, &key).await;
+            let mut again = state.candidate_models().await;
+            state.spread_alias_selection(&mut again, &key).await;
+            assert_eq!(once, again);
+
+            assert_ne!(once[0], "d-TEE");
+            let rest: Vec<&String> = once[1..].iter().collect();
+            let mut expected: Vec<&String> = Vec::new();
+            let ranked = ["a-TEE", "b-TEE", "c-TEE", "d-TEE"].map(String::from);
+            expected.extend(ranked.iter().filter(|m| **m != once[0]));
+            assert_eq!(rest, expected);
+            firsts.insert(once[0].clone());
+        }
+        assert_eq!(firsts.len(), 3);
+    }
 
-        let auth_headers = HeaderMap::from_iter([(
-            axum::http::header::AUTHORIZATION,
-            HeaderValue::from_static("Bearer sticky-api-token"),
-        )]);
-        let client_key = derive_sticky_key(&state.config, &auth_headers, &None).unwrap();
+    #[tokio::test]
+    async fn spread_alias_selection_keeps_boosted_models_first() {
+        let state = AppState::new(AppConfig {
+            selection_mode: SelectionMode::Weighted,
+            selection_top_k: 3,
+            ..Default::default()
+        });
+        state
+            .update_candidate_snapshot(Ok(report_with_scores(&[
+                ("a-TEE", 10),
+                ("b-TEE", 10),
+                ("c-TEE", 10),
+                ("d-TEE", 10),
+            ])))
+            .await;
+        state.runtime.write().await.overrides.push(test_override(
+            1,
+            OverrideAction::Boost,
+            "d-TEE",
+            Duration::from_secs(60),
+        ));
 
-        let resp = app
-            .oneshot(
-                Request::builder()
-                    .method("POST")
-                    .uri("/v1/chat/completions")
-                    .header("content-type", "application/json")
-                    .header("authorization", "Bearer sticky-api-token")
-                    .body(Body::from(r#"{"model":"direct-nontee"}"#))
-                    .unwrap(),
-            )
-            .await
+        let mut seconds = HashSet::new();
+        for client in 0..32 {
+            let mut candidates = state.candidate_models().await;
+            state
+                .spread_alias_selection(&mut candidates, &format!("auth:{client:016x}"))
+                .await;
+            assert_eq!(candidates[0], "d-TEE");
+            assert_eq!(candidates.len(), 4);
+            seconds.insert(candidates[1].clone());
+        }
+        assert_eq!(seconds.len(), 3);
+    }
+
+    #[test]
+    fn scaling_fields_adjust_scores_from_fixture() {
+        let records: Vec<UtilizationRecord> = serde_json::from_str(include_str!(
+            "../tests/testdata/utilization_scaling_fixture.json"
+        ))
+        .unwrap();
+        let report = explain_candidates(records, &HashSet::new());
+
+        let by_name = |name: &str| {
+            report
+                .scored
+                .iter()
+                .find(|s| s.record.name == name)
+                .unwrap()
+                .breakdown
+        };
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+        assert!(close(by_name("steady-TEE").scaling_adjustment, 0.0));
+        assert!(close(by_name("scaling-up-TEE").scaling_adjustment, 0.2));
+        // Three instances are being removed, each with half its capacity free.
+        assert!(close(by_name("scaling-down-TEE").scaling_adjustment, -1.5));
+        // No rate_limit_ratio_* fields: derived from request counts (100 / 1000).
+        let counts_only = by_name("counts-only-TEE");
+        assert!(close(counts_only.rate_limit_ratio_5m, 0.1));
+        assert!(close(counts_only.throttle_signal, 0.1));
+        assert!(close(counts_only.score, 1.2));
+
+        let names: Vec<&str> = report.ranked.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "scaling-up-TEE",
+                "steady-TEE",
+                "scaling-down-TEE",
+                "counts-only-TEE"
+            ]
+        );
+    }
+
+    #[test]
+    fn live_utilization_fixture_credits_pending_scale_up_once() {
+        let records: Vec<UtilizationRecord> =
+            serde_json::from_slice(&live_fixture("utilization_2026-02-18.json")).unwrap();
+        let record = records
+            .iter()
+            .find(|r| r.name == "deepseek-ai/DeepSeek-R1-0528-TEE")
             .unwrap();
+        assert_eq!(record.action_taken.as_deref(), Some("scale_up_candidate"));
+        assert_eq!(
+            (record.instance_count, record.target_count),
+            (Some(4), Some(9))
+        );
 
-        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
-        assert!(state.sticky_model(&client_key).await.is_none());
+        // scale_allowance (5) already covers the 4 -> 9 target, so the adjustment adds nothing.
+        let breakdown = ScoreBreakdown::for_record(record);
+        assert!((breakdown.scale_bonus - 0.25).abs() < 1e-9);
+        assert_eq!(breakdown.scaling_adjustment, 0.0);
+        assert!(records
+            .iter()
+            .filter(|r| r.target_count == r.instance_count && !r.is_scaling_down())
+            .all(|r| ScoreBreakdown::for_record(r).scaling_adjustment == 0.0));
+    }
 
-        let _ = resp.into_body().collect().await.unwrap();
-        upstream_handle.abort();
+    #[test]
+    fn schema_drift_is_clean_for_live_fixture() {
+        let body = live_fixture("utilization_2026-02-18.json");
+        let report = parse_utilization_payload(&body, &HashSet::new(), &HashMap::new()).unwrap();
+        assert_eq!(report.schema_drift.records, 538);
+        assert!(report.schema_drift.missing.is_empty());
+        assert_eq!(report.schema_drift.missing_core, 0);
+    }
+
+    /// The live fixture with `utilization_5m`/`utilization_current` renamed on every other record.
+    fn drifted_utilization_payload() -> Vec<u8> {
+        let mut records: Vec<Value> =
+            serde_json::from_slice(&live_fixture("utilization_2026-02-18.json")).unwrap();
+        for record in records.iter_mut().step_by(2) {
+            let obj = record.as_object_mut().unwrap();
+            for field in ["utilization_5m", "utilization_current"] {
+                let value = obj.remove(field).unwrap();
+                obj.insert(format!("{field}_v2"), value);
+            }
+        }
+        serde_json::to_vec(&records).unwrap()
     }
 
     #[tokio::test]
-    async fn chat_completions_preserves_compressed_upstream_bytes_and_headers() {
-        use flate2::write::GzEncoder;
-        use flate2::Compression;
-        use std::io::Write;
+    async fn strict_schema_mode_keeps_last_known_good_snapshot_on_drift() {
+        let drifted = parse_utilization_payload(
+            &drifted_utilization_payload(),
+            &HashSet::new(),
+            &HashMap::new(),
+        )
+        .unwrap();
+        assert_eq!(drifted.schema_drift.missing_core, 269);
+        assert_eq!(drifted.schema_drift.missing_count("utilization_5m"), 269);
+        assert_eq!(drifted.schema_drift.missing_count("utilization_1h"), 0);
+
+        let strict = AppState::new(AppConfig {
+            utilization_schema_mode: SchemaMode::Strict,
+            utilization_max_drift_pct: 10.0,
+            ..Default::default()
+        });
+        strict
+            .update_candidate_snapshot(Ok(report_with_scores(&[("keep-TEE", 1)])))
+            .await;
+        let result = strict.check_utilization_schema(drifted);
+        assert!(result.is_err());
+        strict.update_candidate_snapshot(result).await;
+        assert_eq!(strict.candidate_models().await, vec!["keep-TEE"]);
+
+        let metrics = strict.metrics.registry.gather();
+        let text = {
+            let mut buf = Vec::new();
+            TextEncoder::new().encode(&metrics, &mut buf).unwrap();
+            String::from_utf8(buf).unwrap()
+        };
+        assert!(text.contains(r#"chutes_autopilot_utilization_schema_drift{field="core"} 269"#));
+        assert!(text.contains("chutes_autopilot_utilization_schema_drift_rejected_total 1"));
 
-        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
-        encoder.write_all(b"hello").unwrap();
-        let gz = encoder.finish().unwrap();
+        // Lenient mode publishes the degraded snapshot but still reports the drift.
+        let lenient = AppState::new(AppConfig::default());
+        let drifted = parse_utilization_payload(
+            &drifted_utilization_payload(),
+            &HashSet::new(),
+            &HashMap::new(),
+        )
+        .unwrap();
+        let result = lenient.check_utilization_schema(drifted);
+        assert!(result.is_ok());
+        lenient.update_candidate_snapshot(result).await;
+        assert!(!lenient.candidate_models().await.is_empty());
+    }
 
-        let upstream_gz = gz.clone();
+    #[test]
+    fn refresh_delay_backs_off_with_jitter_and_caps() {
+        let interval = Duration::from_secs(5);
+        let cap = Duration::from_secs(60);
+        assert_eq!(refresh_delay(interval, 0, cap), interval);
+
+        for _ in 0..50 {
+            let first = refresh_delay(interval, 1, cap);
+            assert!(
+                first >= Duration::from_millis(7_500) && first < Duration::from_secs(10),
+                "{first:?}"
+            );
+            let third = refresh_delay(interval, 3, cap);
+            assert!(third >= Duration::from_millis(22_500) && third < Duration::from_secs(40));
+            let capped = refresh_delay(interval, u32::MAX, cap);
+            assert!(
+                capped >= Duration::from_millis(32_500) && capped < cap,
+                "{capped:?}"
+            );
+        }
+
+        // A failing source is never polled sooner than its healthy interval, and slow sources
+        // stay at it.
+        let models = Duration::from_secs(300);
+        assert_eq!(refresh_delay(models, 4, cap), models);
+    }
+
+    #[tokio::test]
+    async fn fetch_models_catalog_uses_etag_for_conditional_requests() {
         let upstream = Router::new().route(
-            "/v1/chat/completions",
-            post(move |Json(_): Json<Value>| {
-                let upstream_gz = upstream_gz.clone();
-                async move {
-                    let mut resp = Response::new(Body::from(upstream_gz));
-                    *resp.status_mut() = StatusCode::OK;
-                    resp.headers_mut().insert(
-                        axum::http::header::CONTENT_ENCODING,
-                        HeaderValue::from_static("gzip"),
-                    );
-                    resp
+            "/v1/models",
+            get(|headers: HeaderMap| async move {
+                if headers.get("if-none-match").and_then(|v| v.to_str().ok()) == Some("\"v1\"") {
+                    return StatusCode::NOT_MODIFIED.into_response();
                 }
+                (
+                    [("etag", "\"v1\"")],
+                    Json(json!({"data": [{"id": "a-TEE"}]})),
+                )
+                    .into_response()
             }),
         );
-        let (upstream_url, upstream_handle) = spawn_upstream(upstream).await;
+        let (base_url, upstream_handle) = spawn_upstream(upstream).await;
+        let client = Client::new();
+        let url = format!("{base_url}/v1/models");
+        let timeout = Duration::from_secs(1);
 
-        let state = AppState::new(test_config(upstream_url));
-        let router = app(state);
-        let (autopilot_url, autopilot_handle) = spawn_upstream(router).await;
+        let etag = match fetch_models_catalog(&client, &url, timeout, None)
+            .await
+            .unwrap()
+        {
+            ModelsFetch::Modified { models, etag } => {
+                assert_eq!(models[0].id, "a-TEE");
+                etag
+            }
+            ModelsFetch::NotModified => panic!("first fetch must return the catalog"),
+        };
+        assert_eq!(etag.as_deref(), Some("\"v1\""));
 
-        let resp = reqwest::Client::new()
-            .post(format!("{autopilot_url}/v1/chat/completions"))
-            .header("content-type", "application/json")
-            .body(r#"{"model":"direct-nontee"}"#)
-            .send()
+        let again = fetch_models_catalog(&client, &url, timeout, etag.as_deref())
             .await
             .unwrap();
+        assert!(matches!(again, ModelsFetch::NotModified));
 
-        assert_eq!(resp.status(), StatusCode::OK);
+        upstream_handle.abort();
+    }
+
+    #[test]
+    fn control_plane_outcomes_update_per_source_metrics() {
+        let state = AppState::new(AppConfig::default());
+        let err = anyhow::anyhow!("connection refused");
+        let failures = record_control_plane_outcome(&state, "utilization", Err(&err), 0);
+        let failures = record_control_plane_outcome(&state, "utilization", Err(&err), failures);
+        assert_eq!(failures, 2);
         assert_eq!(
-            resp.headers()
-                .get(axum::http::header::CONTENT_ENCODING.as_str())
-                .map(|v| v.to_str().unwrap()),
-            Some("gzip")
+            state
+                .metrics
+                .control_plane_consecutive_failures
+                .with_label_values(&["utilization"])
+                .get(),
+            2
+        );
+        assert!(
+            state
+                .metrics
+                .control_plane_last_error
+                .with_label_values(&["utilization"])
+                .get()
+                > 0
         );
 
-        let body = resp.bytes().await.unwrap();
-        assert_eq!(body.as_ref(), gz.as_slice());
+        let failures = record_control_plane_outcome(&state, "utilization", Ok("success"), failures);
+        assert_eq!(failures, 0);
+        record_control_plane_outcome(&state, "models", Ok("not_modified"), 0);
+        let fetches = |source: &str, outcome: &str| {
+            state
+                .metrics
+                .control_plane_fetch_total
+                .with_label_values(&[source, outcome])
+                .get()
+        };
+        assert_eq!(fetches("utilization", "failure"), 2);
+        assert_eq!(fetches("utilization", "success"), 1);
+        assert_eq!(fetches("models", "not_modified"), 1);
+        assert_eq!(
+            state
+                .metrics
+                .control_plane_consecutive_failures
+                .with_label_values(&["utilization"])
+                .get(),
+            0
+        );
+    }
 
-        autopilot_handle.abort();
-        upstream_handle.abort();
+    fn live_fixture_url(name: &str) -> String {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+        format!(
+            "file://{}",
+            root.join("tests/testdata/chutes_live").join(name).display()
+        )
     }
 
-    #[tokio::test]
-    async fn chat_completions_returns_openai_error_shape() {
-        let state = AppState::new(AppConfig::default());
-        let app = app(state);
+    #[test]
+    fn control_plane_source_parses_schemes() {
+        assert_eq!(
+            ControlPlaneSource::parse("https://llm.chutes.ai/v1/models"),
+            ControlPlaneSource::Http("https://llm.chutes.ai/v1/models".to_string())
+        );
+        assert_eq!(
+            ControlPlaneSource::parse("file:///srv/utilization.json"),
+            ControlPlaneSource::File(PathBuf::from("/srv/utilization.json"))
+        );
+        assert_eq!(
+            ControlPlaneSource::parse("static: b , a,,b"),
+            ControlPlaneSource::Static(vec!["b".to_string(), "a".to_string()])
+        );
+    }
 
-        let resp = app
-            .oneshot(
-                Request::builder()
-                    .method("POST")
-                    .uri("/v1/chat/completions")
-                    .header("content-type", "application/json")
-                    .body(Body::from(r#"{"model":"chutesai/AutoPilot"}"#))
-                    .unwrap(),
-            )
-            .await
-            .unwrap();
+    #[tokio::test]
+    async fn file_models_source_reports_not_modified_until_file_changes() {
+        let path = std::env::temp_dir().join(format!("autopilot-models-{}.json", Uuid::new_v4()));
+        std::fs::write(&path, br#"{"data":[{"id":"a-TEE"}]}"#).unwrap();
+        let url = format!("file://{}", path.display());
+        let client = Client::new();
+        let timeout = Duration::from_secs(1);
+
+        let ModelsFetch::Modified { models, etag } =
+            fetch_models_catalog(&client, &url, timeout, None)
+                .await
+                .unwrap()
+        else {
+            panic!("first read must return the catalog");
+        };
+        assert_eq!(models[0].id, "a-TEE");
+        assert!(matches!(
+            fetch_models_catalog(&client, &url, timeout, etag.as_deref())
+                .await
+                .unwrap(),
+            ModelsFetch::NotModified
+        ));
 
-        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        std::fs::write(&path, br#"{"data":[{"id":"a-TEE"},{"id":"b-TEE"}]}"#).unwrap();
+        let ModelsFetch::Modified { models, .. } =
+            fetch_models_catalog(&client, &url, timeout, etag.as_deref())
+                .await
+                .unwrap()
+        else {
+            panic!("changed file must be re-read");
+        };
+        assert_eq!(models.len(), 2);
 
-        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
-        let parsed: OpenAiErrorResponse = serde_json::from_slice(&bytes).unwrap();
-        assert_eq!(parsed.error.error_type, "server_error");
-        assert_eq!(parsed.error.param.as_deref(), Some("model"));
-        assert_eq!(parsed.error.code.as_deref(), Some("no_candidates"));
+        std::fs::remove_file(&path).unwrap();
+        assert!(fetch_models_catalog(&client, &url, timeout, None)
+            .await
+            .is_err());
     }
 
     #[tokio::test]
-    async fn chat_completions_rejects_model_list_items_not_in_allowlist_when_present() {
-        let state = AppState::new(AppConfig::default());
-        {
-            let mut runtime = state.runtime.write().await;
-            runtime.models_allowlist =
-                HashSet::from(["allowed/Model".to_string(), "other/Model".to_string()]);
-        }
-
-        let app = app(state);
-        let resp = app
-            .oneshot(
-                Request::builder()
-                    .method("POST")
-                    .uri("/v1/chat/completions")
-                    .header("content-type", "application/json")
-                    .body(Body::from(r#"{"model":"allowed/Model,missing/Model"}"#))
-                    .unwrap(),
+    async fn file_utilization_source_is_reparsed_only_when_file_changes() {
+        let path =
+            std::env::temp_dir().join(format!("autopilot-utilization-{}.json", Uuid::new_v4()));
+        let record = |name: &str| {
+            format!(r#"[{{"name":"{name}","active_instance_count":2,"utilization_5m":0.5}}]"#)
+        };
+        std::fs::write(&path, record("a-TEE")).unwrap();
+        let mtime = std::fs::metadata(&path).unwrap().modified().unwrap();
+        let url = format!("file://{}", path.display());
+        let mut cache: UtilizationFileCache = None;
+        async fn fetch(
+            url: &str,
+            allowlist: &[&str],
+            cache: &mut UtilizationFileCache,
+        ) -> anyhow::Result<Vec<String>> {
+            let allowlist: HashSet<String> = allowlist.iter().map(|m| m.to_string()).collect();
+            let timeout = Duration::from_secs(1);
+            let report = fetch_ranked_candidates(
+                &Client::new(),
+                url,
+                &allowlist,
+                &HashMap::new(),
+                timeout,
+                cache,
             )
+            .await?;
+            Ok(report.ranked.into_iter().map(|c| c.name).collect())
+        }
+        assert_eq!(fetch(&url, &[], &mut cache).await.unwrap(), vec!["a-TEE"]);
+
+        // Same length and mtime: the cached records are re-ranked without reading the file.
+        std::fs::write(&path, record("b-TEE")).unwrap();
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(mtime).unwrap();
+        assert_eq!(fetch(&url, &[], &mut cache).await.unwrap(), vec!["a-TEE"]);
+        assert!(fetch(&url, &["other-TEE"], &mut cache)
             .await
-            .unwrap();
+            .unwrap()
+            .is_empty());
 
-        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        std::fs::write(&path, "not json").unwrap();
+        assert!(fetch(&url, &[], &mut cache).await.is_err());
+        assert!(fetch(&url, &[], &mut cache).await.is_err());
 
-        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
-        let parsed: OpenAiErrorResponse = serde_json::from_slice(&bytes).unwrap();
-        assert_eq!(parsed.error.error_type, "invalid_request_error");
-        assert_eq!(parsed.error.param.as_deref(), Some("model"));
-        assert_eq!(parsed.error.code.as_deref(), Some("unknown_model"));
+        std::fs::write(&path, record("c-TEE")).unwrap();
+        assert_eq!(fetch(&url, &[], &mut cache).await.unwrap(), vec!["c-TEE"]);
+        std::fs::remove_file(&path).unwrap();
     }
 
     #[tokio::test]
-    async fn chat_completions_model_list_failover_on_503() {
-        let attempts: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
-        let upstream_attempts = attempts.clone();
-        let upstream = Router::new().route(
-            "/v1/chat/completions",
-            post(move |Json(v): Json<Value>| {
-                let upstream_attempts = upstream_attempts.clone();
-                async move {
-                    let model = v
-                        .get("model")
-                        .and_then(Value::as_str)
-                        .unwrap_or_default()
-                        .to_string();
-                    upstream_attempts.lock().unwrap().push(model.clone());
+    async fn static_sources_rank_in_configured_order() {
+        let client = Client::new();
+        let timeout = Duration::from_secs(1);
+        let report = fetch_ranked_candidates(
+            &client,
+            "static:private-b,private-a,unlisted",
+            &HashSet::from(["private-a".to_string(), "private-b".to_string()]),
+            &HashMap::new(),
+            timeout,
+            &mut None,
+        )
+        .await
+        .unwrap();
+        let names: Vec<&str> = report.ranked.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["private-b", "private-a"]);
 
-                    if model == "first-TEE" {
-                        return (StatusCode::SERVICE_UNAVAILABLE, "try later").into_response();
-                    }
+        let ModelsFetch::Modified { models, etag } =
+            fetch_models_catalog(&client, "static:private-b,private-a", timeout, None)
+                .await
+                .unwrap()
+        else {
+            panic!("static catalog must be returned once");
+        };
+        assert_eq!(models.len(), 2);
+        assert!(matches!(
+            fetch_models_catalog(&client, "static:private-b", timeout, etag.as_deref())
+                .await
+                .unwrap(),
+            ModelsFetch::NotModified
+        ));
+    }
 
-                    (StatusCode::OK, "ok").into_response()
-                }
-            }),
-        );
-        let (base_url, upstream_handle) = spawn_upstream(upstream).await;
+    #[tokio::test]
+    async fn file_sources_replay_live_fixtures_until_ready() {
+        let state = AppState::new(AppConfig {
+            models_url: live_fixture_url("models_2026-02-18.json"),
+            utilization_url: live_fixture_url("utilization_2026-02-18.json"),
+            utilization_refresh_ms: Duration::from_millis(20),
+            ..Default::default()
+        });
+        spawn_control_plane_refresh(state.clone());
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            let resp = app(state.clone())
+                .oneshot(
+                    Request::builder()
+                        .uri("/readyz")
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            if resp.status() == StatusCode::OK {
+                break;
+            }
+            assert!(Instant::now() < deadline, "file sources never became ready");
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
 
-        let state = AppState::new(test_config(base_url));
-        let app = app(state);
+        let allowlist = state.runtime.read().await.models_allowlist.clone();
+        let records: Vec<UtilizationRecord> =
+            serde_json::from_slice(&live_fixture("utilization_2026-02-18.json")).unwrap();
+        let expected: Vec<String> = rank_candidates(records, &allowlist)
+            .into_iter()
+            .map(|c| c.name)
+            .collect();
+        // Wait for a utilization refresh that ran with the allowlist loaded.
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while state.candidate_models().await != expected {
+            assert!(
+                Instant::now() < deadline,
+                "candidates never matched the fixture ranking"
+            );
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    }
 
-        let resp = app
-            .oneshot(
-                Request::builder()
-                    .method("POST")
-                    .uri("/v1/chat/completions")
-                    .header("content-type", "application/json")
-                    .body(Body::from(r#"{"model":"first-TEE,second-TEE"}"#))
-                    .unwrap(),
-            )
-            .await
-            .unwrap();
+    fn local_backend(base_url: String) -> BackendConfig {
+        BackendConfig {
+            name: "local".to_string(),
+            base_url,
+            api_key: Some("local-key".to_string()),
+            models_url: Some("static:local-model".to_string()),
+            utilization_url: None,
+            stream_usage: false,
+        }
+    }
 
-        assert_eq!(resp.status(), StatusCode::OK);
-        assert_eq!(
-            resp.headers().get("x-chutes-autopilot-selected").unwrap(),
-            &axum::http::header::HeaderValue::from_static("second-TEE")
-        );
+    /// One request seen by [`test_upstream`].
+    #[derive(Clone, Debug)]
+    struct RecordedAttempt {
+        model: String,
+        auth: Option<String>,
+        body: Value,
+    }
 
-        let got_attempts = attempts.lock().unwrap().clone();
-        assert_eq!(
-            got_attempts,
-            vec!["first-TEE".to_string(), "second-TEE".to_string()]
-        );
+    type RecordedAttempts = Arc<Mutex<Vec<RecordedAttempt>>>;
 
-        let _ = resp.into_body().collect().await.unwrap();
-        upstream_handle.abort();
+    /// Drains the recorded attempts, returning their models in order.
+    fn take_models(attempts: &RecordedAttempts) -> Vec<String> {
+        attempts
+            .lock()
+            .unwrap()
+            .drain(..)
+            .map(|attempt| attempt.model)
+            .collect()
     }
 
-    #[tokio::test]
-    async fn chat_completions_model_list_failover_on_header_timeout() {
-        let attempts: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
-        let upstream_attempts = attempts.clone();
-        let upstream = Router::new().route(
+    /// Drains the recorded attempts, returning `(model, authorization)` pairs in order.
+    fn take_models_and_auth(attempts: &RecordedAttempts) -> Vec<(String, Option<String>)> {
+        attempts
+            .lock()
+            .unwrap()
+            .drain(..)
+            .map(|attempt| (attempt.model, attempt.auth))
+            .collect()
+    }
+
+    /// Chat completions upstream shared by the proxy tests. Records every attempt and answers by
+    /// model name: `slow*`/`stall*` after 400 ms, `limited*` with `429`, `bad-gateway*` with `502`,
+    /// `backoff*` with `503` and `Retry-After: 60`, `down*` with `503`, and anything else with
+    /// `status`. A `slow-` prefix combines with the others (`slow-down-TEE` is a late `503`).
+    /// Bodies are chat completions naming the model and reporting usage.
+    fn test_upstream(status: StatusCode) -> (Router, RecordedAttempts) {
+        let attempts: RecordedAttempts = Arc::default();
+        let recorded = attempts.clone();
+        let router = Router::new().route(
             "/v1/chat/completions",
-            post(move |Json(v): Json<Value>| {
-                let upstream_attempts = upstream_attempts.clone();
+            post(move |headers: HeaderMap, Json(body): Json<Value>| {
+                let recorded = recorded.clone();
                 async move {
-                    let model = v
-                        .get("model")
-                        .and_then(Value::as_str)
-                        .unwrap_or_default()
-                        .to_string();
-                    upstream_attempts.lock().unwrap().push(model.clone());
-
-                    if model == "slow-TEE" {
-                        tokio::time::sleep(Duration::from_millis(200)).await;
-                        return (StatusCode::OK, "slow").into_response();
+                    let model = body["model"].as_str().unwrap_or_default().to_string();
+                    let auth = headers
+                        .get(axum::http::header::AUTHORIZATION)
+                        .and_then(|v| v.to_str().ok())
+                        .map(ToString::to_string);
+                    recorded.lock().unwrap().push(RecordedAttempt {
+                        model: model.clone(),
+                        auth,
+                        body,
+                    });
+                    if model.starts_with("slow") || model.starts_with("stall") {
+                        tokio::time::sleep(Duration::from_millis(400)).await;
                     }
-
-                    (StatusCode::OK, "fast").into_response()
+                    let mut resp = Json(json!({
+                        "model": model,
+                        "choices": [{"message": {"content": format!("from {model}")}}],
+                        "usage": {"prompt_tokens": 5, "completion_tokens": 7}
+                    }))
+                    .into_response();
+                    *resp.status_mut() = match model.trim_start_matches("slow-") {
+                        m if m.starts_with("limited") => StatusCode::TOO_MANY_REQUESTS,
+                        m if m.starts_with("bad-gateway") => StatusCode::BAD_GATEWAY,
+                        m if m.starts_with("backoff") || m.starts_with("down") => {
+                            StatusCode::SERVICE_UNAVAILABLE
+                        }
+                        _ => status,
+                    };
+                    if model.starts_with("backoff") {
+                        resp.headers_mut()
+                            .insert("retry-after", HeaderValue::from_static("60"));
+                    }
+                    resp
                 }
             }),
         );
-        let (base_url, upstream_handle) = spawn_upstream(upstream).await;
+        (router, attempts)
+    }
 
-        let mut cfg = test_config(base_url);
-        cfg.upstream_header_timeout = Duration::from_millis(50);
-        let state = AppState::new(cfg);
-        let app = app(state);
+    async fn seed_backends(state: &AppState) {
+        let mut runtime = state.runtime.write().await;
+        runtime.models_allowlist = HashSet::from(["chutes-model".to_string()]);
+        runtime.candidates = vec![RankedCandidate {
+            name: "chutes-model".to_string(),
+            active_instance_count: 1,
+            score: 1.0,
+            ..Default::default()
+        }];
+        runtime.backends[0] = BackendSnapshot {
+            models: HashSet::from(["local-model".to_string()]),
+            ranked: vec!["local-model".to_string()],
+            refreshed_at: Some(Instant::now()),
+        };
+    }
 
-        let resp = app
+    #[tokio::test]
+    async fn autopilot_fails_over_to_additional_backend_with_its_credentials() {
+        let (primary, primary_attempts) = test_upstream(StatusCode::SERVICE_UNAVAILABLE);
+        let (local, local_attempts) = test_upstream(StatusCode::OK);
+        let (primary_url, primary_handle) = spawn_upstream(primary).await;
+        let (local_url, local_handle) = spawn_upstream(local).await;
+
+        let state = AppState::new(AppConfig {
+            backends: vec![local_backend(local_url)],
+            ..test_config(primary_url)
+        });
+        seed_backends(&state).await;
+
+        let resp = app(state.clone())
             .oneshot(
                 Request::builder()
                     .method("POST")
                     .uri("/v1/chat/completions")
                     .header("content-type", "application/json")
-                    .body(Body::from(r#"{"model":"slow-TEE,fast-TEE"}"#))
+                    .header("authorization", "Bearer client-key")
+                    .body(Body::from(r#"{"model":"chutesai/AutoPilot"}"#))
                     .unwrap(),
             )
             .await
@@ -2752,737 +9938,1544 @@ mod tests {
         assert_eq!(resp.status(), StatusCode::OK);
         assert_eq!(
             resp.headers().get("x-chutes-autopilot-selected").unwrap(),
-            &axum::http::header::HeaderValue::from_static("fast-TEE")
+            "local-model"
         );
-
-        let got_attempts = attempts.lock().unwrap().clone();
         assert_eq!(
-            got_attempts,
-            vec!["slow-TEE".to_string(), "fast-TEE".to_string()]
+            resp.headers().get("x-chutes-autopilot-backend").unwrap(),
+            "local"
+        );
+        assert_eq!(
+            take_models_and_auth(&primary_attempts),
+            vec![(
+                "chutes-model".to_string(),
+                Some("Bearer client-key".to_string())
+            )]
+        );
+        assert_eq!(
+            take_models_and_auth(&local_attempts),
+            vec![(
+                "local-model".to_string(),
+                Some("Bearer local-key".to_string())
+            )]
         );
 
-        let body = resp.into_body().collect().await.unwrap().to_bytes();
-        assert_eq!(body, Bytes::from_static(b"fast"));
+        let metrics = String::from_utf8(
+            app(state)
+                .oneshot(Request::get("/metrics").body(Body::empty()).unwrap())
+                .await
+                .unwrap()
+                .into_body()
+                .collect()
+                .await
+                .unwrap()
+                .to_bytes()
+                .to_vec(),
+        )
+        .unwrap();
+        assert!(metrics.contains(
+            r#"chutes_autopilot_backend_selection_total{backend="local",status="200"} 1"#
+        ));
 
-        upstream_handle.abort();
+        primary_handle.abort();
+        local_handle.abort();
     }
 
     #[tokio::test]
-    async fn chat_completions_failover_on_connection_reset_before_headers() {
-        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
-        let addr = listener.local_addr().unwrap();
-        let base_url = format!("http://{addr}");
+    async fn direct_requests_route_to_the_backend_serving_the_model() {
+        let (primary, primary_attempts) = test_upstream(StatusCode::OK);
+        let (local, local_attempts) = test_upstream(StatusCode::OK);
+        let (primary_url, primary_handle) = spawn_upstream(primary).await;
+        let (local_url, local_handle) = spawn_upstream(local).await;
+
+        let state = AppState::new(AppConfig {
+            backends: vec![BackendConfig {
+                api_key: None,
+                ..local_backend(local_url)
+            }],
+            ..test_config(primary_url)
+        });
+        seed_backends(&state).await;
 
-        let upstream_handle = tokio::spawn(async move {
-            let mut seen = 0u32;
-            loop {
-                let (mut socket, _) = listener.accept().await.unwrap();
-                seen += 1;
+        let resp = post_chat(app(state.clone()), r#"{"model":"local-model"}"#).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get("x-chutes-autopilot-backend").unwrap(),
+            "local"
+        );
+        assert!(primary_attempts.lock().unwrap().is_empty());
+        assert_eq!(
+            take_models_and_auth(&local_attempts),
+            vec![("local-model".to_string(), None)]
+        );
 
-                if seen == 1 {
-                    // Drop the connection immediately to simulate a reset before headers.
-                    continue;
-                }
+        let resp = post_chat(app(state), r#"{"model":"nowhere-model"}"#).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
 
-                let response = b"HTTP/1.1 200 OK\r\ncontent-length:2\r\n\r\nok";
-                if let Err(err) = socket.write_all(response).await {
-                    tracing::warn!(error = ?err, "failed to write upstream response in test");
-                }
-            }
+        primary_handle.abort();
+        local_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn additional_backend_refresh_ranks_models_in_catalog_order() {
+        let state = AppState::new(AppConfig {
+            backends: vec![BackendConfig {
+                models_url: Some("static:b-model,a-model".to_string()),
+                ..local_backend("http://127.0.0.1:9".to_string())
+            }],
+            ..Default::default()
         });
+        let handle = tokio::spawn(refresh_backend(state.clone(), 0));
 
-        let mut cfg = test_config(base_url);
-        cfg.upstream_header_timeout = Duration::from_millis(200);
-        let state = AppState::new(cfg);
-        {
-            let mut runtime = state.runtime.write().await;
-            runtime.candidates = vec![
-                RankedCandidate {
-                    name: "first-TEE".to_string(),
-                    active_instance_count: 1,
-                    utilization_current: 0.1,
-                    rate_limit_ratio_5m: 0.0,
-                    score: 1.0,
-                },
-                RankedCandidate {
-                    name: "second-TEE".to_string(),
-                    active_instance_count: 1,
-                    utilization_current: 0.1,
-                    rate_limit_ratio_5m: 0.0,
-                    score: 0.9,
-                },
-            ];
-            runtime.snapshot_at = Some(Instant::now());
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while state.runtime.read().await.backends[0].ranked.is_empty() {
+            assert!(Instant::now() < deadline, "backend never refreshed");
+            tokio::time::sleep(Duration::from_millis(10)).await;
         }
+        assert_eq!(
+            state.runtime.read().await.backends[0].ranked,
+            vec!["b-model", "a-model"]
+        );
+        handle.abort();
+    }
 
-        let app = app(state);
-        let resp = app
-            .oneshot(
-                Request::builder()
-                    .method("POST")
-                    .uri("/v1/chat/completions")
-                    .header("content-type", "application/json")
-                    .body(Body::from(r#"{"model":"first-TEE,second-TEE"}"#))
-                    .unwrap(),
-            )
-            .await
-            .unwrap();
+    #[test]
+    fn evidence_fixtures_classify_by_documented_error_shape() {
+        let cases = [
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "evidence_missing_nonce_2026-02-18.json",
+                AttestationStatus::Error,
+            ),
+            (
+                StatusCode::BAD_REQUEST,
+                "evidence_short_nonce_2026-02-18.json",
+                AttestationStatus::Error,
+            ),
+            (
+                StatusCode::BAD_REQUEST,
+                "evidence_nontee_2026-02-18.json",
+                AttestationStatus::NonTee,
+            ),
+            (
+                StatusCode::BAD_REQUEST,
+                "evidence_tee_version_error_2026-02-18.json",
+                AttestationStatus::RuntimeTooOld,
+            ),
+        ];
+        for (status, fixture, expected) in cases {
+            assert_eq!(
+                classify_evidence(status, &live_fixture(fixture)),
+                expected,
+                "{fixture}"
+            );
+        }
+
+        let probe: Value =
+            serde_json::from_slice(&live_fixture("evidence_probe_2026-02-18.json")).unwrap();
+        for sample in probe["samples"].as_array().unwrap() {
+            let status =
+                StatusCode::from_u16(sample["http_status"].as_u64().unwrap() as u16).unwrap();
+            let body = serde_json::to_vec(&json!({ "detail": sample["detail"] })).unwrap();
+            assert_eq!(
+                classify_evidence(status, &body),
+                AttestationStatus::RuntimeTooOld
+            );
+        }
 
-        assert_eq!(resp.status(), StatusCode::OK);
         assert_eq!(
-            resp.headers().get("x-chutes-autopilot-selected").unwrap(),
-            &axum::http::header::HeaderValue::from_static("second-TEE")
+            classify_evidence(StatusCode::OK, br#"{"quote":"abc"}"#),
+            AttestationStatus::EvidenceReturned
+        );
+        assert_eq!(
+            classify_evidence(StatusCode::OK, b"<html>"),
+            AttestationStatus::Error
+        );
+        assert_eq!(
+            classify_evidence(StatusCode::BAD_GATEWAY, b"{}"),
+            AttestationStatus::Error
+        );
+    }
+
+    #[test]
+    fn attestation_nonces_are_fresh_64_hex_strings() {
+        let nonce = attestation_nonce();
+        assert_eq!(nonce.len(), 64);
+        assert!(nonce.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(nonce, attestation_nonce());
+    }
+
+    #[tokio::test]
+    async fn require_attested_routes_only_to_chutes_returning_evidence() {
+        let nonces: Arc<Mutex<Vec<String>>> = Arc::default();
+        let seen = nonces.clone();
+        let evidence = Router::new().route(
+            "/chutes/:chute_id/evidence",
+            get(
+                move |axum::extract::Path(chute_id): axum::extract::Path<String>,
+                      axum::extract::Query(query): axum::extract::Query<HashMap<String, String>>| {
+                    let seen = seen.clone();
+                    async move {
+                        seen.lock().unwrap().push(query["nonce"].clone());
+                        if chute_id == "id-current" {
+                            (StatusCode::OK, r#"{"evidence":[]}"#).into_response()
+                        } else {
+                            (
+                                StatusCode::BAD_REQUEST,
+                                r#"{"detail":"Instances requires chutes_version >= 0.6.0 to retrieve evidence."}"#,
+                            )
+                                .into_response()
+                        }
+                    }
+                },
+            ),
+        );
+        let (evidence_url, evidence_handle) = spawn_upstream(evidence).await;
+
+        let state = AppState::new(AppConfig {
+            require_attested: true,
+            attestation_evidence_url: format!("{evidence_url}/chutes/{{chute_id}}/evidence"),
+            utilization_refresh_ms: Duration::from_millis(10),
+            ..test_config("http://127.0.0.1:9".to_string())
+        });
+        let records = [("old-TEE", "id-old"), ("current-TEE", "id-current")]
+            .into_iter()
+            .map(|(name, chute_id)| UtilizationRecord {
+                chute_id: Some(chute_id.to_string()),
+                name: name.to_string(),
+                active_instance_count: 1,
+                ..Default::default()
+            })
+            .collect();
+        state
+            .update_candidate_snapshot(Ok(explain_candidates(records, &HashSet::new())))
+            .await;
+        assert!(state.candidate_models().await.is_empty());
+
+        let handle = tokio::spawn(refresh_attestations(state.clone()));
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while state.candidate_models().await.is_empty() {
+            assert!(Instant::now() < deadline, "attestations never completed");
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        handle.abort();
+
+        assert_eq!(state.candidate_models().await, vec!["current-TEE"]);
+        assert_eq!(
+            state.runtime.read().await.attestations["old-TEE"].status,
+            AttestationStatus::RuntimeTooOld
         );
+        let nonces = nonces.lock().unwrap().clone();
+        assert_eq!(nonces.len(), 2);
+        assert!(nonces.iter().all(|n| n.len() == 64));
 
+        let resp = post_chat(app(state), r#"{"model":"old-TEE"}"#).await;
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
         let body = resp.into_body().collect().await.unwrap().to_bytes();
-        assert_eq!(body, Bytes::from_static(b"ok"));
+        let parsed: OpenAiErrorResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(parsed.error.code.as_deref(), Some("attestation_required"));
 
-        upstream_handle.abort();
+        evidence_handle.abort();
     }
 
-    #[tokio::test]
-    async fn chat_completions_connect_error_reports_bad_gateway_after_exhaustion() {
-        // Reserve a local port and drop the listener so connects fail immediately.
-        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
-        let addr = listener.local_addr().unwrap();
-        drop(listener);
+    #[test]
+    fn chutes_version_gate_matches_probe_snapshot() {
+        let probe: Value =
+            serde_json::from_slice(&live_fixture("evidence_probe_2026-02-18.json")).unwrap();
+        for sample in probe["samples"].as_array().unwrap() {
+            let version = sample["chutes_version"].as_str().unwrap();
+            assert_eq!(meets_evidence_gate(version), Some(false), "{version}");
+        }
 
-        let mut cfg = test_config(format!("http://{addr}"));
-        cfg.upstream_connect_timeout = Duration::from_millis(50);
-        cfg.upstream_header_timeout = Duration::from_millis(200);
-        let state = AppState::new(cfg);
+        assert_eq!(meets_evidence_gate("0.6.0"), Some(true));
+        assert_eq!(meets_evidence_gate("0.6.0.rc1"), Some(false));
+        assert_eq!(meets_evidence_gate("0.6.2"), Some(true));
+        assert_eq!(meets_evidence_gate("1.0"), Some(true));
+        assert_eq!(meets_evidence_gate("unknown"), None);
+    }
+
+    #[tokio::test]
+    async fn attestation_status_is_reported_per_request_and_per_candidate() {
+        let (upstream, _attempts) = test_upstream(StatusCode::OK);
+        let (base_url, upstream_handle) = spawn_upstream(upstream).await;
+        let state = AppState::new(AppConfig {
+            attestation_enabled: true,
+            admin_token: Some(AdminToken("s3cret".to_string())),
+            ..test_config(base_url)
+        });
+        let records = vec![
+            UtilizationRecord {
+                chute_id: Some("id-a".to_string()),
+                name: "a-TEE".to_string(),
+                active_instance_count: 3,
+                ..Default::default()
+            },
+            UtilizationRecord {
+                chute_id: Some("id-b".to_string()),
+                name: "b-TEE".to_string(),
+                active_instance_count: 2,
+                chutes_version: Some("0.5.4.rc7".to_string()),
+                ..Default::default()
+            },
+            UtilizationRecord {
+                chute_id: Some("id-c".to_string()),
+                name: "c-TEE".to_string(),
+                active_instance_count: 1,
+                ..Default::default()
+            },
+        ];
+        state
+            .update_candidate_snapshot(Ok(explain_candidates(records, &HashSet::new())))
+            .await;
         {
             let mut runtime = state.runtime.write().await;
-            runtime.candidates = vec![
-                RankedCandidate {
-                    name: "first-TEE".to_string(),
-                    active_instance_count: 1,
-                    utilization_current: 0.1,
-                    rate_limit_ratio_5m: 0.0,
-                    score: 1.0,
-                },
-                RankedCandidate {
-                    name: "second-TEE".to_string(),
-                    active_instance_count: 1,
-                    utilization_current: 0.1,
-                    rate_limit_ratio_5m: 0.0,
-                    score: 0.9,
-                },
-            ];
-            runtime.snapshot_at = Some(Instant::now());
+            for (model, chute_id, status) in [
+                ("a-TEE", "id-a", AttestationStatus::EvidenceReturned),
+                ("b-TEE", "id-b", AttestationStatus::RuntimeTooOld),
+            ] {
+                runtime.attestations.insert(
+                    model.to_string(),
+                    Attestation {
+                        chute_id: chute_id.to_string(),
+                        status,
+                        checked_at: Instant::now(),
+                    },
+                );
+            }
         }
 
-        let app = app(state);
-        let resp = app
+        let resp = post_chat(app(state.clone()), r#"{"model":"chutesai/AutoPilot"}"#).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers()
+                .get("x-chutes-autopilot-attestation")
+                .unwrap(),
+            "evidence-returned"
+        );
+        let resp = post_chat(app(state.clone()), r#"{"model":"c-TEE,b-TEE"}"#).await;
+        assert_eq!(
+            resp.headers()
+                .get("x-chutes-autopilot-attestation")
+                .unwrap(),
+            "unverified"
+        );
+
+        let resp = admin_app(state.clone())
             .oneshot(
-                Request::builder()
-                    .method("POST")
-                    .uri("/v1/chat/completions")
-                    .header("content-type", "application/json")
-                    .body(Body::from(r#"{"model":"first-TEE,second-TEE"}"#))
+                Request::get("/debug/candidates")
+                    .header("authorization", "Bearer s3cret")
+                    .body(Body::empty())
                     .unwrap(),
             )
             .await
             .unwrap();
+        let body: Value =
+            serde_json::from_slice(&resp.into_body().collect().await.unwrap().to_bytes()).unwrap();
+        let summary: Vec<(&str, &str, &str)> = body["attestation"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|a| {
+                (
+                    a["model"].as_str().unwrap(),
+                    a["summary"].as_str().unwrap(),
+                    a["runtime_gate"].as_str().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("a-TEE", "evidence_returned", "meets"),
+                ("b-TEE", "unverified", "below"),
+                ("c-TEE", "unverified", "unknown"),
+            ]
+        );
 
-        assert_eq!(resp.status(), StatusCode::BAD_GATEWAY);
-        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
-        let parsed: OpenAiErrorResponse = serde_json::from_slice(&bytes).unwrap();
-        assert_eq!(parsed.error.code.as_deref(), Some("upstream_connect_error"));
+        state
+            .metrics
+            .observe_attestations(&candidate_attestations(&*state.runtime.read().await));
+        let metrics = String::from_utf8(
+            app(state)
+                .oneshot(Request::get("/metrics").body(Body::empty()).unwrap())
+                .await
+                .unwrap()
+                .into_body()
+                .collect()
+                .await
+                .unwrap()
+                .to_bytes()
+                .to_vec(),
+        )
+        .unwrap();
+        assert!(metrics
+            .contains(r#"chutes_autopilot_attestation_candidates{status="evidence_returned"} 1"#));
+        assert!(
+            metrics.contains(r#"chutes_autopilot_attestation_candidates{status="unverified"} 2"#)
+        );
+        assert!(metrics.contains(r#"chutes_autopilot_tee_runtime_gate_candidates{gate="below"} 1"#));
+
+        upstream_handle.abort();
     }
 
-    #[tokio::test]
-    async fn chat_completions_model_list_does_not_retry_on_429() {
-        let attempts: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
-        let upstream_attempts = attempts.clone();
-        let upstream = Router::new().route(
-            "/v1/chat/completions",
-            post(move |Json(v): Json<Value>| {
-                let upstream_attempts = upstream_attempts.clone();
-                async move {
-                    let model = v
-                        .get("model")
-                        .and_then(Value::as_str)
-                        .unwrap_or_default()
-                        .to_string();
-                    upstream_attempts.lock().unwrap().push(model.clone());
+    #[test]
+    fn utilization_joins_catalog_by_chute_id() {
+        let catalog = vec![OpenAiModelItem {
+            id: "vendor/Renamed-TEE".to_string(),
+            chute_id: Some("0df3133d-c477-56d2-b4db-f2093bb150a1".to_string()),
+            ..Default::default()
+        }];
+        let body = serde_json::to_vec(&json!([
+            {
+                "chute_id": "0df3133d-c477-56d2-b4db-f2093bb150a1",
+                "name": "vendor/Old-Name-TEE",
+                "active_instance_count": 2,
+            },
+            { "chute_id": "other", "name": "vendor/Uncatalogued-TEE", "active_instance_count": 2 },
+        ]))
+        .unwrap();
+        let allowlist = HashSet::from(["vendor/Renamed-TEE".to_string()]);
+
+        let report =
+            parse_utilization_payload(&body, &allowlist, &catalog_chute_names(&catalog)).unwrap();
+        assert_eq!(ranked_names(&report), vec!["vendor/Renamed-TEE"]);
+        assert_eq!(
+            report.ranked[0].chute_id.as_deref(),
+            Some("0df3133d-c477-56d2-b4db-f2093bb150a1")
+        );
+        assert_eq!(report.excluded[0].reason, "not_in_allowlist");
+    }
 
-                    if model == "first-TEE" {
-                        return (StatusCode::TOO_MANY_REQUESTS, "rate limited").into_response();
-                    }
+    #[test]
+    fn live_fixtures_share_chute_ids_between_catalog_and_utilization() {
+        let catalog: OpenAiModelListResponse =
+            serde_json::from_slice(&live_fixture("models_2026-02-18.json")).unwrap();
+        let chute_names = catalog_chute_names(&catalog.data);
+        assert_eq!(chute_names.len(), catalog.data.len());
+
+        let records: Vec<UtilizationRecord> =
+            serde_json::from_slice(&live_fixture("utilization_2026-02-18.json")).unwrap();
+        let joined = records
+            .iter()
+            .filter_map(|r| r.chute_id.as_ref())
+            .filter(|id| chute_names.contains_key(*id))
+            .count();
+        assert_eq!(joined, 61);
+    }
 
-                    (StatusCode::OK, "ok").into_response()
-                }
-            }),
-        );
+    #[tokio::test]
+    async fn requests_can_address_chutes_by_id() {
+        let (upstream, attempts) = test_upstream(StatusCode::OK);
         let (base_url, upstream_handle) = spawn_upstream(upstream).await;
-
         let state = AppState::new(test_config(base_url));
-        let app = app(state);
+        {
+            let mut runtime = state.runtime.write().await;
+            for (model, chute_id) in [
+                ("a-TEE", "11111111-1111-4111-8111-111111111111"),
+                ("b-TEE", "22222222-2222-4222-8222-222222222222"),
+            ] {
+                runtime.models_allowlist.insert(model.to_string());
+                runtime.models_catalog.insert(
+                    model.to_string(),
+                    OpenAiModelItem {
+                        id: model.to_string(),
+                        chute_id: Some(chute_id.to_string()),
+                        ..Default::default()
+                    },
+                );
+            }
+        }
 
-        let resp = app
-            .oneshot(
-                Request::builder()
-                    .method("POST")
-                    .uri("/v1/chat/completions")
-                    .header("content-type", "application/json")
-                    .body(Body::from(r#"{"model":"first-TEE,second-TEE"}"#))
-                    .unwrap(),
-            )
-            .await
-            .unwrap();
+        let resp = post_chat(
+            app(state.clone()),
+            r#"{"model":"11111111-1111-4111-8111-111111111111"}"#,
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
 
-        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        let resp = post_chat(
+            app(state.clone()),
+            r#"{"model":"22222222-2222-4222-8222-222222222222,b-TEE,a-TEE"}"#,
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
         assert_eq!(
             resp.headers().get("x-chutes-autopilot-selected").unwrap(),
-            &axum::http::header::HeaderValue::from_static("first-TEE")
+            "b-TEE"
+        );
+        assert_eq!(
+            resp.headers()
+                .get("x-chutes-autopilot-selected-chute-id")
+                .unwrap(),
+            "22222222-2222-4222-8222-222222222222"
         );
 
-        let got_attempts = attempts.lock().unwrap().clone();
-        assert_eq!(got_attempts, vec!["first-TEE".to_string()]);
+        let resp = post_chat(
+            app(state),
+            r#"{"model":"33333333-3333-4333-8333-333333333333"}"#,
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
 
-        let _ = resp.into_body().collect().await.unwrap();
+        let models: Vec<String> = attempts
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|attempt| attempt.model.clone())
+            .collect();
+        assert_eq!(models, vec!["a-TEE", "b-TEE"]);
         upstream_handle.abort();
     }
 
-    #[tokio::test]
-    async fn chat_completions_model_list_failover_on_first_body_timeout() {
-        let attempts: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
-        let upstream_attempts = attempts.clone();
-        let upstream = Router::new().route(
-            "/v1/chat/completions",
-            post(move |Json(v): Json<Value>| {
-                let upstream_attempts = upstream_attempts.clone();
-                async move {
-                    let model = v
-                        .get("model")
-                        .and_then(Value::as_str)
-                        .unwrap_or_default()
-                        .to_string();
-                    upstream_attempts.lock().unwrap().push(model.clone());
+    #[test]
+    fn sampling_parameters_match_the_catalog_vocabulary() {
+        let catalog: OpenAiModelListResponse =
+            serde_json::from_slice(&live_fixture("models_2026-02-18.json")).unwrap();
+        let vocabulary: HashSet<String> = catalog
+            .data
+            .into_iter()
+            .flat_map(|item| item.supported_sampling_parameters.unwrap_or_default())
+            .collect();
+        let known: HashSet<String> = SAMPLING_PARAMETERS.map(ToString::to_string).into();
+        assert_eq!(vocabulary, known);
+    }
 
-                    if model == "stall-TEE" {
-                        let delayed = stream::once(async move {
-                            tokio::time::sleep(Duration::from_millis(200)).await;
-                            Ok::<Bytes, std::io::Error>(Bytes::from_static(b"late"))
-                        });
-                        let mut resp = Response::new(Body::from_stream(delayed));
-                        *resp.status_mut() = StatusCode::OK;
-                        return resp;
-                    }
+    #[test]
+    fn attempt_body_strips_only_unsupported_sampling_params() {
+        let mut body = json!({
+            "model": "auto",
+            "messages": [],
+            "temperature": 0.2,
+            "top_k": 40,
+            "logit_bias": {"1": 5},
+            "max_tokens": 10
+        });
+        let limits = AttemptLimits {
+            sampling_params: Some(HashSet::from(["temperature".to_string()])),
+            max_output_length: None,
+            stream_usage: false,
+        };
 
-                    (StatusCode::OK, "ok").into_response()
-                }
-            }),
+        let (bytes, adjustments) = attempt_body(&mut body, "m", &limits).unwrap();
+        assert_eq!(adjustments.stripped_params, vec!["top_k"]);
+        let sent: Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(
+            sent,
+            json!({
+                "model": "m",
+                "messages": [],
+                "temperature": 0.2,
+                "logit_bias": {"1": 5},
+                "max_tokens": 10
+            })
         );
+        // The shared body keeps everything for the next candidate.
+        assert_eq!(body["top_k"], 40);
+
+        let (bytes, adjustments) = attempt_body(&mut body, "n", &AttemptLimits::default()).unwrap();
+        assert!(adjustments.stripped_params.is_empty());
+        let sent: Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(sent["top_k"], 40);
+    }
+
+    #[tokio::test]
+    async fn sanitizer_strips_params_per_candidate_and_reports_them() {
+        let (upstream, attempts) = test_upstream(StatusCode::OK);
         let (base_url, upstream_handle) = spawn_upstream(upstream).await;
+        let mut cfg = test_config(base_url);
+        cfg.sanitize_sampling_params = true;
+        let state = AppState::new(cfg);
+        {
+            let mut runtime = state.runtime.write().await;
+            for (model, params) in [
+                ("down-narrow", Some(vec!["temperature".to_string()])),
+                ("unlisted", None),
+            ] {
+                runtime.models_allowlist.insert(model.to_string());
+                runtime.models_catalog.insert(
+                    model.to_string(),
+                    OpenAiModelItem {
+                        id: model.to_string(),
+                        supported_sampling_parameters: params,
+                        ..Default::default()
+                    },
+                );
+            }
+        }
 
-        let state = AppState::new(test_config(base_url));
-        let app = app(state);
+        let resp = post_chat(
+            app(state.clone()),
+            r#"{"model":"down-narrow","temperature":0.5,"top_k":20,"seed":7}"#,
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
 
-        let resp = app
-            .oneshot(
-                Request::builder()
-                    .method("POST")
-                    .uri("/v1/chat/completions")
-                    .header("content-type", "application/json")
-                    .body(Body::from(r#"{"model":"stall-TEE,second-TEE"}"#))
-                    .unwrap(),
-            )
-            .await
-            .unwrap();
+        let resp = post_chat(
+            app(state),
+            r#"{"model":"down-narrow,unlisted","temperature":0.5,"top_k":20,"seed":7}"#,
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(resp
+            .headers()
+            .get("x-chutes-autopilot-stripped-params")
+            .is_none());
+
+        let bodies: Vec<Value> = attempts
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|a| a.body.clone())
+            .collect();
+        assert_eq!(bodies.len(), 3);
+        assert!(bodies[0].get("top_k").is_none());
+        assert!(bodies[0].get("seed").is_none());
+        assert_eq!(bodies[0]["temperature"], 0.5);
+        assert_eq!(bodies[2]["model"], "unlisted");
+        assert_eq!(bodies[2]["top_k"], 20);
+        assert_eq!(bodies[2]["seed"], 7);
+        upstream_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn sanitizer_header_lists_stripped_params_for_the_selected_model() {
+        let (upstream, _attempts) = test_upstream(StatusCode::OK);
+        let (base_url, upstream_handle) = spawn_upstream(upstream).await;
+        let mut cfg = test_config(base_url);
+        cfg.sanitize_sampling_params = true;
+        let state = AppState::new(cfg);
+        {
+            let mut runtime = state.runtime.write().await;
+            runtime.models_allowlist.insert("narrow".to_string());
+            runtime.models_catalog.insert(
+                "narrow".to_string(),
+                OpenAiModelItem {
+                    id: "narrow".to_string(),
+                    supported_sampling_parameters: Some(vec!["temperature".to_string()]),
+                    ..Default::default()
+                },
+            );
+        }
 
+        let resp = post_chat(
+            app(state),
+            r#"{"model":"narrow","temperature":0.5,"top_k":20,"seed":7}"#,
+        )
+        .await;
         assert_eq!(resp.status(), StatusCode::OK);
         assert_eq!(
-            resp.headers().get("x-chutes-autopilot-selected").unwrap(),
-            &axum::http::header::HeaderValue::from_static("second-TEE")
+            resp.headers()
+                .get("x-chutes-autopilot-stripped-params")
+                .unwrap(),
+            "top_k,seed"
         );
+        upstream_handle.abort();
+    }
 
-        let got_attempts = attempts.lock().unwrap().clone();
-        assert_eq!(
-            got_attempts,
-            vec!["stall-TEE".to_string(), "second-TEE".to_string()]
-        );
+    #[test]
+    fn attempt_body_clamps_output_length_fields() {
+        let mut body = json!({
+            "model": "auto",
+            "max_tokens": 100000,
+            "max_completion_tokens": 1000
+        });
+        let limits = AttemptLimits {
+            sampling_params: None,
+            max_output_length: Some(65536),
+            stream_usage: false,
+        };
 
-        let body = resp.into_body().collect().await.unwrap().to_bytes();
-        assert_eq!(body, Bytes::from_static(b"ok"));
+        let (bytes, adjustments) = attempt_body(&mut body, "m", &limits).unwrap();
+        assert_eq!(adjustments.max_tokens_clamped, Some(65536));
+        let sent: Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(sent["max_tokens"], 65536);
+        assert_eq!(sent["max_completion_tokens"], 1000);
+        assert_eq!(body["max_tokens"], 100000);
 
-        upstream_handle.abort();
+        body["max_tokens"] = json!(4096);
+        let (_, adjustments) = attempt_body(&mut body, "m", &limits).unwrap();
+        assert_eq!(adjustments.max_tokens_clamped, None);
     }
 
     #[tokio::test]
-    async fn chat_completions_single_candidate_returns_504_on_first_body_timeout() {
-        let upstream = Router::new().route(
-            "/v1/chat/completions",
-            post(|Json(_): Json<Value>| async move {
-                let delayed = stream::once(async move {
-                    tokio::time::sleep(Duration::from_millis(200)).await;
-                    Ok::<Bytes, std::io::Error>(Bytes::from_static(b"late"))
-                });
-                let mut resp = Response::new(Body::from_stream(delayed));
-                *resp.status_mut() = StatusCode::OK;
-                resp
-            }),
-        );
+    async fn max_tokens_clamp_is_per_candidate_and_reported() {
+        let (upstream, attempts) = test_upstream(StatusCode::OK);
         let (base_url, upstream_handle) = spawn_upstream(upstream).await;
-
         let mut cfg = test_config(base_url);
-        cfg.upstream_first_body_byte_timeout = Duration::from_millis(50);
+        cfg.clamp_max_tokens = true;
         let state = AppState::new(cfg);
-        let app = app(state);
+        {
+            let mut runtime = state.runtime.write().await;
+            for (model, limit) in [("down-large", 262144), ("small", 16384)] {
+                runtime.models_allowlist.insert(model.to_string());
+                runtime.models_catalog.insert(
+                    model.to_string(),
+                    OpenAiModelItem {
+                        id: model.to_string(),
+                        max_output_length: Some(limit),
+                        ..Default::default()
+                    },
+                );
+            }
+        }
 
-        let resp = app
-            .oneshot(
-                Request::builder()
-                    .method("POST")
-                    .uri("/v1/chat/completions")
-                    .header("content-type", "application/json")
-                    .body(Body::from(r#"{"model":"single-TEE"}"#))
-                    .unwrap(),
-            )
+        let resp = post_chat(
+            app(state),
+            r#"{"model":"down-large,small","max_tokens":100000}"#,
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers()
+                .get("x-chutes-autopilot-max-tokens-clamped")
+                .unwrap(),
+            "16384"
+        );
+
+        let bodies: Vec<Value> = attempts
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|a| a.body.clone())
+            .collect();
+        assert_eq!(bodies[0]["max_tokens"], 100000);
+        assert_eq!(bodies[1]["model"], "small");
+        assert_eq!(bodies[1]["max_tokens"], 16384);
+        upstream_handle.abort();
+    }
+
+    #[test]
+    fn routing_hints_parse_and_reject_bad_values() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(
+            RoutingHints::from_headers(&headers).unwrap(),
+            RoutingHints::default()
+        );
+
+        headers.insert(EXCLUDE_HEADER, HeaderValue::from_static(" a-TEE, ,b "));
+        headers.insert(REQUIRE_TEE_HEADER, HeaderValue::from_static("TRUE"));
+        headers.insert(STICKY_HEADER, HeaderValue::from_static("false"));
+        headers.insert(PREFER_HEADER, HeaderValue::from_static("cost"));
+        let hints = RoutingHints::from_headers(&headers).unwrap();
+        assert_eq!(
+            hints.exclude,
+            HashSet::from(["a-TEE".to_string(), "b".to_string()])
+        );
+        assert!(hints.require_tee && hints.disable_sticky);
+        assert_eq!(hints.prefer, Some(RoutePreference::Cost));
+        assert!(!hints.allows("a-TEE"));
+        assert!(!hints.allows("c"));
+        assert!(hints.allows("c-TEE"));
+
+        for (name, value) in [
+            (EXCLUDE_HEADER, ", ,"),
+            (REQUIRE_TEE_HEADER, "yes"),
+            (STICKY_HEADER, "1"),
+            (PREFER_HEADER, "speed"),
+        ] {
+            let mut headers = HeaderMap::new();
+            headers.insert(name, HeaderValue::from_static(value));
+            assert_eq!(RoutingHints::from_headers(&headers).unwrap_err().name, name);
+        }
+    }
+
+    async fn post_chat_with_headers(
+        app: Router,
+        body: &'static str,
+        headers: &[(&'static str, &'static str)],
+    ) -> Response {
+        let mut builder = Request::builder()
+            .method("POST")
+            .uri("/v1/chat/completions")
+            .header("content-type", "application/json");
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+        app.oneshot(builder.body(Body::from(body)).unwrap())
             .await
-            .unwrap();
+            .unwrap()
+    }
 
-        assert_eq!(resp.status(), StatusCode::GATEWAY_TIMEOUT);
+    #[tokio::test]
+    async fn routing_hints_narrow_and_reorder_candidates() {
+        let (upstream, attempts) = test_upstream(StatusCode::SERVICE_UNAVAILABLE);
+        let (base_url, upstream_handle) = spawn_upstream(upstream).await;
+        let state = AppState::new(test_config(base_url));
+        {
+            let mut runtime = state.runtime.write().await;
+            for (model, price) in [("a-TEE", 3.0), ("b-TEE", 1.0), ("c-TEE", 2.0), ("d", 0.5)] {
+                runtime.models_allowlist.insert(model.to_string());
+                runtime.models_catalog.insert(
+                    model.to_string(),
+                    OpenAiModelItem {
+                        id: model.to_string(),
+                        pricing: Some(ModelPricing {
+                            prompt: price,
+                            completion: price,
+                        }),
+                        ..Default::default()
+                    },
+                );
+            }
+        }
+
+        let resp = post_chat_with_headers(
+            app(state.clone()),
+            r#"{"model":"a-TEE,b-TEE,c-TEE,d"}"#,
+            &[
+                (EXCLUDE_HEADER, "c-TEE"),
+                (REQUIRE_TEE_HEADER, "true"),
+                (PREFER_HEADER, "cost"),
+            ],
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let models: Vec<String> = take_models(&attempts);
+        assert_eq!(models, vec!["b-TEE", "a-TEE"]);
+
+        let resp = post_chat_with_headers(
+            app(state.clone()),
+            r#"{"model":"d"}"#,
+            &[(REQUIRE_TEE_HEADER, "true")],
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
         let bytes = resp.into_body().collect().await.unwrap().to_bytes();
         let parsed: OpenAiErrorResponse = serde_json::from_slice(&bytes).unwrap();
         assert_eq!(
             parsed.error.code.as_deref(),
-            Some("upstream_first_body_byte_timeout")
+            Some("excluded_by_routing_hints")
         );
 
+        let resp = post_chat_with_headers(
+            app(state),
+            r#"{"model":"a-TEE"}"#,
+            &[(PREFER_HEADER, "cheapest")],
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+        let parsed: OpenAiErrorResponse = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(parsed.error.code.as_deref(), Some("invalid_header"));
+        assert_eq!(parsed.error.param.as_deref(), Some(PREFER_HEADER));
+        assert!(attempts.lock().unwrap().is_empty());
         upstream_handle.abort();
     }
 
     #[tokio::test]
-    async fn chat_completions_model_list_does_not_retry_after_committing_body_bytes() {
-        let attempts: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
-        let upstream_attempts = attempts.clone();
-        let upstream = Router::new().route(
-            "/v1/chat/completions",
-            post(move |Json(v): Json<Value>| {
-                let upstream_attempts = upstream_attempts.clone();
-                async move {
-                    let model = v
-                        .get("model")
-                        .and_then(Value::as_str)
-                        .unwrap_or_default()
-                        .to_string();
-                    upstream_attempts.lock().unwrap().push(model.clone());
+    async fn prefer_hint_keeps_boosted_alias_models_first() {
+        let (upstream, attempts) = test_upstream(StatusCode::SERVICE_UNAVAILABLE);
+        let (base_url, upstream_handle) = spawn_upstream(upstream).await;
+        let state = AppState::new(test_config(base_url));
+        state
+            .update_candidate_snapshot(Ok(report_with_scores(&[
+                ("a-TEE", 30),
+                ("b-TEE", 20),
+                ("c-TEE", 10),
+            ])))
+            .await;
+        {
+            let mut runtime = state.runtime.write().await;
+            for (model, price) in [("a-TEE", 3.0), ("b-TEE", 1.0), ("c-TEE", 2.0)] {
+                runtime.models_catalog.insert(
+                    model.to_string(),
+                    OpenAiModelItem {
+                        id: model.to_string(),
+                        pricing: Some(ModelPricing {
+                            prompt: price,
+                            completion: price,
+                        }),
+                        ..Default::default()
+                    },
+                );
+            }
+            runtime.overrides.push(test_override(
+                1,
+                OverrideAction::Boost,
+                "a-TEE",
+                Duration::from_secs(60),
+            ));
+        }
 
-                    if model == "first-TEE" {
-                        let first = stream::once(async move {
-                            Ok::<Bytes, std::io::Error>(Bytes::from_static(b"first"))
-                        });
-                        let second = stream::once(async move {
-                            tokio::time::sleep(Duration::from_millis(10)).await;
-                            Err::<Bytes, std::io::Error>(std::io::Error::other("boom"))
-                        });
-                        let combined = first.chain(second);
+        let resp = post_chat_with_headers(
+            app(state),
+            r#"{"model":"chutesai/AutoPilot"}"#,
+            &[(PREFER_HEADER, "cost")],
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(take_models(&attempts), vec!["a-TEE", "b-TEE", "c-TEE"]);
+        upstream_handle.abort();
+    }
 
-                        let mut resp = Response::new(Body::from_stream(combined));
-                        *resp.status_mut() = StatusCode::OK;
-                        return resp;
-                    }
+    #[tokio::test]
+    async fn sticky_hint_false_skips_stickiness() {
+        let (upstream, attempts) = test_upstream(StatusCode::OK);
+        let (base_url, upstream_handle) = spawn_upstream(upstream).await;
+        let state = AppState::new(test_config(base_url));
+        let mut auth = HeaderMap::new();
+        auth.insert("authorization", HeaderValue::from_static("Bearer test"));
+        let key = auth_token_key(&auth).unwrap();
+        state
+            .set_sticky_model(key.clone(), "b-TEE".to_string())
+            .await;
 
-                    (StatusCode::OK, "ok").into_response()
-                }
-            }),
-        );
-        let (upstream_url, upstream_handle) = spawn_upstream(upstream).await;
+        let resp = post_chat_with_headers(
+            app(state.clone()),
+            r#"{"model":"a-TEE,b-TEE"}"#,
+            &[("authorization", "Bearer test"), (STICKY_HEADER, "false")],
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(take_models(&attempts)[0], "a-TEE");
+        assert_eq!(state.sticky_model(&key).await.as_deref(), Some("b-TEE"));
+        upstream_handle.abort();
+    }
 
-        let state = AppState::new(test_config(upstream_url));
-        let router = app(state);
-        let (autopilot_url, autopilot_handle) = spawn_upstream(router).await;
+    #[test]
+    fn model_patterns_match_globs_and_families() {
+        assert!(glob_matches(
+            "deepseek-ai/DeepSeek-V3*",
+            "deepseek-ai/DeepSeek-V3.1-TEE"
+        ));
+        assert!(glob_matches("*-TEE", "zai-org/GLM-5-TEE"));
+        assert!(glob_matches("*/GLM-*-TEE", "zai-org/GLM-4.7-TEE"));
+        assert!(!glob_matches("*-TEE", "zai-org/GLM-4.7-FP8"));
+        assert!(!glob_matches("a*a", "a"));
 
-        let resp = reqwest::Client::new()
-            .post(format!("{autopilot_url}/v1/chat/completions"))
-            .header("content-type", "application/json")
-            .body(r#"{"model":"first-TEE,second-TEE"}"#)
-            .send()
-            .await
-            .unwrap();
+        let family = ModelPattern::parse("family:qwen3").unwrap();
+        assert!(family.matches("Qwen/Qwen3-32B"));
+        assert!(family.matches("Qwen/Qwen3.5-397B-A17B-TEE"));
+        assert!(!family.matches("Qwen/Qwen2.5-72B-Instruct"));
+        assert!(!family.matches("Qwen/Qwen30-Hypothetical"));
 
-        assert_eq!(resp.status(), StatusCode::OK);
         assert_eq!(
-            resp.headers()
-                .get("x-chutes-autopilot-selected")
-                .unwrap()
-                .to_str()
-                .unwrap(),
-            "first-TEE"
+            ModelPattern::parse("AutoPilot"),
+            Some(ModelPattern::AutoPilot)
         );
+        assert_eq!(ModelPattern::parse("Qwen/Qwen3-32B"), None);
+        assert_eq!(
+            routing_mode_for_model("*-TEE"),
+            RoutingMode::ExplicitModelList
+        );
+    }
 
-        let mut body_stream = resp.bytes_stream();
-        let first = tokio::time::timeout(Duration::from_millis(100), body_stream.next())
-            .await
-            .expect("timed out waiting for first body chunk")
-            .expect("body ended early")
-            .expect("chunk error");
-        assert_eq!(first, Bytes::from_static(b"first"));
-
-        tokio::time::sleep(Duration::from_millis(50)).await;
-
-        let got_attempts = attempts.lock().unwrap().clone();
-        assert_eq!(got_attempts, vec!["first-TEE".to_string()]);
+    #[tokio::test]
+    async fn preference_list_patterns_expand_in_rank_order() {
+        let cfg = AppConfig {
+            max_model_list_items: 4,
+            ..AppConfig::default()
+        };
+        let state = AppState::new(cfg);
+        {
+            let mut runtime = state.runtime.write().await;
+            runtime.models_allowlist = ["x/A-TEE", "x/B-TEE", "x/C", "y/Fav", "y/Other-TEE"]
+                .into_iter()
+                .map(ToString::to_string)
+                .collect();
+            runtime.candidates = ["x/B-TEE", "x/A-TEE"]
+                .into_iter()
+                .map(|name| RankedCandidate {
+                    name: name.to_string(),
+                    active_instance_count: 1,
+                    score: 1.0,
+                    ..Default::default()
+                })
+                .collect();
+        }
+        let items = |raw: &str| parse_model_preference_list(raw, 8).unwrap();
 
-        autopilot_handle.abort();
-        upstream_handle.abort();
+        assert_eq!(
+            state.expand_model_patterns(items("*-TEE")).await.unwrap(),
+            vec!["x/B-TEE", "x/A-TEE", "y/Other-TEE"]
+        );
+        assert_eq!(
+            state
+                .expand_model_patterns(items("y/Fav,autopilot,x/A-TEE"))
+                .await
+                .unwrap(),
+            vec!["y/Fav", "x/B-TEE", "x/A-TEE"]
+        );
+        assert_eq!(
+            state
+                .expand_model_patterns(items("y/Fav,x/*"))
+                .await
+                .unwrap(),
+            vec!["y/Fav", "x/B-TEE", "x/A-TEE", "x/C"]
+        );
+        assert_eq!(
+            state
+                .expand_model_patterns(items("y/*,x/*"))
+                .await
+                .unwrap_err()
+                .code,
+            "invalid_model_list"
+        );
+        assert_eq!(
+            state
+                .expand_model_patterns(items("family:Nope"))
+                .await
+                .unwrap_err()
+                .code,
+            "unknown_model"
+        );
+        assert_eq!(
+            state
+                .expand_model_patterns(items("a,family:"))
+                .await
+                .unwrap_err()
+                .code,
+            "invalid_model_list"
+        );
     }
 
     #[tokio::test]
-    async fn chat_completions_invalid_model_list_returns_400_openai_shape() {
-        let state = AppState::new(AppConfig::default());
-        let app = app(state);
-
-        let resp = app
-            .oneshot(
-                Request::builder()
-                    .method("POST")
-                    .uri("/v1/chat/completions")
-                    .header("content-type", "application/json")
-                    .body(Body::from(r#"{"model":","}"#))
-                    .unwrap(),
-            )
-            .await
-            .unwrap();
+    async fn chat_completions_rejects_patterns_that_expand_past_the_list_limit() {
+        let (upstream, attempts) = test_upstream(StatusCode::OK);
+        let (base_url, upstream_handle) = spawn_upstream(upstream).await;
+        let state = AppState::new(AppConfig {
+            max_model_list_items: 2,
+            ..test_config(base_url)
+        });
+        state.runtime.write().await.models_allowlist = ["a-TEE", "b-TEE", "c-TEE"]
+            .into_iter()
+            .map(ToString::to_string)
+            .collect();
 
+        let resp = post_chat(app(state), r#"{"model":"*-TEE"}"#).await;
         assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
-
         let bytes = resp.into_body().collect().await.unwrap().to_bytes();
         let parsed: OpenAiErrorResponse = serde_json::from_slice(&bytes).unwrap();
-        assert_eq!(parsed.error.error_type, "invalid_request_error");
-        assert_eq!(parsed.error.param.as_deref(), Some("model"));
         assert_eq!(parsed.error.code.as_deref(), Some("invalid_model_list"));
+        assert!(attempts.lock().unwrap().is_empty());
+        upstream_handle.abort();
     }
 
-    #[tokio::test]
-    async fn chat_completions_allows_non_tee_items_in_model_list_when_allowlist_is_empty() {
-        let attempts: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
-        let upstream_attempts = attempts.clone();
-        let upstream = Router::new().route(
-            "/v1/chat/completions",
-            post(move |Json(v): Json<Value>| {
-                let upstream_attempts = upstream_attempts.clone();
-                async move {
-                    let model = v
-                        .get("model")
-                        .and_then(Value::as_str)
-                        .unwrap_or_default()
-                        .to_string();
-                    upstream_attempts.lock().unwrap().push(model.clone());
+    #[test]
+    fn sunset_dates_parse_strictly_and_format_as_http_dates() {
+        let sunset = SunsetDate::parse("2026-12-31").unwrap();
+        assert_eq!(sunset.to_string(), "2026-12-31");
+        assert_eq!(sunset.http_date(), "Thu, 31 Dec 2026 00:00:00 GMT");
+        assert_eq!(
+            SunsetDate::parse("2024-02-29").unwrap().http_date(),
+            "Thu, 29 Feb 2024 00:00:00 GMT"
+        );
+        for bad in [
+            "2026-02-30",
+            "2026-13-01",
+            "26-1-1x",
+            "2026/12/31",
+            "1969-12-31",
+        ] {
+            assert_eq!(SunsetDate::parse(bad), None, "{bad}");
+        }
 
-                    if model == "a-TEE" {
-                        return (StatusCode::SERVICE_UNAVAILABLE, "try later").into_response();
-                    }
+        let day = |d: u64| UNIX_EPOCH + Duration::from_secs(d * 86_400);
+        assert!(!sunset.has_passed(day(sunset.days - 1)));
+        assert!(sunset.has_passed(day(sunset.days)));
 
-                    (StatusCode::OK, "ok").into_response()
-                }
-            }),
+        let redirect: ModelRedirect =
+            serde_json::from_str(r#"{"from":"a","to":"b","sunset":"2026-12-31"}"#).unwrap();
+        assert_eq!(redirect.sunset, Some(sunset));
+        assert!(
+            serde_json::from_str::<ModelRedirect>(r#"{"from":"a","to":"b","sunset":"soon"}"#)
+                .is_err()
         );
-        let (base_url, upstream_handle) = spawn_upstream(upstream).await;
+    }
 
-        let state = AppState::new(test_config(base_url));
-        let app = app(state);
+    #[test]
+    fn model_redirects_validate_and_default_the_deprecation_day() {
+        let redirect = |from: &str, to: &str| ModelRedirect {
+            from: from.to_string(),
+            to: to.to_string(),
+            deprecated: None,
+            sunset: None,
+        };
+        let now = UNIX_EPOCH + Duration::from_secs(1_767_225_600 + 3_600);
+
+        let mut redirects = vec![redirect("a", "c"), redirect("b", "c")];
+        redirects[1].deprecated = SunsetDate::parse("2025-06-01");
+        validate_model_redirects(&mut redirects, now).unwrap();
+        assert_eq!(redirects[0].deprecated, SunsetDate::parse("2026-01-01"));
+        assert_eq!(redirects[1].deprecated, SunsetDate::parse("2025-06-01"));
+
+        for (mut invalid, message) in [
+            (vec![redirect("a", "b"), redirect("a", "c")], "duplicate"),
+            (
+                vec![redirect("a", "b"), redirect("b", "c")],
+                "another redirected",
+            ),
+            (vec![redirect(" ", "b")], "non-empty"),
+        ] {
+            let err = validate_model_redirects(&mut invalid, now).unwrap_err();
+            assert!(err.to_string().contains(message), "{err}");
+        }
+    }
 
-        let resp = app
-            .oneshot(
-                Request::builder()
-                    .method("POST")
-                    .uri("/v1/chat/completions")
-                    .header("content-type", "application/json")
-                    .body(Body::from(r#"{"model":"a-TEE,b"}"#))
-                    .unwrap(),
-            )
+    #[tokio::test]
+    async fn redirected_models_route_to_replacement_until_sunset() {
+        let (upstream, attempts) = test_upstream(StatusCode::OK);
+        let (base_url, upstream_handle) = spawn_upstream(upstream).await;
+        let mut cfg = test_config(base_url);
+        cfg.model_redirects = vec![
+            ModelRedirect {
+                from: "old-TEE".to_string(),
+                to: "new-TEE".to_string(),
+                deprecated: SunsetDate::parse("2026-01-01"),
+                sunset: SunsetDate::parse("2999-01-01"),
+            },
+            ModelRedirect {
+                from: "gone-TEE".to_string(),
+                to: "new-TEE".to_string(),
+                deprecated: None,
+                sunset: SunsetDate::parse("2001-01-01"),
+            },
+        ];
+        let state = AppState::new(cfg);
+        state
+            .runtime
+            .write()
             .await
-            .unwrap();
+            .models_allowlist
+            .insert("new-TEE".to_string());
 
+        let resp = post_chat(app(state.clone()), r#"{"model":"old-TEE,new-TEE"}"#).await;
         assert_eq!(resp.status(), StatusCode::OK);
+        // RFC 9745: midnight UTC on 2026-01-01 as Unix seconds.
+        assert_eq!(resp.headers().get("deprecation").unwrap(), "@1767225600");
+        assert_eq!(
+            resp.headers().get("sunset").unwrap(),
+            "Tue, 01 Jan 2999 00:00:00 GMT"
+        );
         assert_eq!(
             resp.headers()
-                .get("x-chutes-autopilot-selected")
-                .unwrap()
-                .to_str()
+                .get("x-chutes-autopilot-redirected-from")
                 .unwrap(),
-            "b"
+            "old-TEE"
         );
+        assert_eq!(take_models(&attempts)[0], "new-TEE");
 
-        let got_attempts = attempts.lock().unwrap().clone();
-        assert_eq!(got_attempts, vec!["a-TEE".to_string(), "b".to_string()]);
+        let resp = post_chat(app(state.clone()), r#"{"model":"new-TEE"}"#).await;
+        assert!(resp.headers().get("deprecation").is_none());
+
+        let resp = post_chat(app(state.clone()), r#"{"model":"gone-TEE"}"#).await;
+        assert_eq!(resp.status(), StatusCode::GONE);
+        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+        let parsed: OpenAiErrorResponse = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(parsed.error.code.as_deref(), Some("model_retired"));
+        assert_eq!(attempts.lock().unwrap().len(), 1);
 
+        let metrics = String::from_utf8(
+            app(state)
+                .oneshot(Request::get("/metrics").body(Body::empty()).unwrap())
+                .await
+                .unwrap()
+                .into_body()
+                .collect()
+                .await
+                .unwrap()
+                .to_bytes()
+                .to_vec(),
+        )
+        .unwrap();
+        assert!(metrics
+            .contains(r#"chutes_autopilot_model_redirect_total{from="old-TEE",to="new-TEE"} 1"#));
         upstream_handle.abort();
     }
 
     #[test]
-    fn derive_sticky_key_parses_xff_socketaddr_when_peer_is_trusted() {
-        let cfg = AppConfig {
-            trust_proxy_headers: true,
-            trusted_proxy_cidrs: vec!["203.0.113.0/24".parse::<IpNet>().unwrap()],
-            ..Default::default()
-        };
+    fn unknown_model_suggestions_rank_common_typos() {
+        let catalog: Vec<String> = serde_json::from_slice::<OpenAiModelListResponse>(
+            &live_fixture("models_2026-02-18.json"),
+        )
+        .unwrap()
+        .data
+        .into_iter()
+        .map(|item| item.id)
+        .collect();
+        let suggest = |model: &str| suggest_models(model, catalog.iter(), MAX_MODEL_SUGGESTIONS);
 
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            "x-forwarded-for",
-            HeaderValue::from_static("198.51.100.7:1234, 203.0.113.10"),
+        assert_eq!(suggest("Qwen/qwen3-32b")[0], "Qwen/Qwen3-32B");
+        assert_eq!(
+            suggest("deepseek-ai/DeepSeek-V3.2")[0],
+            "deepseek-ai/DeepSeek-V3.2-TEE"
         );
-        let connect_info = Some(ConnectInfo(
-            "203.0.113.10:5678".parse::<SocketAddr>().unwrap(),
-        ));
-
-        let got = derive_sticky_key(&cfg, &headers, &connect_info).unwrap();
-        assert_eq!(got, "ip:198.51.100.7");
+        assert_eq!(suggest("GLM-5-TEE"), vec!["zai-org/GLM-5-TEE"]);
+        assert_eq!(suggest("Qwen/Qwen3-32C")[0], "Qwen/Qwen3-32B");
+        assert!(suggest("something/else-entirely").is_empty());
+        assert!(suggest(&"x".repeat(1024 * 1024)).is_empty());
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
     }
 
-    fn live_fixture(name: &str) -> Vec<u8> {
-        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
-        std::fs::read(root.join("tests/testdata/chutes_live").join(name)).unwrap()
-    }
-
-    #[test]
-    fn live_models_fixture_parses_allowlist() {
-        let bytes = live_fixture("models_2026-02-18.json");
-        let parsed: OpenAiModelListResponse = serde_json::from_slice(&bytes).unwrap();
-        assert!(parsed.data.len() > 50);
-        assert!(
-            parsed.data.iter().any(|m| m.id.contains("TEE")),
-            "fixture should include TEE-prefixed models"
+    #[tokio::test]
+    async fn unknown_model_errors_suggest_and_case_folding_is_opt_in() {
+        let (upstream, attempts) = test_upstream(StatusCode::OK);
+        let (base_url, upstream_handle) = spawn_upstream(upstream).await;
+        let state = AppState::new(test_config(base_url.clone()));
+        let allowlist: HashSet<String> = ["Qwen/Qwen3-32B", "zai-org/GLM-5-TEE"]
+            .into_iter()
+            .map(ToString::to_string)
+            .collect();
+        state.runtime.write().await.models_allowlist = allowlist.clone();
+
+        let resp = post_chat(app(state), r#"{"model":"qwen/qwen3-32b,GLM-5"}"#).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+        let parsed: OpenAiErrorResponse = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(parsed.error.code.as_deref(), Some("unknown_model"));
+        assert_eq!(
+            parsed.error.message,
+            "model list contains unknown model(s): qwen/qwen3-32b (did you mean Qwen/Qwen3-32B?), \
+             GLM-5 (did you mean zai-org/GLM-5-TEE?)"
         );
+
+        let cfg = AppConfig {
+            case_insensitive_models: true,
+            ..test_config(base_url)
+        };
+        let state = AppState::new(cfg);
+        state.runtime.write().await.models_allowlist = allowlist;
+        let resp = post_chat(app(state), r#"{"model":"qwen/qwen3-32b"}"#).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(take_models(&attempts)[0], "Qwen/Qwen3-32B");
+        upstream_handle.abort();
     }
 
-    #[test]
-    fn live_utilization_fixture_ranks_candidates_with_allowlist() {
-        let util_bytes = live_fixture("utilization_2026-02-18.json");
-        let records: Vec<UtilizationRecord> = serde_json::from_slice(&util_bytes).unwrap();
-        assert!(records.len() > 100);
+    #[tokio::test]
+    async fn mirror_rules_shadow_sampled_requests_without_affecting_the_client() {
+        let (upstream, attempts) = test_upstream(StatusCode::OK);
+        let (base_url, upstream_handle) = spawn_upstream(upstream).await;
+        let log_path =
+            std::env::temp_dir().join(format!("mirror-{}.jsonl", Uuid::new_v4().simple()));
+        let mut cfg = test_config(base_url);
+        cfg.mirror_log_path = Some(log_path.clone());
+        cfg.mirror_rules = vec![
+            MirrorRule {
+                name: "eval".to_string(),
+                source: "primary-TEE".to_string(),
+                shadow: "shadow-TEE".to_string(),
+                sample_percent: 100.0,
+                api_key: "eval-key".to_string(),
+            },
+            MirrorRule {
+                name: "never".to_string(),
+                source: "primary-TEE".to_string(),
+                shadow: "other-TEE".to_string(),
+                sample_percent: 0.0,
+                api_key: "never-key".to_string(),
+            },
+        ];
+        let state = AppState::new(cfg);
+
+        let resp = post_chat_with_headers(
+            app(state.clone()),
+            r#"{"model":"primary-TEE","stream":false,"messages":[]}"#,
+            &[("authorization", "Bearer client-key")],
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["model"], "primary-TEE");
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let line = loop {
+            if let Ok(contents) = std::fs::read_to_string(&log_path) {
+                if let Some(line) = contents.lines().next() {
+                    break serde_json::from_str::<Value>(line).unwrap();
+                }
+            }
+            assert!(Instant::now() < deadline, "mirror log never written");
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        };
+        std::fs::remove_file(&log_path).unwrap();
+
+        assert_eq!(line["rule"], "eval");
+        assert_eq!(line["primary_model"], "primary-TEE");
+        assert_eq!(line["primary_body"]["model"], "primary-TEE");
+        assert_eq!(line["shadow_model"], "shadow-TEE");
+        assert_eq!(line["shadow_status"], 200);
+        assert_eq!(line["shadow_usage"]["completion_tokens"], 7);
+        assert_eq!(
+            line["shadow_body"]["choices"][0]["message"]["content"],
+            "from shadow-TEE"
+        );
+        assert_eq!(attempts.lock().unwrap()[1].body["stream"], false);
+        assert_eq!(
+            take_models_and_auth(&attempts),
+            vec![
+                (
+                    "primary-TEE".to_string(),
+                    Some("Bearer client-key".to_string())
+                ),
+                (
+                    "shadow-TEE".to_string(),
+                    Some("Bearer eval-key".to_string())
+                ),
+            ]
+        );
 
-        let models_bytes = live_fixture("models_2026-02-18.json");
-        let allowlist: HashSet<String> =
-            serde_json::from_slice::<OpenAiModelListResponse>(&models_bytes)
+        let metrics = String::from_utf8(
+            app(state)
+                .oneshot(Request::get("/metrics").body(Body::empty()).unwrap())
+                .await
                 .unwrap()
-                .data
-                .into_iter()
-                .map(|m| m.id)
-                .collect();
-
-        let ranked = rank_candidates(records, &allowlist);
-        assert!(!ranked.is_empty());
-        assert!(ranked.iter().all(|c| allowlist.contains(&c.name)));
-        assert!(ranked.iter().all(|c| c.active_instance_count > 0));
+                .into_body()
+                .collect()
+                .await
+                .unwrap()
+                .to_bytes()
+                .to_vec(),
+        )
+        .unwrap();
+        assert!(metrics
+            .contains(r#"chutes_autopilot_mirror_requests_total{outcome="200",rule="eval"} 1"#));
+        assert!(metrics
+            .contains(r#"chutes_autopilot_mirror_tokens_total{kind="completion",rule="eval"} 7"#));
+        assert!(!metrics.contains(r#"rule="never""#));
+        upstream_handle.abort();
     }
 
     #[tokio::test]
-    async fn chat_completions_does_not_failover_on_upstream_401() {
-        let attempts: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
-        let upstream_attempts = attempts.clone();
-        let upstream_body = live_fixture("chat_completions_invalid_token_2026-02-18.json");
-        let upstream = Router::new().route(
-            "/v1/chat/completions",
-            post(move |Json(v): Json<Value>| {
-                let upstream_attempts = upstream_attempts.clone();
-                let body = upstream_body.clone();
-                async move {
-                    let model = v
-                        .get("model")
-                        .and_then(Value::as_str)
-                        .unwrap_or_default()
-                        .to_string();
-                    upstream_attempts.lock().unwrap().push(model);
-                    let mut resp = Response::new(Body::from(body));
-                    *resp.status_mut() = StatusCode::UNAUTHORIZED;
-                    resp
-                }
-            }),
-        );
-        let (base_url, upstream_handle) = spawn_upstream(upstream).await;
-
-        let state = AppState::new(test_config(base_url));
+    async fn backend_tier_is_reached_within_max_attempts() {
+        let (primary, primary_attempts) = test_upstream(StatusCode::SERVICE_UNAVAILABLE);
+        let (local, local_attempts) = test_upstream(StatusCode::OK);
+        let (primary_url, primary_handle) = spawn_upstream(primary).await;
+        let (local_url, local_handle) = spawn_upstream(local).await;
+
+        let state = AppState::new(AppConfig {
+            backends: vec![local_backend(local_url)],
+            max_upstream_attempts: 4,
+            ..test_config(primary_url)
+        });
+        seed_backends(&state).await;
         {
             let mut runtime = state.runtime.write().await;
-            runtime.candidates = vec![
-                RankedCandidate {
-                    name: "primary-TEE".to_string(),
-                    active_instance_count: 1,
-                    utilization_current: 0.1,
-                    rate_limit_ratio_5m: 0.0,
-                    score: 0.9,
-                },
-                RankedCandidate {
-                    name: "secondary-TEE".to_string(),
+            runtime.candidates = (0..10)
+                .map(|i| RankedCandidate {
+                    name: format!("chutes-{i}"),
                     active_instance_count: 1,
-                    utilization_current: 0.2,
-                    rate_limit_ratio_5m: 0.0,
-                    score: 0.8,
-                },
-            ];
-            runtime.snapshot_at = Some(Instant::now());
-            runtime.models_allowlist =
-                HashSet::from(["primary-TEE".to_string(), "secondary-TEE".to_string()]);
-            runtime.models_allowlist_at = Some(Instant::now());
+                    score: 1.0 - f64::from(i) / 100.0,
+                    ..Default::default()
+                })
+                .collect();
+            runtime.models_allowlist = runtime.candidates.iter().map(|c| c.name.clone()).collect();
         }
 
-        let app = app(state);
-        let resp = app
-            .oneshot(
-                Request::builder()
-                    .method("POST")
-                    .uri("/v1/chat/completions")
-                    .header("content-type", "application/json")
-                    .body(Body::from(r#"{"model":"primary-TEE,secondary-TEE"}"#))
-                    .unwrap(),
-            )
-            .await
-            .unwrap();
-
-        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        let resp = post_chat(app(state), r#"{"model":"chutesai/AutoPilot"}"#).await;
+        assert_eq!(resp.status(), StatusCode::OK);
         assert_eq!(
-            resp.headers().get("x-chutes-autopilot-selected").unwrap(),
-            &axum::http::header::HeaderValue::from_static("primary-TEE")
+            resp.headers().get("x-chutes-autopilot-backend").unwrap(),
+            "local"
         );
-        assert_eq!(attempts.lock().unwrap().len(), 1);
-
-        let body = resp.into_body().collect().await.unwrap().to_bytes();
-        let expected = live_fixture("chat_completions_invalid_token_2026-02-18.json");
-        assert_eq!(body.as_ref(), expected.as_slice());
+        assert_eq!(primary_attempts.lock().unwrap().len(), 3);
+        assert_eq!(local_attempts.lock().unwrap().len(), 1);
 
-        upstream_handle.abort();
+        primary_handle.abort();
+        local_handle.abort();
     }
 
-    #[tokio::test]
-    async fn chat_completions_preserves_html_body_on_429() {
-        let attempts: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
-        let upstream_attempts = attempts.clone();
-        let upstream_body = live_fixture("chat_completions_no_auth_429_2026-02-18.html");
-        let upstream = Router::new().route(
+    const CONTENT_CHUNK: &str = "data: {\"choices\":[{\"delta\":{\"content\":\"hi\"}}]}\n\n";
+    const USAGE_CHUNK: &str = "data: {\"choices\":[],\"usage\":{\"prompt_tokens\":3,\"completion_tokens\":2,\"total_tokens\":5}}\n\n";
+
+    /// Streams a content chunk, a usage chunk and `[DONE]`, recording each request's
+    /// `stream_options`.
+    fn usage_stream_upstream(stream_options: Arc<Mutex<Vec<Value>>>) -> Router {
+        Router::new().route(
             "/v1/chat/completions",
             post(move |Json(v): Json<Value>| {
-                let upstream_attempts = upstream_attempts.clone();
-                let body = upstream_body.clone();
+                let seen = stream_options.clone();
                 async move {
-                    let model = v
-                        .get("model")
-                        .and_then(Value::as_str)
-                        .unwrap_or_default()
-                        .to_string();
-                    upstream_attempts.lock().unwrap().push(model);
-                    let mut resp = Response::new(Body::from(body));
-                    *resp.status_mut() = StatusCode::TOO_MANY_REQUESTS;
-                    resp
+                    seen.lock()
+                        .unwrap()
+                        .push(v.get("stream_options").cloned().unwrap_or(Value::Null));
+                    (
+                        [("content-type", "text/event-stream")],
+                        format!("{CONTENT_CHUNK}{USAGE_CHUNK}data: [DONE]\n\n"),
+                    )
                 }
             }),
-        );
-        let (base_url, upstream_handle) = spawn_upstream(upstream).await;
+        )
+    }
 
-        let state = AppState::new(test_config(base_url));
-        {
-            let mut runtime = state.runtime.write().await;
-            runtime.candidates = vec![
-                RankedCandidate {
-                    name: "preferred-TEE".to_string(),
-                    active_instance_count: 1,
-                    utilization_current: 0.1,
-                    rate_limit_ratio_5m: 0.0,
-                    score: 0.9,
-                },
-                RankedCandidate {
-                    name: "backup-TEE".to_string(),
-                    active_instance_count: 1,
-                    utilization_current: 0.2,
-                    rate_limit_ratio_5m: 0.0,
-                    score: 0.8,
-                },
-            ];
-            runtime.snapshot_at = Some(Instant::now());
-            runtime.models_allowlist =
-                HashSet::from(["preferred-TEE".to_string(), "backup-TEE".to_string()]);
-            runtime.models_allowlist_at = Some(Instant::now());
-        }
+    #[tokio::test]
+    async fn budgeted_streams_request_usage_and_persist_spend_in_background() {
+        let stream_options: Arc<Mutex<Vec<Value>>> = Arc::default();
+        let upstream = usage_stream_upstream(stream_options.clone());
+        let (base_url, upstream_handle) = spawn_upstream(upstream).await;
+        let state_path =
+            std::env::temp_dir().join(format!("budget-state-{}.json", Uuid::new_v4().simple()));
 
-        let app = app(state);
-        let resp = app
-            .oneshot(
-                Request::builder()
-                    .method("POST")
-                    .uri("/v1/chat/completions")
-                    .header("content-type", "application/json")
-                    .body(Body::from(r#"{"model":"preferred-TEE,backup-TEE"}"#))
-                    .unwrap(),
-            )
-            .await
-            .unwrap();
+        let cfg = AppConfig {
+            client_budgets: vec![test_budget(Some(100), None)],
+            client_budget_state_path: Some(state_path.clone()),
+            ..test_config(base_url)
+        };
+        let resp = post_chat_with_headers(
+            app(AppState::new(cfg)),
+            r#"{"model":"direct-model","stream":true,"stream_options":{"foo":1}}"#,
+            &[("authorization", "Bearer budget-key")],
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = resp.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, format!("{CONTENT_CHUNK}data: [DONE]\n\n"));
 
-        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
         assert_eq!(
-            resp.headers().get("x-chutes-autopilot-selected").unwrap(),
-            &axum::http::header::HeaderValue::from_static("preferred-TEE")
+            *stream_options.lock().unwrap(),
+            vec![json!({"foo": 1, "include_usage": true})]
         );
-        assert_eq!(attempts.lock().unwrap().len(), 1);
 
-        let body = resp.into_body().collect().await.unwrap().to_bytes();
-        let expected = live_fixture("chat_completions_no_auth_429_2026-02-18.html");
-        assert_eq!(body.as_ref(), expected.as_slice());
+        let mut persisted = None;
+        for _ in 0..100 {
+            if let Ok(bytes) = std::fs::read(&state_path) {
+                persisted = serde_json::from_slice::<HashMap<String, BudgetSpend>>(&bytes).ok();
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(persisted.unwrap()["team-a"].tokens, 5);
 
+        let _ = std::fs::remove_file(&state_path);
         upstream_handle.abort();
     }
 
     #[tokio::test]
-    async fn chat_completions_rejects_body_over_max_request_bytes_with_openai_shape() {
-        let cfg = AppConfig {
-            max_request_bytes: 32,
-            ..Default::default()
-        };
+    async fn usd_budgets_skip_unpriced_candidates() {
+        let (upstream, attempts) = test_upstream(StatusCode::OK);
+        let (primary_url, primary_handle) = spawn_upstream(upstream).await;
+        let (local, local_attempts) = test_upstream(StatusCode::OK);
+        let (local_url, local_handle) = spawn_upstream(local).await;
+        let state = AppState::new(AppConfig {
+            backends: vec![local_backend(local_url)],
+            client_budgets: vec![test_budget(None, Some(1.0))],
+            ..test_config(primary_url)
+        });
+        seed_backends(&state).await;
+        {
+            let mut runtime = state.runtime.write().await;
+            for (model, pricing) in [
+                ("unpriced-TEE", None),
+                (
+                    "priced-TEE",
+                    Some(ModelPricing {
+                        prompt: 1.0,
+                        completion: 1.0,
+                    }),
+                ),
+            ] {
+                runtime.models_allowlist.insert(model.to_string());
+                runtime.models_catalog.insert(
+                    model.to_string(),
+                    OpenAiModelItem {
+                        id: model.to_string(),
+                        pricing,
+                        ..Default::default()
+                    },
+                );
+            }
+        }
 
-        let state = AppState::new(cfg);
-        let app = app(state);
+        let resp = post_chat_with_headers(
+            app(state.clone()),
+            r#"{"model":"local-model"}"#,
+            &[("authorization", "Bearer budget-key")],
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+        let parsed: OpenAiErrorResponse = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(parsed.error.code.as_deref(), Some("unpriced_model"));
+        assert!(local_attempts.lock().unwrap().is_empty());
 
-        let resp = app
-            .oneshot(
-                Request::builder()
-                    .method("POST")
-                    .uri("/v1/chat/completions")
-                    .header("content-type", "application/json")
-                    .body(Body::from(vec![b'a'; 128]))
-                    .unwrap(),
-            )
-            .await
-            .unwrap();
+        let resp = post_chat_with_headers(
+            app(state.clone()),
+            r#"{"model":"unpriced-TEE,priced-TEE"}"#,
+            &[("authorization", "Bearer budget-key")],
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(take_models(&attempts), vec!["priced-TEE"]);
 
-        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let resp = post_chat(app(state), r#"{"model":"local-model"}"#).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(take_models(&local_attempts), vec!["local-model"]);
 
-        let bytes = resp.into_body().collect().await.unwrap().to_bytes();
-        let parsed: OpenAiErrorResponse = serde_json::from_slice(&bytes).unwrap();
-        assert_eq!(parsed.error.error_type, "invalid_request_error");
-        assert_eq!(parsed.error.code.as_deref(), Some("request_too_large"));
-        assert_eq!(parsed.error.message, "request body too large");
+        primary_handle.abort();
+        local_handle.abort();
     }
 
-    proptest! {
-        #[test]
-        fn chat_completions_rejects_any_body_exceeding_limit(extra in 1usize..64) {
-            let status = tokio::runtime::Runtime::new()
-                .unwrap()
-                .block_on(async move {
-                    let cfg = AppConfig {
-                        max_request_bytes: 32,
-                        ..Default::default()
-                    };
+    #[tokio::test]
+    async fn budgeted_streams_keep_usage_the_client_asked_for() {
+        let stream_options: Arc<Mutex<Vec<Value>>> = Arc::default();
+        let (base_url, upstream_handle) =
+            spawn_upstream(usage_stream_upstream(stream_options.clone())).await;
+        let state = AppState::new(AppConfig {
+            client_budgets: vec![test_budget(Some(100), None)],
+            ..test_config(base_url)
+        });
 
-                    let app = app(AppState::new(cfg));
-                    let resp = app
-                        .oneshot(
-                            Request::builder()
-                                .method("POST")
-                                .uri("/v1/chat/completions")
-                                .header("content-type", "application/json")
-                                .body(Body::from(vec![b'a'; 32 + extra]))
-                                .unwrap(),
-                        )
-                        .await
-                        .unwrap();
-                    resp.status()
-                });
+        let resp = post_chat_with_headers(
+            app(state.clone()),
+            r#"{"model":"direct-model","stream":true,"stream_options":{"include_usage":true}}"#,
+            &[("authorization", "Bearer budget-key")],
+        )
+        .await;
+        let body = resp.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(
+            body,
+            format!("{CONTENT_CHUNK}{USAGE_CHUNK}data: [DONE]\n\n")
+        );
+        assert_eq!(state.budgets.spend.lock().unwrap()["team-a"].tokens, 5);
 
-            prop_assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
-        }
+        upstream_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn budgeted_streams_to_backends_without_stream_usage_are_sent_unchanged() {
+        let (primary_url, primary_handle) = spawn_upstream(test_upstream(StatusCode::OK).0).await;
+        let stream_options: Arc<Mutex<Vec<Value>>> = Arc::default();
+        let (local_url, local_handle) =
+            spawn_upstream(usage_stream_upstream(stream_options.clone())).await;
+        let state = AppState::new(AppConfig {
+            backends: vec![local_backend(local_url.clone())],
+            client_budgets: vec![test_budget(Some(100), None)],
+            ..test_config(primary_url.clone())
+        });
+        seed_backends(&state).await;
+
+        let resp = post_chat_with_headers(
+            app(state),
+            r#"{"model":"local-model","stream":true}"#,
+            &[("authorization", "Bearer budget-key")],
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let _ = resp.into_body().collect().await.unwrap();
+        assert_eq!(*stream_options.lock().unwrap(), vec![Value::Null]);
+
+        let state = AppState::new(AppConfig {
+            backends: vec![BackendConfig {
+                stream_usage: true,
+                ..local_backend(local_url)
+            }],
+            client_budgets: vec![test_budget(Some(100), None)],
+            ..test_config(primary_url)
+        });
+        seed_backends(&state).await;
+        let resp = post_chat_with_headers(
+            app(state),
+            r#"{"model":"local-model","stream":true}"#,
+            &[("authorization", "Bearer budget-key")],
+        )
+        .await;
+        let body = resp.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, format!("{CONTENT_CHUNK}data: [DONE]\n\n"));
+        assert_eq!(
+            stream_options.lock().unwrap()[1],
+            json!({"include_usage": true})
+        );
+
+        primary_handle.abort();
+        local_handle.abort();
     }
 }
@@ -1,3 +1,5 @@
+use std::collections::HashSet;
+use std::future::IntoFuture;
 use std::net::SocketAddr;
 use std::time::Duration;
 
@@ -13,6 +15,10 @@ fn env_usize(name: &str) -> Option<usize> {
     std::env::var(name).ok()?.trim().parse::<usize>().ok()
 }
 
+fn env_f64(name: &str) -> Option<f64> {
+    std::env::var(name).ok()?.trim().parse::<f64>().ok()
+}
+
 fn env_string(name: &str) -> Option<String> {
     std::env::var(name).ok().map(|v| v.trim().to_string())
 }
@@ -56,6 +62,108 @@ fn parse_trusted_proxy_cidrs(raw: &str) -> anyhow::Result<Vec<IpNet>> {
     Ok(out)
 }
 
+fn parse_retryable_statuses(name: &str, raw: &str) -> anyhow::Result<Vec<http::StatusCode>> {
+    let mut out = Vec::new();
+    for token in raw.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+        let status = token
+            .parse::<u16>()
+            .ok()
+            .and_then(|code| http::StatusCode::from_u16(code).ok())
+            .ok_or_else(|| anyhow::anyhow!("invalid status code in {name}: {token:?}"))?;
+        // 429 and other 4xx responses are caller-caused; retrying them would bypass rate limits.
+        if !status.is_server_error() {
+            return Err(anyhow::anyhow!(
+                "{name} may only contain 5xx statuses, got {token:?}"
+            ));
+        }
+        out.push(status);
+    }
+    Ok(out)
+}
+
+fn load_client_budgets(path: &str) -> anyhow::Result<Vec<chutes_autopilot::ClientBudget>> {
+    let bytes = std::fs::read(path)
+        .map_err(|e| anyhow::anyhow!("failed to read CLIENT_BUDGETS_PATH {path:?}: {e}"))?;
+    let budgets: Vec<chutes_autopilot::ClientBudget> = serde_json::from_slice(&bytes)
+        .map_err(|e| anyhow::anyhow!("invalid CLIENT_BUDGETS_PATH {path:?}: {e}"))?;
+    for budget in &budgets {
+        if budget.max_tokens.is_none() && budget.max_usd.is_none() {
+            return Err(anyhow::anyhow!(
+                "client budget {:?} must set max_tokens and/or max_usd",
+                budget.name
+            ));
+        }
+    }
+    Ok(budgets)
+}
+
+fn load_backends(path: &str) -> anyhow::Result<Vec<chutes_autopilot::BackendConfig>> {
+    let bytes = std::fs::read(path)
+        .map_err(|e| anyhow::anyhow!("failed to read BACKENDS_PATH {path:?}: {e}"))?;
+    let backends: Vec<chutes_autopilot::BackendConfig> = serde_json::from_slice(&bytes)
+        .map_err(|e| anyhow::anyhow!("invalid BACKENDS_PATH {path:?}: {e}"))?;
+    let mut names = vec![chutes_autopilot::PRIMARY_BACKEND];
+    for backend in &backends {
+        if backend.name.trim().is_empty() || backend.base_url.trim().is_empty() {
+            return Err(anyhow::anyhow!(
+                "backend {:?} must set a non-empty name and base_url",
+                backend.name
+            ));
+        }
+        if names.contains(&backend.name.as_str()) {
+            return Err(anyhow::anyhow!(
+                "duplicate backend name {:?} in BACKENDS_PATH",
+                backend.name
+            ));
+        }
+        names.push(&backend.name);
+    }
+    Ok(backends)
+}
+
+fn load_model_redirects(path: &str) -> anyhow::Result<Vec<chutes_autopilot::ModelRedirect>> {
+    let bytes = std::fs::read(path)
+        .map_err(|e| anyhow::anyhow!("failed to read MODEL_REDIRECTS_PATH {path:?}: {e}"))?;
+    let mut redirects: Vec<chutes_autopilot::ModelRedirect> = serde_json::from_slice(&bytes)
+        .map_err(|e| anyhow::anyhow!("invalid MODEL_REDIRECTS_PATH {path:?}: {e}"))?;
+    chutes_autopilot::validate_model_redirects(&mut redirects, std::time::SystemTime::now())
+        .map_err(|e| anyhow::anyhow!("invalid MODEL_REDIRECTS_PATH {path:?}: {e}"))?;
+    Ok(redirects)
+}
+
+fn load_mirror_rules(path: &str) -> anyhow::Result<Vec<chutes_autopilot::MirrorRule>> {
+    let bytes = std::fs::read(path)
+        .map_err(|e| anyhow::anyhow!("failed to read MIRROR_RULES_PATH {path:?}: {e}"))?;
+    let rules: Vec<chutes_autopilot::MirrorRule> = serde_json::from_slice(&bytes)
+        .map_err(|e| anyhow::anyhow!("invalid MIRROR_RULES_PATH {path:?}: {e}"))?;
+    let mut names = HashSet::new();
+    for rule in &rules {
+        if rule.name.trim().is_empty()
+            || rule.source.trim().is_empty()
+            || rule.shadow.trim().is_empty()
+            || rule.api_key.trim().is_empty()
+        {
+            return Err(anyhow::anyhow!(
+                "mirror rule {:?} must set a non-empty name, source, shadow and api_key",
+                rule.name
+            ));
+        }
+        if !(0.0..=100.0).contains(&rule.sample_percent) {
+            return Err(anyhow::anyhow!(
+                "mirror rule {:?} sample_percent must be between 0 and 100",
+                rule.name
+            ));
+        }
+        if !names.insert(rule.name.as_str()) {
+            return Err(anyhow::anyhow!(
+                "duplicate mirror rule name {:?} in MIRROR_RULES_PATH",
+                rule.name
+            ));
+        }
+    }
+    Ok(rules)
+}
+
 fn config_from_env() -> anyhow::Result<chutes_autopilot::AppConfig> {
     let mut cfg = chutes_autopilot::AppConfig::default();
 
@@ -74,6 +182,24 @@ fn config_from_env() -> anyhow::Result<chutes_autopilot::AppConfig> {
     if let Some(url) = env_string("BACKEND_BASE_URL") {
         cfg.backend_base_url = url;
     }
+    if let Some(path) = env_string("BACKENDS_PATH").filter(|p| !p.is_empty()) {
+        cfg.backends = load_backends(&path)?;
+    }
+    if let Some(path) = env_string("MODEL_REDIRECTS_PATH").filter(|p| !p.is_empty()) {
+        cfg.model_redirects = load_model_redirects(&path)?;
+    }
+    if let Some(fold) = env_bool("CASE_INSENSITIVE_MODELS")? {
+        cfg.case_insensitive_models = fold;
+    }
+    if let Some(path) = env_string("MIRROR_RULES_PATH").filter(|p| !p.is_empty()) {
+        cfg.mirror_rules = load_mirror_rules(&path)?;
+    }
+    if let Some(path) = env_string("MIRROR_LOG_PATH").filter(|p| !p.is_empty()) {
+        cfg.mirror_log_path = Some(path.into());
+    }
+    if let Some(ms) = env_u64("MIRROR_TIMEOUT_MS") {
+        cfg.mirror_timeout = Duration::from_millis(ms);
+    }
     if let Some(url) = env_string("MODELS_URL") {
         cfg.models_url = url;
     }
@@ -89,6 +215,9 @@ fn config_from_env() -> anyhow::Result<chutes_autopilot::AppConfig> {
     if let Some(ms) = env_u64("CONTROL_PLANE_TIMEOUT_MS") {
         cfg.control_plane_timeout = Duration::from_millis(ms);
     }
+    if let Some(ms) = env_u64("CONTROL_PLANE_MAX_BACKOFF_MS") {
+        cfg.control_plane_max_backoff = Duration::from_millis(ms);
+    }
     if let Some(ms) = env_u64("UPSTREAM_CONNECT_TIMEOUT_MS") {
         cfg.upstream_connect_timeout = Duration::from_millis(ms);
     }
@@ -114,6 +243,106 @@ fn config_from_env() -> anyhow::Result<chutes_autopilot::AppConfig> {
         }
     }
 
+    if let Some(margin) = env_f64("RANKING_LEADER_SCORE_MARGIN") {
+        cfg.leader_score_margin = margin.max(0.0);
+    }
+    if let Some(ms) = env_u64("RANKING_LEADER_MIN_DWELL_MS") {
+        cfg.leader_min_dwell = Duration::from_millis(ms);
+    }
+
+    if let Some(raw) = env_string("UTILIZATION_SCHEMA_MODE").filter(|v| !v.is_empty()) {
+        cfg.utilization_schema_mode = match raw.to_ascii_lowercase().as_str() {
+            "lenient" => chutes_autopilot::SchemaMode::Lenient,
+            "strict" => chutes_autopilot::SchemaMode::Strict,
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "invalid UTILIZATION_SCHEMA_MODE: {raw:?} (expected lenient or strict)"
+                ));
+            }
+        };
+    }
+    if let Some(pct) = env_f64("UTILIZATION_SCHEMA_MAX_DRIFT_PCT") {
+        cfg.utilization_max_drift_pct = pct.clamp(0.0, 100.0);
+    }
+
+    if let Some(raw) = env_string("AUTOPILOT_SELECTION").filter(|v| !v.is_empty()) {
+        cfg.selection_mode = match raw.to_ascii_lowercase().as_str() {
+            "top" => chutes_autopilot::SelectionMode::Top,
+            "weighted" => chutes_autopilot::SelectionMode::Weighted,
+            "p2c" => chutes_autopilot::SelectionMode::PowerOfTwo,
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "invalid AUTOPILOT_SELECTION: {raw:?} (expected top, weighted, or p2c)"
+                ));
+            }
+        };
+    }
+    if let Some(k) = env_usize("AUTOPILOT_SELECTION_TOP_K") {
+        cfg.selection_top_k = k;
+    }
+
+    if let Some(raw) = env_string("RETRYABLE_STATUSES_ALIAS") {
+        cfg.retryable_statuses.alias = parse_retryable_statuses("RETRYABLE_STATUSES_ALIAS", &raw)?;
+    }
+    if let Some(raw) = env_string("RETRYABLE_STATUSES_LIST") {
+        cfg.retryable_statuses.list = parse_retryable_statuses("RETRYABLE_STATUSES_LIST", &raw)?;
+    }
+    if let Some(raw) = env_string("RETRYABLE_STATUSES_DIRECT") {
+        cfg.retryable_statuses.direct =
+            parse_retryable_statuses("RETRYABLE_STATUSES_DIRECT", &raw)?;
+    }
+    if let Some(secs) = env_u64("RETRY_AFTER_MAX_COOLDOWN_SECS") {
+        cfg.retry_after_max_cooldown = Duration::from_secs(secs);
+    }
+
+    if let Some(enabled) = env_bool("NORMALIZE_UPSTREAM_ERRORS")? {
+        cfg.normalize_upstream_errors = enabled;
+    }
+
+    if let Some(enabled) = env_bool("HEDGE_ENABLED")? {
+        cfg.hedge_enabled = enabled;
+    }
+    if let Some(ms) = env_u64("HEDGE_DELAY_MS") {
+        cfg.hedge_delay = Duration::from_millis(ms);
+    }
+
+    if let Some(value) = env_usize("MAX_UPSTREAM_ATTEMPTS") {
+        cfg.max_upstream_attempts = value;
+    }
+    if let Some(ms) = env_u64("UPSTREAM_RETRY_BUDGET_MS") {
+        cfg.upstream_retry_budget = Duration::from_millis(ms);
+    }
+
+    if let Some(path) = env_string("CLIENT_BUDGETS_PATH").filter(|p| !p.is_empty()) {
+        cfg.client_budgets = load_client_budgets(&path)?;
+    }
+    if let Some(path) = env_string("CLIENT_BUDGET_STATE_PATH").filter(|p| !p.is_empty()) {
+        cfg.client_budget_state_path = Some(path.into());
+    }
+
+    if let Some(enabled) = env_bool("ATTESTATION_ENABLED")? {
+        cfg.attestation_enabled = enabled;
+    }
+    if let Some(url) = env_string("ATTESTATION_EVIDENCE_URL").filter(|u| !u.is_empty()) {
+        cfg.attestation_evidence_url = url;
+    }
+    if let Some(ms) = env_u64("ATTESTATION_REFRESH_MS") {
+        cfg.attestation_refresh = Duration::from_millis(ms);
+    }
+    if let Some(required) = env_bool("REQUIRE_ATTESTED")? {
+        cfg.require_attested = required;
+    }
+    if let Some(sanitize) = env_bool("SANITIZE_SAMPLING_PARAMS")? {
+        cfg.sanitize_sampling_params = sanitize;
+    }
+    if let Some(clamp) = env_bool("CLAMP_MAX_TOKENS")? {
+        cfg.clamp_max_tokens = clamp;
+    }
+
+    if let Some(token) = env_string("ADMIN_TOKEN").filter(|t| !t.is_empty()) {
+        cfg.admin_token = Some(chutes_autopilot::AdminToken(token));
+    }
+
     Ok(cfg)
 }
 
@@ -127,17 +356,39 @@ async fn main() -> anyhow::Result<()> {
         .unwrap_or_else(|_| "0.0.0.0:8080".to_string())
         .parse()?;
 
-    let state = chutes_autopilot::AppState::new(config_from_env()?);
+    let admin_listen: SocketAddr = std::env::var("ADMIN_LISTEN_ADDR")
+        .unwrap_or_else(|_| "127.0.0.1:8081".to_string())
+        .parse()?;
+
+    let cfg = config_from_env()?;
+    // Operator endpoints get their own listener, and only when they can be used at all.
+    let admin_listener = if cfg.admin_token.is_some() {
+        Some(TcpListener::bind(admin_listen).await?)
+    } else {
+        None
+    };
+
+    let state = chutes_autopilot::AppState::new(cfg);
     chutes_autopilot::spawn_control_plane_refresh(state.clone());
-    let app = chutes_autopilot::app(state);
 
+    let admin_app = chutes_autopilot::admin_app(state.clone());
+    let admin = async move {
+        let Some(listener) = admin_listener else {
+            return Ok(());
+        };
+        tracing::info!(listen = %admin_listen, "admin API listening");
+        axum::serve(listener, admin_app.into_make_service()).await
+    };
+
+    let app = chutes_autopilot::app(state);
     let listener = TcpListener::bind(listen).await?;
     tracing::info!(%listen, "listening");
-    axum::serve(
+    let public = axum::serve(
         listener,
         app.into_make_service_with_connect_info::<SocketAddr>(),
-    )
-    .await?;
+    );
+
+    tokio::try_join!(public.into_future(), admin)?;
 
     Ok(())
 }